@@ -11,10 +11,10 @@ async fn main() {
         .await
         .unwrap();
 
-    let sub = Subscribe {
-        product_ids: vec!["BTC-USD".into()],
-        channels: vec![Channel::Name(ChannelType::Full)],
-    };
+    let sub = Subscribe::new(
+        vec!["BTC-USD".into()],
+        vec![Channel::Name(ChannelType::Full)],
+    );
 
     stream.subscribe(&sub).await.unwrap();
 
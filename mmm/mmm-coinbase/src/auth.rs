@@ -0,0 +1,133 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt;
+use thiserror::Error;
+
+pub const KEY_ENV: &str = "CB_ACCESS_KEY";
+pub const SECRET_ENV: &str = "CB_ACCESS_SECRET";
+pub const PASSPHRASE_ENV: &str = "CB_ACCESS_PASSPHRASE";
+
+// NOTE: websocket subscriptions are signed as if they were `GET /users/self/verify`.
+const VERIFY_METHOD: &str = "GET";
+const VERIFY_PATH: &str = "/users/self/verify";
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("environment variable {0} is not set.")]
+    MissingEnv(&'static str),
+    #[error("api secret is not valid base64. ({0:?})")]
+    InvalidSecret(#[source] base64::DecodeError),
+}
+
+/// Fields added to a message to authenticate it.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone)]
+pub struct Auth {
+    pub signature: String,
+    pub key: String,
+    pub passphrase: String,
+    pub timestamp: String,
+}
+
+// subscriptions are logged, so the signature and passphrase are left out.
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Auth")
+            .field("key", &self.key)
+            .field("timestamp", &self.timestamp)
+            .finish_non_exhaustive()
+    }
+}
+
+/// API key, secret and passphrase issued by coinbase.
+#[derive(Clone)]
+pub struct Credentials {
+    key: String,
+    secret: Vec<u8>,
+    passphrase: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    /// `secret` is the base64 encoded secret as issued.
+    pub fn new(key: &str, secret: &str, passphrase: &str) -> Result<Self, AuthError> {
+        Ok(Self {
+            key: key.to_string(),
+            secret: base64::decode(secret).map_err(AuthError::InvalidSecret)?,
+            passphrase: passphrase.to_string(),
+        })
+    }
+
+    /// Reads credentials from `CB_ACCESS_KEY`, `CB_ACCESS_SECRET` and `CB_ACCESS_PASSPHRASE`.
+    pub fn from_env() -> Result<Self, AuthError> {
+        let var = |name| std::env::var(name).map_err(|_| AuthError::MissingEnv(name));
+        Self::new(&var(KEY_ENV)?, &var(SECRET_ENV)?, &var(PASSPHRASE_ENV)?)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    /// base64(HMAC-SHA256(secret, timestamp + method + request_path + body))
+    pub fn sign(&self, timestamp: &str, method: &str, request_path: &str, body: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC can take key of any size");
+        mac.update(timestamp.as_bytes());
+        mac.update(method.as_bytes());
+        mac.update(request_path.as_bytes());
+        mac.update(body.as_bytes());
+        base64::encode(mac.finalize().into_bytes())
+    }
+
+    /// Signs a websocket subscription at the given unix timestamp.
+    pub fn auth_at(&self, timestamp: i64) -> Auth {
+        let timestamp = timestamp.to_string();
+        Auth {
+            signature: self.sign(&timestamp, VERIFY_METHOD, VERIFY_PATH, ""),
+            key: self.key.clone(),
+            passphrase: self.passphrase.clone(),
+            timestamp,
+        }
+    }
+
+    /// Signs a websocket subscription now.
+    /// NOTE: coinbase rejects signatures older than 30 seconds, so sign right before sending.
+    pub fn auth(&self) -> Auth {
+        self.auth_at(chrono::Utc::now().timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_matches_reference() {
+        // NOTE: reference computed with python `hmac` + `hashlib.sha256`.
+        let credentials = Credentials::new("key", "c2VjcmV0", "pass").unwrap();
+        let auth = credentials.auth_at(1600000000);
+        assert_eq!(auth.timestamp, "1600000000");
+        assert_eq!(
+            auth.signature,
+            credentials.sign("1600000000", "GET", "/users/self/verify", "")
+        );
+        assert_eq!(
+            auth.signature,
+            "hyKv1TAlU08qsWI+EV8fA85pkTzE8WoSqxRp/4MtO0c="
+        );
+        assert!(Credentials::new("key", "not base64!", "pass").is_err());
+        let debug = format!("{:?}", auth);
+        assert!(!debug.contains("pass") && !debug.contains(&auth.signature));
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{NaiveDate, Utc};
+use mmm_coinbase::{
+    convert::{convert_files, Scales},
+    high_availability::highly_available_receive,
+    recorder::{upload_date, Recorder, RecorderConfig},
+    rest::RestClient,
+    sink::{LocalSink, S3Sink, Sink},
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+use structopt::StructOpt;
+use url::Url;
+
+#[derive(StructOpt)]
+struct SinkOpt {
+    /// copy finished recordings under this directory
+    #[structopt(long, parse(from_os_str))]
+    local: Option<PathBuf>,
+    /// upload finished recordings to this bucket, using AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY
+    #[structopt(long)]
+    bucket: Option<String>,
+    /// S3 compatible endpoint
+    #[structopt(long, default_value = "https://s3.amazonaws.com")]
+    s3_endpoint: Url,
+    #[structopt(long, default_value = "us-east-1")]
+    s3_region: String,
+}
+
+impl SinkOpt {
+    fn build(self) -> anyhow::Result<Option<Arc<dyn Sink>>> {
+        Ok(match (self.local, self.bucket) {
+            (Some(_), Some(_)) => anyhow::bail!("choose either --local or --bucket."),
+            (Some(root), None) => Some(Arc::new(LocalSink::new(root))),
+            (None, Some(bucket)) => Some(Arc::new(S3Sink::from_env(
+                self.s3_endpoint,
+                &bucket,
+                &self.s3_region,
+            )?)),
+            (None, None) => None,
+        })
+    }
+}
+
+#[derive(StructOpt)]
+enum Opt {
+    /// Record the full channel of every product.
+    Record {
+        /// destination path to save data
+        #[structopt(parse(from_os_str))]
+        dest: PathBuf,
+        /// coinbase websocket endpoint
+        #[structopt(default_value = "wss://ws-feed.pro.coinbase.com")]
+        endpoint: Url,
+        /// number of connections per interface.
+        #[structopt(default_value = "6", short, long)]
+        redundancy: usize,
+        /// network interfaces to use
+        #[structopt(short, long)]
+        interfaces: Option<Vec<IpAddr>>,
+        /// compression level
+        #[structopt(short = "z", long = "compression", default_value = "10")]
+        compression_level: u32,
+        #[structopt(flatten)]
+        sink: SinkOpt,
+    },
+    /// Upload recordings of a date left behind, yesterday by default.
+    Upload {
+        #[structopt(parse(from_os_str))]
+        dest: PathBuf,
+        #[structopt(long)]
+        date: Option<NaiveDate>,
+        #[structopt(flatten)]
+        sink: SinkOpt,
+    },
+    /// Convert captures of a product into the 9-field `.bin.zst` layout.
+    Convert {
+        /// `full-{sequence}.json.zst` files of a product in order
+        #[structopt(name = "FILE", parse(from_os_str))]
+        files: Vec<PathBuf>,
+        #[structopt(short, long)]
+        out_dir: PathBuf,
+        #[structopt(short, long)]
+        product_id: String,
+        /// date of the captures, time is nanoseconds since its midnight (UTC)
+        #[structopt(long)]
+        date: NaiveDate,
+        /// decimal places of prices, `quote_increment` of the product by default
+        #[structopt(long)]
+        price_scale: Option<u32>,
+        /// decimal places of sizes, `base_increment` of the product by default
+        #[structopt(long)]
+        size_scale: Option<u32>,
+    },
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate = signal(SignalKind::terminate()).unwrap();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    match Opt::from_args() {
+        Opt::Record {
+            dest,
+            endpoint,
+            redundancy,
+            interfaces,
+            compression_level,
+            sink,
+        } => {
+            let sink = sink.build()?;
+            let interfaces =
+                interfaces.map(|ifs| ifs.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect());
+            let (cb_tx, cb_rx) = highly_available_receive(endpoint, redundancy, interfaces).await;
+
+            let mut config = RecorderConfig::new(dest);
+            config.compression_level = compression_level;
+            let mut recorder = Recorder::new(config, RestClient::new());
+            if let Some(sink) = sink {
+                recorder = recorder.with_sink(sink);
+            }
+            recorder.run(cb_tx, cb_rx, shutdown_signal()).await?;
+        }
+        Opt::Upload { dest, date, sink } => {
+            let sink = match sink.build()? {
+                Some(sink) => sink,
+                None => anyhow::bail!("either --local or --bucket is required."),
+            };
+            let date = date.unwrap_or_else(|| Utc::now().date_naive().pred_opt().unwrap());
+            upload_date(sink.as_ref(), &dest, date).await?;
+        }
+        Opt::Convert {
+            files,
+            out_dir,
+            product_id,
+            date,
+            price_scale,
+            size_scale,
+        } => {
+            let scales = match (price_scale, size_scale) {
+                (Some(price), Some(size)) => Scales { price, size },
+                _ => {
+                    let product = RestClient::new().product(&product_id).await?;
+                    let scales = Scales::from_product(&product);
+                    Scales {
+                        price: price_scale.unwrap_or(scales.price),
+                        size: size_scale.unwrap_or(scales.size),
+                    }
+                }
+            };
+            let out_path = tokio::task::spawn_blocking(move || {
+                convert_files(&files, &out_dir, &product_id, scales, date)
+            })
+            .await??;
+            log::info!("{:?} written with {:?}", out_path, scales);
+        }
+    }
+    Ok(())
+}
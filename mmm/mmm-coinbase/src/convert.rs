@@ -0,0 +1,373 @@
+use crate::decimal::Decimal;
+use crate::rest::Product;
+use crate::websocket::{Decrement, Full};
+use chrono::{DateTime, NaiveDate, Utc};
+use mmm_us::{encode_side, util::Time};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Same layout as the NASDAQ/ARCA preprocessed `.bin.zst`.
+/// [type, time, reference, shares, price, side, orig_shares, extra, next_index]
+pub const NUM_FIELDS: usize = 9;
+
+const ADD: u64 = 0;
+const DELETE: u64 = 1;
+const CANCEL: u64 = 2;
+const EXECUTE: u64 = 4;
+
+#[derive(Error, Debug)]
+pub enum ConvertError {
+    #[error("io error. ({0:?})")]
+    Io(#[from] std::io::Error),
+    #[error("invalid capture line. ({0:?})")]
+    InvalidLine(#[from] serde_json::Error),
+    #[error("{0:} can not be scaled to {1:} decimal places.")]
+    Scale(Decimal, u32),
+}
+
+/// Decimal places of integer units for prices and sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scales {
+    pub price: u32,
+    pub size: u32,
+}
+
+impl Scales {
+    /// Scales of `quote_increment` and `base_increment`.
+    pub fn from_product(product: &Product) -> Self {
+        Self {
+            price: product.quote_increment.normalize().scale(),
+            size: product.base_increment.normalize().scale(),
+        }
+    }
+}
+
+fn scale(value: &Decimal, scale: u32) -> Result<u64, ConvertError> {
+    value
+        .rescale(scale)
+        .and_then(|scaled| u64::try_from(scaled.mantissa()).ok())
+        .ok_or(ConvertError::Scale(*value, scale))
+}
+
+struct OrderStatus {
+    reference: u64,
+    price: u64,
+    side: u64,
+    shares: u64,
+    index: usize,
+}
+
+/// Converts a `Full` stream of a product into the 9-field layout.
+/// Only orders resting on the book are encoded: `Open` is an add, `Match` an execution of the maker,
+/// `Change` a cancel and `Done` a delete of what remains. Taker and market orders never rest, so
+/// their trades are seen through the maker side only.
+/// Order ids are interned to references from 1 in order of `Open`.
+/// Time is nanoseconds since midnight (UTC) of `date`, not New York as in ITCH,
+/// so rows are decoded with `Message::decode_since(values, converter.midnight())`.
+/// NOTE: orders resting before the capture starts are unknown and their messages are skipped.
+pub struct Converter {
+    scales: Scales,
+    midnight: DateTime<Utc>,
+    orders: HashMap<Uuid, OrderStatus>,
+    references: Vec<Uuid>,
+    messages: Vec<[u64; NUM_FIELDS]>,
+    skipped: usize,
+}
+
+impl Converter {
+    pub fn new(scales: Scales, date: NaiveDate) -> Self {
+        Self {
+            scales,
+            midnight: DateTime::from_naive_utc_and_offset(date.and_hms_opt(0, 0, 0).unwrap(), Utc),
+            orders: HashMap::new(),
+            references: Vec::new(),
+            messages: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Midnight (UTC) the times of the messages count from.
+    pub fn midnight(&self) -> Time {
+        self.midnight.into()
+    }
+
+    pub fn messages(&self) -> &[[u64; NUM_FIELDS]] {
+        &self.messages
+    }
+
+    /// Order id of each reference, i.e. `references()[reference - 1]`.
+    pub fn references(&self) -> &[Uuid] {
+        &self.references
+    }
+
+    /// Number of messages about unknown orders.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    fn time(&self, time: &DateTime<Utc>) -> u64 {
+        (*time - self.midnight)
+            .num_nanoseconds()
+            .and_then(|ns| u64::try_from(ns).ok())
+            .unwrap_or_default()
+    }
+
+    /// Links the previous message of the order to the next one and returns the order.
+    fn touch(&mut self, order_id: &Uuid) -> Option<&mut OrderStatus> {
+        let current_index = self.messages.len();
+        match self.orders.get_mut(order_id) {
+            Some(status) => {
+                self.messages[status.index][NUM_FIELDS - 1] = current_index as u64;
+                status.index = current_index;
+                Some(status)
+            }
+            None => {
+                self.skipped += 1;
+                None
+            }
+        }
+    }
+
+    pub fn push(&mut self, full: &Full) -> Result<(), ConvertError> {
+        let time = self.time(full.time());
+        let encoded = match full {
+            Full::Received(_) => None,
+            Full::Open(open) => {
+                let status = OrderStatus {
+                    reference: self.references.len() as u64 + 1,
+                    price: scale(&open.quote.price, self.scales.price)?,
+                    side: encode_side(open.side.into()),
+                    shares: scale(&open.quote.remaining_size, self.scales.size)?,
+                    index: self.messages.len(),
+                };
+                self.references.push(open.order_id);
+                let encoded = [
+                    ADD,
+                    time,
+                    status.reference,
+                    status.shares,
+                    status.price,
+                    status.side,
+                    0,
+                    0,
+                    0,
+                ];
+                self.orders.insert(open.order_id, status);
+                Some(encoded)
+            }
+            Full::Match(m) => {
+                let executed = scale(&m.size, self.scales.size)?;
+                self.touch(&m.maker_order_id).map(|status| {
+                    let orig_shares = status.shares;
+                    status.shares = status.shares.saturating_sub(executed);
+                    [
+                        EXECUTE,
+                        time,
+                        status.reference,
+                        executed,
+                        status.price,
+                        status.side,
+                        orig_shares,
+                        0,
+                        0,
+                    ]
+                })
+            }
+            Full::Decremented(decremented) => match &decremented.decrement {
+                Decrement::Limit {
+                    old_size, new_size, ..
+                } if old_size > new_size => {
+                    let cancelled = scale(&(*old_size - *new_size), self.scales.size)?;
+                    self.touch(&decremented.order_id).map(|status| {
+                        let orig_shares = status.shares;
+                        status.shares = status.shares.saturating_sub(cancelled);
+                        [
+                            CANCEL,
+                            time,
+                            status.reference,
+                            cancelled,
+                            status.price,
+                            status.side,
+                            orig_shares,
+                            0,
+                            0,
+                        ]
+                    })
+                }
+                _ => None,
+            },
+            Full::Done(done) => match self.orders.get(&done.order_id).map(|s| s.shares) {
+                // NOTE: an order filled by executions leaves the book without a message as in ITCH.
+                Some(0) => {
+                    self.orders.remove(&done.order_id);
+                    None
+                }
+                Some(_) => {
+                    let status = self.touch(&done.order_id).unwrap();
+                    let encoded = [
+                        DELETE,
+                        time,
+                        status.reference,
+                        status.shares,
+                        status.price,
+                        status.side,
+                        status.shares,
+                        0,
+                        0,
+                    ];
+                    self.orders.remove(&done.order_id);
+                    Some(encoded)
+                }
+                None => {
+                    self.skipped += 1;
+                    None
+                }
+            },
+        };
+        if let Some(encoded) = encoded {
+            self.messages.push(encoded);
+        }
+        Ok(())
+    }
+
+    /// Writes `{product_id}.bin.zst` and `{product_id}_references.json.zst` into `out_dir`.
+    pub fn dump(&self, out_dir: &Path, product_id: &str) -> Result<(), ConvertError> {
+        std::fs::create_dir_all(out_dir)?;
+        let serialized = self
+            .messages
+            .iter()
+            .flatten()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect::<Vec<u8>>();
+        dump(
+            &out_dir.join(format!("{}.bin.zst", product_id)),
+            &serialized,
+        )?;
+        dump(
+            &out_dir.join(format!("{}_references.json.zst", product_id)),
+            &serde_json::to_vec(&self.references)?,
+        )
+    }
+}
+
+// same frame as the NASDAQ/ARCA `dump`, with the content size that python `zstandard.decompress` needs.
+fn dump(out_path: &Path, serialized: &[u8]) -> Result<(), ConvertError> {
+    let compressed = zstd::block::compress(serialized, 0)?;
+    std::fs::File::create(out_path)?.write_all(&compressed)?;
+    Ok(())
+}
+
+/// Converts captures of a product written by `recorder::Recorder`, i.e. `full-{sequence}.json.zst`
+/// files of (machine time, message) lines, in the given order into `out_dir`.
+/// Returns the path of the written `.bin.zst`.
+pub fn convert_files(
+    paths: &[PathBuf],
+    out_dir: &Path,
+    product_id: &str,
+    scales: Scales,
+    date: NaiveDate,
+) -> Result<PathBuf, ConvertError> {
+    let mut converter = Converter::new(scales, date);
+    for path in paths {
+        let reader = BufReader::new(zstd::Decoder::new(std::fs::File::open(path)?)?);
+        for line in reader.lines() {
+            let (_, full): (DateTime<Utc>, Full) = serde_json::from_str(&line?)?;
+            converter.push(&full)?;
+        }
+    }
+    if converter.skipped() > 0 {
+        log::info!(
+            "{} messages of unknown orders skipped in {}.",
+            converter.skipped(),
+            product_id
+        );
+    }
+    converter.dump(out_dir, product_id)?;
+    Ok(out_dir.join(format!("{}.bin.zst", product_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmm_nasdaq::book::{Message, NasdaqOrderBook};
+    use mmm_nasdaq::data::load;
+
+    #[test]
+    fn convert_and_replay() {
+        let msgs = [
+            r#"{"type":"received","side":"sell","product_id":"BTC-USD","time":"2021-08-25T00:00:01Z","sequence":1,"order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","order_type":"limit","size":"1.5","price":"100.01"}"#,
+            r#"{"type":"open","side":"sell","product_id":"BTC-USD","time":"2021-08-25T00:00:01Z","sequence":2,"price":"100.01","order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","remaining_size":"1.5"}"#,
+            r#"{"type":"open","side":"buy","product_id":"BTC-USD","time":"2021-08-25T00:00:02Z","sequence":3,"price":"99.5","order_id":"61b0a035-f130-439c-8b8e-554f102d572d","remaining_size":"2"}"#,
+            r#"{"type":"match","trade_id":7,"maker_order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","taker_order_id":"b118f276-be5c-434b-870f-78fb8a30b553","side":"sell","size":"0.5","price":"100.01","product_id":"BTC-USD","sequence":4,"time":"2021-08-25T00:00:03Z"}"#,
+            r#"{"type":"change","side":"buy","product_id":"BTC-USD","time":"2021-08-25T00:00:04Z","sequence":5,"order_id":"61b0a035-f130-439c-8b8e-554f102d572d","price":"99.5","old_size":"2","new_size":"1.25"}"#,
+            r#"{"type":"done","side":"buy","product_id":"BTC-USD","time":"2021-08-25T00:00:05Z","sequence":6,"order_id":"61b0a035-f130-439c-8b8e-554f102d572d","reason":"canceled","price":"99.5","remaining_size":"1.25"}"#,
+            r#"{"type":"match","trade_id":8,"maker_order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","taker_order_id":"b118f276-be5c-434b-870f-78fb8a30b553","side":"sell","size":"1","price":"100.01","product_id":"BTC-USD","sequence":7,"time":"2021-08-25T00:00:06Z"}"#,
+            r#"{"type":"done","side":"sell","product_id":"BTC-USD","time":"2021-08-25T00:00:06Z","sequence":8,"order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","reason":"filled","price":"100.01","remaining_size":"0"}"#,
+            r#"{"type":"done","side":"buy","product_id":"BTC-USD","time":"2021-08-25T00:00:07Z","sequence":9,"order_id":"da863862-25f4-4868-ac41-005d11ab0a5f","reason":"canceled","price":"1","remaining_size":"1"}"#,
+        ];
+        let scales = Scales { price: 2, size: 8 };
+        let mut converter = Converter::new(scales, NaiveDate::from_ymd_opt(2021, 8, 25).unwrap());
+        for msg in msgs {
+            converter
+                .push(&serde_json::from_str::<Full>(msg).unwrap())
+                .unwrap();
+        }
+
+        let sec = 1_000_000_000;
+        assert_eq!(
+            converter.messages(),
+            &[
+                [ADD, sec, 1, 150_000_000, 10001, 1, 0, 0, 2],
+                [ADD, 2 * sec, 2, 200_000_000, 9950, 2, 0, 0, 3],
+                [EXECUTE, 3 * sec, 1, 50_000_000, 10001, 1, 150_000_000, 0, 5],
+                [CANCEL, 4 * sec, 2, 75_000_000, 9950, 2, 200_000_000, 0, 4],
+                [DELETE, 5 * sec, 2, 125_000_000, 9950, 2, 125_000_000, 0, 0],
+                [
+                    EXECUTE,
+                    6 * sec,
+                    1,
+                    100_000_000,
+                    10001,
+                    1,
+                    100_000_000,
+                    0,
+                    0
+                ],
+            ]
+        );
+        assert_eq!(converter.skipped(), 1);
+
+        let out_dir = std::env::temp_dir().join(format!("mmm-convert-{}", std::process::id()));
+        converter.dump(&out_dir, "BTC-USD").unwrap();
+        let compressed = std::fs::read(out_dir.join("BTC-USD.bin.zst")).unwrap();
+        assert_eq!(
+            zstd_safe::get_frame_content_size(&compressed),
+            (converter.messages().len() * NUM_FIELDS * 8) as u64
+        );
+        let loaded = load(out_dir.join("BTC-USD.bin.zst"), NUM_FIELDS);
+        assert_eq!(
+            loaded,
+            converter
+                .messages()
+                .iter()
+                .map(|m| m.to_vec())
+                .collect::<Vec<_>>()
+        );
+
+        let mut book = NasdaqOrderBook::new(true);
+        let messages = loaded
+            .iter()
+            .map(|values| Message::decode_since(values, converter.midnight()))
+            .collect::<Vec<_>>();
+        let first: DateTime<Utc> = "2021-08-25T00:00:01Z".parse().unwrap();
+        assert_eq!(messages[0].time.to_utc(), first);
+        for message in &messages {
+            book.handle(message).unwrap();
+        }
+        assert_eq!(book.bbo(), (None, None));
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+}
@@ -0,0 +1,533 @@
+use mmm_core::tick::{Direction, PriceTick};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Rem, Sub, SubAssign};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DecimalError {
+    #[error("invalid decimal string. ({0:})")]
+    Invalid(String),
+    #[error("decimal out of range. ({0:})")]
+    Overflow(String),
+    #[error("increment must be positive. ({0:})")]
+    InvalidIncrement(Decimal),
+    #[error("{0:} is not a multiple of increment {1:}.")]
+    NotMultiple(Decimal, Decimal),
+}
+
+/// Fixed-point decimal of `mantissa * 10^-scale`.
+/// The scale is kept as parsed so that a decimal prints back to its original string,
+/// while comparison, equality and hashing are by value, i.e. "1.50" == "1.5".
+/// The operators panic where the checked methods return an error, e.g. on overflow.
+#[derive(Clone, Copy, Default)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+/// Largest scale, as 10^38 is the largest power of ten in an i128.
+pub const MAX_SCALE: u32 = 38;
+
+/// Smallest scale of a quotient, so that a price or cost divided by a size keeps its digits.
+pub const DIV_SCALE: u32 = 18;
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    /// Panics if `scale` is larger than `MAX_SCALE`.
+    pub fn new(mantissa: i128, scale: u32) -> Self {
+        assert!(scale <= MAX_SCALE, "decimal scale {} out of range", scale);
+        Self { mantissa, scale }
+    }
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+    pub fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+
+    /// Mantissa of the value at a larger or equal scale. None on overflow.
+    fn mantissa_at(&self, scale: u32) -> Option<i128> {
+        pow10(scale - self.scale)?.checked_mul(self.mantissa)
+    }
+
+    /// Same value at `scale`. None if digits would be lost.
+    pub fn rescale(&self, scale: u32) -> Option<Decimal> {
+        if scale > MAX_SCALE {
+            None
+        } else if scale >= self.scale {
+            self.mantissa_at(scale)
+                .map(|mantissa| Decimal::new(mantissa, scale))
+        } else {
+            let div = pow10(self.scale - scale)?;
+            (self.mantissa % div == 0).then(|| Decimal::new(self.mantissa / div, scale))
+        }
+    }
+
+    /// Same value without trailing zeros.
+    pub fn normalize(&self) -> Decimal {
+        let mut d = *self;
+        while d.scale > 0 && d.mantissa % 10 == 0 {
+            d.mantissa /= 10;
+            d.scale -= 1;
+        }
+        if d.mantissa == 0 {
+            d.scale = 0;
+        }
+        d
+    }
+
+    /// The value on the ticks of a product's `quote_increment` or `base_increment`.
+    /// It is printed at the scale of the increment when no digit is lost, e.g. "27.20",
+    /// and a value off the ticks is kept as is, see `PriceTick::valid_price`.
+    pub fn with_increment(&self, increment: &Decimal) -> Result<TickedDecimal, DecimalError> {
+        if *increment <= Decimal::ZERO {
+            return Err(DecimalError::InvalidIncrement(*increment));
+        }
+        let value = match self.scale < increment.scale {
+            true => self.rescale(increment.scale).unwrap_or(*self),
+            false => *self,
+        };
+        Ok(TickedDecimal::new(value, *increment))
+    }
+
+    fn aligned(&self, other: &Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.mantissa_at(scale)?, other.mantissa_at(scale)?, scale))
+    }
+
+    fn overflow(&self, op: &str, rhs: &Decimal) -> DecimalError {
+        DecimalError::Overflow(format!("{} {} {}", self, op, rhs))
+    }
+
+    pub fn checked_add(&self, rhs: &Decimal) -> Result<Decimal, DecimalError> {
+        self.aligned(rhs)
+            .and_then(|(lhs, rhs, scale)| Some(Decimal::new(lhs.checked_add(rhs)?, scale)))
+            .ok_or_else(|| self.overflow("+", rhs))
+    }
+
+    pub fn checked_sub(&self, rhs: &Decimal) -> Result<Decimal, DecimalError> {
+        self.aligned(rhs)
+            .and_then(|(lhs, rhs, scale)| Some(Decimal::new(lhs.checked_sub(rhs)?, scale)))
+            .ok_or_else(|| self.overflow("-", rhs))
+    }
+
+    /// Exact product, e.g. price * size, without trailing zeros
+    /// so that repeated products of padded values such as "100.50000000" keep a small scale.
+    pub fn checked_mul(&self, rhs: &Decimal) -> Result<Decimal, DecimalError> {
+        let scale = self.scale + rhs.scale;
+        self.mantissa
+            .checked_mul(rhs.mantissa)
+            .map(|mantissa| Decimal { mantissa, scale }.normalize())
+            .filter(|product| product.scale <= MAX_SCALE)
+            .ok_or_else(|| self.overflow("*", rhs))
+    }
+
+    /// Quotient rounded toward zero at the scale of either operand or `DIV_SCALE`,
+    /// whichever is larger, without trailing zeros, e.g. "27.205" for ("27.20" + "27.21") / 2.
+    /// An error when `rhs` is zero.
+    pub fn checked_div(&self, rhs: &Decimal) -> Result<Decimal, DecimalError> {
+        let scale = self.scale.max(rhs.scale).max(DIV_SCALE);
+        pow10(scale - self.scale + rhs.scale)
+            .and_then(|exp| self.mantissa.checked_mul(exp)?.checked_div(rhs.mantissa))
+            .map(|mantissa| Decimal::new(mantissa, scale).normalize())
+            .ok_or_else(|| self.overflow("/", rhs))
+    }
+
+    /// Remainder of the division by `rhs`, an error when it is zero.
+    pub fn checked_rem(&self, rhs: &Decimal) -> Result<Decimal, DecimalError> {
+        self.aligned(rhs)
+            .and_then(|(lhs, rhs, scale)| Some(Decimal::new(lhs.checked_rem(rhs)?, scale)))
+            .ok_or_else(|| self.overflow("%", rhs))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = DecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || DecimalError::Invalid(s.to_string());
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if frac.len() > MAX_SCALE as usize {
+            return Err(DecimalError::Overflow(s.to_string()));
+        }
+        if int.is_empty()
+            || !int.bytes().all(|b| b.is_ascii_digit())
+            || !frac.bytes().all(|b| b.is_ascii_digit())
+            || (digits.contains('.') && frac.is_empty())
+        {
+            return Err(invalid());
+        }
+        let mantissa = int
+            .bytes()
+            .chain(frac.bytes())
+            .try_fold(0i128, |acc, b| {
+                acc.checked_mul(10)?.checked_add(i128::from(b - b'0'))
+            })
+            .ok_or_else(|| DecimalError::Overflow(s.to_string()))?;
+        Ok(Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: frac.len() as u32,
+        })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let abs = self.mantissa.unsigned_abs();
+        if self.scale == 0 {
+            return write!(f, "{}{}", sign, abs);
+        }
+        let div = 10u128.pow(self.scale);
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            abs / div,
+            abs % div,
+            width = self.scale as usize
+        )
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.aligned(other) {
+            Some((lhs, rhs, _)) => lhs.cmp(&rhs),
+            // only the one of the smaller scale is scaled up, and out of the range of the other
+            None if self.scale < other.scale => self.mantissa.cmp(&0),
+            None => 0.cmp(&other.mantissa),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalize();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+/// Panics on overflow, see `Decimal::checked_add`.
+impl Add for Decimal {
+    type Output = Decimal;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(&rhs).unwrap()
+    }
+}
+
+/// Panics on overflow, see `Decimal::checked_sub`.
+impl Sub for Decimal {
+    type Output = Decimal;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(&rhs).unwrap()
+    }
+}
+
+/// Panics on overflow, see `Decimal::checked_rem`.
+impl Rem for Decimal {
+    type Output = Decimal;
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.checked_rem(&rhs).unwrap()
+    }
+}
+
+/// Exact product, panics on overflow, see `Decimal::checked_mul`.
+impl Mul for Decimal {
+    type Output = Decimal;
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(&rhs).unwrap()
+    }
+}
+
+/// Quotient rounded toward zero, panics on overflow or a zero divisor, see `Decimal::checked_div`.
+impl Div for Decimal {
+    type Output = Decimal;
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(&rhs).unwrap()
+    }
+}
+
+impl From<u8> for Decimal {
+    fn from(value: u8) -> Self {
+        Decimal::new(value.into(), 0)
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+    fn neg(self) -> Self::Output {
+        Decimal::new(-self.mantissa, self.scale)
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Decimal::ZERO, Add::add)
+    }
+}
+
+/// TickedDecimal is a value ticked by the increment of its product, see `Decimal::with_increment`,
+/// e.g. the price of an `OrderBook`.
+/// Values are compared, equal and hashed by value, whatever their increment.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct TickedDecimal {
+    pub value: Decimal,
+    pub increment: Decimal,
+}
+
+impl TickedDecimal {
+    pub fn new(value: Decimal, increment: Decimal) -> Self {
+        Self { value, increment }
+    }
+}
+
+impl fmt::Display for TickedDecimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl PartialEq for TickedDecimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for TickedDecimal {}
+
+impl Ord for TickedDecimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl PartialOrd for TickedDecimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for TickedDecimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl AddAssign for TickedDecimal {
+    fn add_assign(&mut self, rhs: Self) {
+        self.value += rhs.value;
+    }
+}
+
+impl SubAssign for TickedDecimal {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.value -= rhs.value;
+    }
+}
+
+impl Rem for TickedDecimal {
+    type Output = Self;
+    fn rem(mut self, rhs: Self) -> Self {
+        self.value = self.value % rhs.value;
+        self
+    }
+}
+
+impl PriceTick for TickedDecimal {
+    fn tickify(&self, _direction: Direction) -> Self {
+        TickedDecimal::new(self.increment, self.increment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmm_core::collections::{book::OrderBook, level::PriceLevelBook, pnl::Position, Side};
+
+    fn d(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in ["27.2005", "20", "0.00000001", "1.0757000", "-3.10", "0"] {
+            assert_eq!(d(s).to_string(), s);
+            let json = serde_json::to_string(&d(s)).unwrap();
+            assert_eq!(json, format!("\"{}\"", s));
+            assert_eq!(
+                serde_json::from_str::<Decimal>(&json).unwrap().to_string(),
+                s
+            );
+        }
+        for s in ["", "-", ".5", "5.", "1e5", "1.2.3", "+1", " 1"] {
+            assert!(s.parse::<Decimal>().is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn order_and_arithmetic() {
+        assert_eq!(d("1.50"), d("1.5"));
+        assert!(d("1.49") < d("1.5"));
+        assert!(d("-0.1") < d("0"));
+        assert_eq!((d("1.5") + d("0.25")).to_string(), "1.75");
+        assert_eq!((d("1.5") - d("2")).to_string(), "-0.5");
+        assert_eq!((d("2354.7") * d("0.5")).to_string(), "1177.35");
+        // price, size and fee rate as sent by coinbase, and a 1000th fill of the same
+        let fee = d("100.50000000") * d("4.25000000") * d("0.00400000");
+        assert_eq!(fee.to_string(), "1.7085");
+        assert_eq!((fee * d("1000.00000000")).to_string(), "1708.5");
+        assert_eq!((d("1.05") % d("0.1")).to_string(), "0.05");
+        assert_eq!((d("54.41") / Decimal::from(2)).to_string(), "27.205");
+        assert_eq!(
+            (d("-2") / d("3")).to_string(),
+            format!("-0.{}", "6".repeat(18))
+        );
+        assert_eq!((d("1.50") / d("0.5")).to_string(), "3");
+        assert_eq!(
+            [d("1"), d("0.1"), d("0.01")].into_iter().sum::<Decimal>(),
+            d("1.11")
+        );
+    }
+
+    #[test]
+    fn position_and_mid() {
+        let mut position = Position::new();
+        position.fill(Side::Bid, d("27.20"), d("0.5"), Decimal::ZERO);
+        position.fill(Side::Bid, d("27.21"), d("1.0"), Decimal::ZERO);
+        let average_cost = format!("27.206{}", "6".repeat(15));
+        assert_eq!(position.average_cost(), Some(d(&average_cost)));
+        position.fill(Side::Ask, d("27.30"), d("1.5"), Decimal::ZERO);
+        assert_eq!(position.average_cost(), None);
+        assert_eq!(*position.realized(), d("0.14"));
+
+        let mut levels = PriceLevelBook::new();
+        levels.set(Side::Ask, d("27.21"), d("1"));
+        levels.set(Side::Bid, d("27.20"), d("2"));
+        assert_eq!(levels.mid(), Some(d("27.205")));
+    }
+
+    #[test]
+    fn tick_by_increment() {
+        let increment = d("0.01");
+        let price = d("27.2").with_increment(&increment).unwrap();
+        assert_eq!(price.to_string(), "27.20");
+        assert_eq!(price.ceiling_price().to_string(), "27.21");
+        assert_eq!(price.floor_price().to_string(), "27.19");
+        assert!(price.valid_price());
+        let off_tick = d("27.205").with_increment(&increment).unwrap();
+        assert!(!off_tick.valid_price());
+        assert_eq!(off_tick.to_string(), "27.205");
+
+        let half = d("1").with_increment(&d("0.5")).unwrap();
+        assert!(half.valid_price());
+        assert_eq!(half.ceiling_price().to_string(), "1.5");
+        assert_eq!(
+            d("1").with_increment(&Decimal::ZERO).unwrap_err(),
+            DecimalError::InvalidIncrement(Decimal::ZERO)
+        );
+    }
+
+    #[test]
+    fn ticked_book_price() {
+        let increment = d("0.01");
+        let t = |s: &str| d(s).with_increment(&increment).unwrap();
+        let mut book = OrderBook::<u64, TickedDecimal, Decimal, ()>::new();
+        book.insert_ask(1, t("27.21"), d("1"), ()).unwrap();
+        book.insert_ask(2, t("27.2").ceiling_price(), d("2"), ())
+            .unwrap();
+        book.insert_bid(3, t("27.20"), d("3"), ()).unwrap();
+        assert_eq!(book.ask_price_top().unwrap().0.to_string(), "27.21");
+        assert_eq!(book.ask_volume_at(t("27.210")), d("3"));
+        assert_eq!(book.price_levels().best_bid(), Some((&t("27.2"), &d("3"))));
+
+        let json = serde_json::to_string(&t("27.2")).unwrap();
+        assert_eq!(json, r#"{"value":"27.20","increment":"0.01"}"#);
+        let price = serde_json::from_str::<TickedDecimal>(&json).unwrap();
+        assert!(price.valid_price() && price.increment == increment);
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let tiny = format!("0.{}1", "0".repeat(38));
+        assert!(matches!(
+            tiny.parse::<Decimal>(),
+            Err(DecimalError::Overflow(_))
+        ));
+        let fine = d(&format!("0.{}1", "0".repeat(37)));
+        let huge = Decimal::new(i128::MAX, 0);
+        assert!(fine < huge && -huge < fine);
+        assert!(fine.checked_mul(&d("0.1")).is_err());
+        assert!(huge.checked_add(&d("1")).is_err());
+        assert!(d("1").checked_rem(&Decimal::ZERO).is_err());
+        assert!(d("1").checked_div(&Decimal::ZERO).is_err());
+        assert!(huge.checked_div(&d("2")).is_err());
+        assert_eq!(d("1").rescale(MAX_SCALE + 1), None);
+    }
+}
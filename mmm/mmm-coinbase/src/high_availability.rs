@@ -1,3 +1,4 @@
+use crate::auth::Credentials;
 use crate::util::RateLimit;
 use crate::websocket::{
     into_cb_stream, CBMessage, CBSink, CBStream, Full, Subscribe, WEBSOCKET_CONFIG,
@@ -11,19 +12,73 @@ use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::{collections::VecDeque, sync::Arc};
 use tokio::net::TcpSocket;
 use tokio::{
-    sync::{broadcast, mpsc},
-    time::Duration,
+    sync::{broadcast, mpsc, watch},
+    time::{Duration, Instant},
 };
 use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::Error as TError;
 use url::Url;
 
+/// Bounds applied by the broker to each per-product reorder buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferConfig {
+    /// maximum number of sequences kept between the last delivered sequence and the newest one.
+    /// a jump beyond this window forces the older gaps to be declared lost.
+    pub max_window: usize,
+    /// a gap at the head of the buffer is declared lost once it has blocked delivery for this long.
+    pub gap_timeout: Duration,
+    /// a connection that has not delivered any message for this long is excluded from the quorum.
+    pub stale_timeout: Duration,
+    /// interval at which timeouts are checked and metrics are published.
+    pub tick_interval: Duration,
+}
+
+impl Default for BufferConfig {
+    fn default() -> Self {
+        Self {
+            max_window: 100_000,
+            gap_timeout: Duration::from_secs(5),
+            stale_timeout: Duration::from_secs(30),
+            tick_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BufferMetrics {
+    /// number of sequences currently buffered (including gaps)
+    pub depth: usize,
+    /// largest depth observed so far
+    pub max_depth: usize,
+    /// number of `Loss` messages emitted
+    pub losses: u64,
+    /// number of sequences covered by the emitted `Loss` messages
+    pub lost_sequences: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BrokerMetrics {
+    pub products: HashMap<String, BufferMetrics>,
+    pub stale_sources: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Output<T> {
+    Data(u64, T),
+    Loss { sequence: u64, length: u64 },
+}
+
 struct Buffer<T> {
     name: String,
     read_seq: u64,
     write_seqs: Vec<u64>,
     queue: VecDeque<Option<(u64, T)>>,
+    // outputs forced out of `queue` by a jump beyond `max_window`
+    ready: VecDeque<Output<T>>,
+    // when the gap at the head of `queue` started blocking delivery
+    gap_since: Option<Instant>,
+    metrics: BufferMetrics,
 }
 
 impl<T> Buffer<T> {
@@ -33,10 +88,13 @@ impl<T> Buffer<T> {
             read_seq: sequence,
             write_seqs: vec![0; count],
             queue: Default::default(),
+            ready: Default::default(),
+            gap_since: None,
+            metrics: Default::default(),
         }
     }
 
-    pub fn write(&mut self, source: usize, sequence: u64, data: T)
+    pub fn write(&mut self, source: usize, sequence: u64, data: T, max_window: usize)
     where
         T: Clone,
     {
@@ -61,6 +119,10 @@ impl<T> Buffer<T> {
             ),
         }
 
+        if sequence > self.read_seq + max_window as u64 {
+            self.force_advance(sequence - max_window as u64);
+        }
+
         if sequence > self.read_seq {
             let len_queue = self.queue.len();
             let len_required = (sequence - self.read_seq) as usize;
@@ -82,104 +144,236 @@ impl<T> Buffer<T> {
                 std::cmp::Ordering::Equal => {}
             }
         }
+
+        self.metrics.depth = self.queue.len();
+        self.metrics.max_depth = self.metrics.max_depth.max(self.queue.len());
+    }
+
+    // moves everything up to `read_seq` into `ready`, declaring the remaining gaps lost.
+    fn force_advance(&mut self, read_seq: u64) {
+        log::debug!(
+            "{}| sequence jump beyond the reorder window. ({} -> {})",
+            self.name,
+            self.read_seq,
+            read_seq
+        );
+        while self.read_seq < read_seq {
+            match self.queue.pop_front() {
+                Some(Some((seq, data))) => {
+                    self.read_seq = seq;
+                    self.ready.push_back(Output::Data(seq, data));
+                }
+                Some(None) => {
+                    self.read_seq += 1;
+                    self.push_loss(self.read_seq, 1);
+                }
+                None => {
+                    self.push_loss(self.read_seq + 1, read_seq - self.read_seq);
+                    self.read_seq = read_seq;
+                }
+            }
+        }
+        self.gap_since = None;
+    }
+
+    fn push_loss(&mut self, sequence: u64, length: u64) {
+        match self.ready.back_mut() {
+            Some(Output::Loss {
+                sequence: last,
+                length: last_length,
+            }) if *last + *last_length == sequence => *last_length += length,
+            _ => self.ready.push_back(Output::Loss { sequence, length }),
+        }
+        self.metrics.lost_sequences += length;
     }
 
-    pub fn read(&mut self) -> Option<(u64, Option<T>)> {
-        match self.queue.front() {
+    // a gap is released once every live source has advanced past it.
+    // with no live source there is no quorum, and the gap waits for `gap_timeout`.
+    fn passed_by_quorum(&self, sequence: u64, live: &[bool]) -> bool {
+        let mut live_seqs = self
+            .write_seqs
+            .iter()
+            .zip(live)
+            .filter(|(_, live)| **live)
+            .peekable();
+        live_seqs.peek().is_some() && live_seqs.all(|(write_seq, _)| *write_seq >= sequence)
+    }
+
+    pub fn read(
+        &mut self,
+        now: Instant,
+        gap_timeout: Duration,
+        live: &[bool],
+    ) -> Option<Output<T>> {
+        if let Some(output) = self.ready.pop_front() {
+            if let Output::Loss { .. } = output {
+                self.metrics.losses += 1;
+            }
+            return Some(output);
+        }
+
+        let output = match self.queue.front() {
             Some(Some(_)) => {
                 let (seq, data) = self.queue.pop_front().unwrap().unwrap();
                 self.read_seq = seq;
-                Some((seq, Some(data)))
+                self.gap_since = None;
+                Some(Output::Data(seq, data))
             }
             Some(None) => {
-                if self
-                    .write_seqs
-                    .iter()
-                    .any(|write_seq| *write_seq <= self.read_seq)
-                {
+                let gap_since = *self.gap_since.get_or_insert(now);
+                let timed_out = now.duration_since(gap_since) >= gap_timeout;
+                if !timed_out && !self.passed_by_quorum(self.read_seq + 1, live) {
                     None
                 } else {
-                    self.queue.pop_front();
-                    self.read_seq += 1;
-                    Some((self.read_seq, None))
+                    let sequence = self.read_seq + 1;
+                    let mut length = 0;
+                    while let Some(None) = self.queue.front() {
+                        if !timed_out && !self.passed_by_quorum(self.read_seq + 1, live) {
+                            break;
+                        }
+                        self.queue.pop_front();
+                        self.read_seq += 1;
+                        length += 1;
+                    }
+                    self.gap_since = None;
+                    self.metrics.losses += 1;
+                    self.metrics.lost_sequences += length;
+                    Some(Output::Loss { sequence, length })
                 }
             }
             None => None,
-        }
+        };
+        self.metrics.depth = self.queue.len();
+        output
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Loss {
     pub product_id: String,
+    /// first lost sequence
     pub sequence: u64,
+    /// number of consecutive sequences lost starting from `sequence`
+    pub length: u64,
+}
+
+fn drain(
+    buffer: &mut Buffer<Full>,
+    now: Instant,
+    config: &BufferConfig,
+    live: &[bool],
+    tx: &mpsc::UnboundedSender<Result<Full, Loss>>,
+) -> Option<()> {
+    while let Some(output) = buffer.read(now, config.gap_timeout, live) {
+        let msg = match output {
+            Output::Data(sequence, data) => {
+                log::trace!(
+                    "[Read] product: {} seq: {} write_seq: {:?}, read_seq: {:?}",
+                    buffer.name,
+                    sequence,
+                    buffer.write_seqs,
+                    buffer.read_seq
+                );
+                Ok(data)
+            }
+            Output::Loss { sequence, length } => {
+                log::debug!(
+                    "[Loss] product: {} seq: {} length: {} write_seq: {:?}, read_seq: {:?}",
+                    buffer.name,
+                    sequence,
+                    length,
+                    buffer.write_seqs,
+                    buffer.read_seq
+                );
+                Err(Loss {
+                    product_id: buffer.name.clone(),
+                    sequence,
+                    length,
+                })
+            }
+        };
+        tx.send(msg).ok()?
+    }
+    Some(())
 }
 
 async fn run_broker(
     rxs: Vec<impl Stream<Item = (usize, Full)> + Unpin>,
     tx: mpsc::UnboundedSender<Result<Full, Loss>>,
+    metrics_tx: watch::Sender<BrokerMetrics>,
+    config: BufferConfig,
 ) -> Option<()> {
     let count = rxs.len();
     let mut rx = select_all(rxs);
-    let mut flow_map = HashMap::new();
+    let mut flow_map: HashMap<String, Buffer<Full>> = HashMap::new();
+    let mut last_seen = vec![Instant::now(); count];
+    let mut ticker = tokio::time::interval(config.tick_interval);
 
     loop {
-        let (id, full) = rx.next().await?;
-        let seq = full.sequence();
-        let seq_flow = match flow_map.get_mut(full.product_id()) {
-            Some(flows) => flows,
-            None => flow_map
-                .entry(full.product_id().to_string())
-                .or_insert_with(|| Buffer::new(full.product_id().into(), count, seq - 1)),
-        };
+        tokio::select! {
+            msg = rx.next() => {
+                let (id, full) = msg?;
+                let now = Instant::now();
+                last_seen[id] = now;
+                let live = liveness(&last_seen, now, config.stale_timeout);
+
+                let seq = full.sequence();
+                let seq_flow = match flow_map.get_mut(full.product_id()) {
+                    Some(flows) => flows,
+                    None => flow_map
+                        .entry(full.product_id().to_string())
+                        .or_insert_with(|| Buffer::new(full.product_id().into(), count, seq - 1)),
+                };
 
-        log::trace!(
-            "[Write] id: {} product: {} seq: {} write_seq: {:?}, read_seq: {:?}",
-            id,
-            full.product_id(),
-            full.sequence(),
-            seq_flow.write_seqs,
-            seq_flow.read_seq
-        );
+                log::trace!(
+                    "[Write] id: {} product: {} seq: {} write_seq: {:?}, read_seq: {:?}",
+                    id,
+                    full.product_id(),
+                    full.sequence(),
+                    seq_flow.write_seqs,
+                    seq_flow.read_seq
+                );
 
-        seq_flow.write(id, seq, full);
-
-        if let Some((sequence, data)) = seq_flow.read() {
-            log::trace!(
-                "[Read] id: {} product: {} seq: {} loss: {} write_seq: {:?}, read_seq: {:?}",
-                id,
-                seq_flow.name,
-                sequence,
-                data.is_none(),
-                seq_flow.write_seqs,
-                seq_flow.read_seq
-            );
-            let msg = data.ok_or_else(|| Loss {
-                product_id: seq_flow.name.clone(),
-                sequence,
-            });
-            tx.send(msg).ok()?
-        } else if seq_flow
-            .write_seqs
-            .iter()
-            .all(|write_seq| *write_seq == 0 || *write_seq > seq_flow.read_seq)
-        {
-            log::trace!(
-                "[Warning] data loss may occured. id: {} product: {} write_seq: {:?}, read_seq: {:?}",
-                id,
-                seq_flow.name,
-                seq_flow.write_seqs,
-                seq_flow.read_seq
-            );
+                seq_flow.write(id, seq, full, config.max_window);
+                drain(seq_flow, now, &config, &live, &tx)?;
+            }
+            _ = ticker.tick() => {
+                let now = Instant::now();
+                let live = liveness(&last_seen, now, config.stale_timeout);
+                for seq_flow in flow_map.values_mut() {
+                    drain(seq_flow, now, &config, &live, &tx)?;
+                }
+                let _ = metrics_tx.send(BrokerMetrics {
+                    products: flow_map
+                        .iter()
+                        .map(|(product_id, seq_flow)| (product_id.clone(), seq_flow.metrics.clone()))
+                        .collect(),
+                    stale_sources: live
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, live)| !**live)
+                        .map(|(id, _)| id)
+                        .collect(),
+                });
+            }
         }
     }
 }
 
+fn liveness(last_seen: &[Instant], now: Instant, stale_timeout: Duration) -> Vec<bool> {
+    last_seen
+        .iter()
+        .map(|seen| now.duration_since(*seen) < stale_timeout)
+        .collect()
+}
+
 async fn highly_available_channel(
     count: usize,
+    config: BufferConfig,
 ) -> (
     Vec<mpsc::UnboundedSender<Full>>,
     mpsc::UnboundedReceiver<Result<Full, Loss>>,
+    watch::Receiver<BrokerMetrics>,
 ) {
     let (txs, rxs) = (0..count)
         .into_iter()
@@ -190,10 +384,11 @@ async fn highly_available_channel(
         })
         .unzip::<_, _, Vec<_>, Vec<_>>();
     let (tx, rx) = mpsc::unbounded_channel();
+    let (metrics_tx, metrics_rx) = watch::channel(BrokerMetrics::default());
 
-    tokio::spawn(run_broker(rxs, tx));
+    tokio::spawn(run_broker(rxs, tx, metrics_tx, config));
 
-    (txs, rx)
+    (txs, rx, metrics_rx)
 }
 
 fn merge_subscribe(orig: &mut Option<Subscribe>, update: Subscribe) {
@@ -204,11 +399,29 @@ fn merge_subscribe(orig: &mut Option<Subscribe>, update: Subscribe) {
     }
 }
 
+/// Subscription of a connection, sent again on each reconnect.
+#[derive(Debug)]
+struct Subscription {
+    subscribe: Option<Subscribe>,
+    // signatures expire in 30 seconds, so subscriptions are signed on each send.
+    credentials: Option<Credentials>,
+}
+
+impl Subscription {
+    async fn send(&self, cbws: &mut impl CBSink, subscribe: &Subscribe) -> anyhow::Result<()> {
+        match &self.credentials {
+            Some(credentials) => cbws.subscribe_authenticated(subscribe, credentials).await,
+            None => cbws.subscribe(subscribe).await,
+        }
+        .context("failed to send subscription message.")
+    }
+}
+
 async fn run_async(
     id: usize,
     endpoint: &Url,
     rate_limit: &mut Arc<Mutex<RateLimit>>,
-    subscribe: &mut Option<Subscribe>,
+    subscription: &mut Subscription,
     tx: &mut mpsc::UnboundedSender<Full>,
     rx: &mut broadcast::Receiver<Subscribe>,
     interface: SocketAddr,
@@ -218,10 +431,8 @@ async fn run_async(
         rate_limit.wait().await;
         connect_async_via(id, endpoint, interface).await
     }?;
-    if let Some(subscribe) = subscribe {
-        cbws.subscribe(subscribe)
-            .await
-            .context("failed to send subscription message.")?;
+    if let Some(subscribe) = &subscription.subscribe {
+        subscription.send(&mut cbws, subscribe).await?;
     }
 
     loop {
@@ -240,9 +451,9 @@ async fn run_async(
             {
                 let sub_update = sub.context("failed to receive subscription.")?;
                 log::info!("{}| update subscription request received. ({:?})", id, sub_update);
-                cbws.subscribe(&sub_update).await.context("failed to send subscription message.")?;
-                merge_subscribe(subscribe, sub_update);
-                log::info!("{}| subscription updated. ({:?})", id, subscribe);
+                subscription.send(&mut cbws, &sub_update).await?;
+                merge_subscribe(&mut subscription.subscribe, sub_update);
+                log::info!("{}| subscription updated. ({:?})", id, subscription.subscribe);
             },
         }
     }
@@ -255,10 +466,14 @@ async fn run_async_forever(
     mut tx: mpsc::UnboundedSender<Full>,
     mut rx: broadcast::Receiver<Subscribe>,
     interface: SocketAddr,
+    credentials: Option<Credentials>,
 ) {
-    let mut subscribe = None;
+    let mut subscription = Subscription {
+        subscribe: None,
+        credentials,
+    };
     while let Ok(sub_msg) = rx.try_recv() {
-        merge_subscribe(&mut subscribe, sub_msg);
+        merge_subscribe(&mut subscription.subscribe, sub_msg);
     }
 
     loop {
@@ -266,7 +481,7 @@ async fn run_async_forever(
             id,
             &endpoint,
             &mut rate_limit,
-            &mut subscribe,
+            &mut subscription,
             &mut tx,
             &mut rx,
             interface,
@@ -283,6 +498,30 @@ pub async fn highly_available_receive(
 ) -> (
     broadcast::Sender<Subscribe>,
     mpsc::UnboundedReceiver<Result<Full, Loss>>,
+) {
+    let (sub_tx, rx, _) = highly_available_receive_with_config(
+        endpoint,
+        redundancy,
+        interfaces,
+        Default::default(),
+        None,
+    )
+    .await;
+    (sub_tx, rx)
+}
+
+/// With `credentials`, subscriptions are signed each time they are sent, including on reconnects,
+/// as the user channel requires.
+pub async fn highly_available_receive_with_config(
+    endpoint: Url,
+    redundancy: usize,
+    interfaces: Option<Vec<SocketAddr>>,
+    config: BufferConfig,
+    credentials: Option<Credentials>,
+) -> (
+    broadcast::Sender<Subscribe>,
+    mpsc::UnboundedReceiver<Result<Full, Loss>>,
+    watch::Receiver<BrokerMetrics>,
 ) {
     let rate_limit = Arc::new(Mutex::new(RateLimit::new(Duration::from_secs(4))));
 
@@ -292,7 +531,7 @@ pub async fn highly_available_receive(
     let mut interfaces = interfaces.into_iter().cycle();
 
     let (sub_tx, mut sub_rx) = broadcast::channel(8);
-    let (txs, rx) = highly_available_channel(count).await;
+    let (txs, rx, metrics_rx) = highly_available_channel(count, config).await;
 
     for (id, tx) in txs.into_iter().enumerate() {
         tokio::spawn(run_async_forever(
@@ -302,11 +541,12 @@ pub async fn highly_available_receive(
             tx.clone(),
             sub_rx,
             interfaces.next().unwrap(),
+            credentials.clone(),
         ));
         sub_rx = sub_tx.subscribe();
     }
 
-    (sub_tx, rx)
+    (sub_tx, rx, metrics_rx)
 }
 
 async fn connect_async_via(
@@ -374,3 +614,155 @@ async fn to_socket_addrs(endpoint: &Url) -> Result<impl Iterator<Item = SocketAd
     let addr = format!("{}:{}", host, port);
     Ok(lookup_host(addr).await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn read_all(buffer: &mut Buffer<u64>, now: Instant, live: &[bool]) -> Vec<Output<u64>> {
+        std::iter::from_fn(|| buffer.read(now, TIMEOUT, live)).collect()
+    }
+
+    #[test]
+    fn gap_filled_by_other_source() {
+        let now = Instant::now();
+        let live = [true, true];
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 1, 1, 100);
+        buffer.write(0, 3, 3, 100);
+        assert_eq!(read_all(&mut buffer, now, &live), vec![Output::Data(1, 1)]);
+
+        buffer.write(1, 2, 2, 100);
+        assert_eq!(
+            read_all(&mut buffer, now, &live),
+            vec![Output::Data(2, 2), Output::Data(3, 3)]
+        );
+        assert_eq!(buffer.metrics.depth, 0);
+        assert_eq!(buffer.metrics.losses, 0);
+    }
+
+    #[test]
+    fn gap_released_by_quorum() {
+        let now = Instant::now();
+        let live = [true, true];
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 1, 1, 100);
+        buffer.write(0, 4, 4, 100);
+        assert_eq!(read_all(&mut buffer, now, &live), vec![Output::Data(1, 1)]);
+
+        buffer.write(1, 4, 4, 100);
+        assert_eq!(
+            read_all(&mut buffer, now, &live),
+            vec![
+                Output::Loss {
+                    sequence: 2,
+                    length: 2
+                },
+                Output::Data(4, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn gap_released_by_timeout() {
+        let now = Instant::now();
+        let live = [true, true];
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 2, 2, 100);
+        assert!(read_all(&mut buffer, now, &live).is_empty());
+        assert!(read_all(&mut buffer, now + TIMEOUT / 2, &live).is_empty());
+        assert_eq!(
+            read_all(&mut buffer, now + TIMEOUT, &live),
+            vec![
+                Output::Loss {
+                    sequence: 1,
+                    length: 1
+                },
+                Output::Data(2, 2)
+            ]
+        );
+        assert_eq!(buffer.metrics.lost_sequences, 1);
+    }
+
+    #[test]
+    fn stale_source_excluded_from_quorum() {
+        let now = Instant::now();
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 2, 2, 100);
+        assert!(read_all(&mut buffer, now, &[true, true]).is_empty());
+        assert_eq!(
+            read_all(&mut buffer, now, &[true, false]),
+            vec![
+                Output::Loss {
+                    sequence: 1,
+                    length: 1
+                },
+                Output::Data(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn no_quorum_without_live_source() {
+        let now = Instant::now();
+        let stale = [false, false];
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 2, 2, 100);
+        assert!(read_all(&mut buffer, now, &stale).is_empty());
+        assert_eq!(buffer.metrics.losses, 0);
+        assert_eq!(
+            read_all(&mut buffer, now + TIMEOUT, &stale),
+            vec![
+                Output::Loss {
+                    sequence: 1,
+                    length: 1
+                },
+                Output::Data(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn jump_beyond_window() {
+        let now = Instant::now();
+        let live = [true, true];
+        let mut buffer = Buffer::new("BTC-USD".into(), 2, 0);
+
+        buffer.write(0, 2, 2, 10);
+        buffer.write(0, 1_000_000, 1_000_000, 10);
+        assert_eq!(buffer.queue.len(), 10);
+        assert_eq!(
+            read_all(&mut buffer, now, &live),
+            vec![
+                Output::Loss {
+                    sequence: 1,
+                    length: 1
+                },
+                Output::Data(2, 2),
+                Output::Loss {
+                    sequence: 3,
+                    length: 999_988
+                },
+            ]
+        );
+        assert_eq!(buffer.read_seq, 999_990);
+        assert_eq!(buffer.metrics.max_depth, 10);
+    }
+
+    #[test]
+    fn liveness_by_last_seen() {
+        let now = Instant::now();
+        let last_seen = [now, now - Duration::from_secs(60)];
+        assert_eq!(
+            liveness(&last_seen, now, Duration::from_secs(30)),
+            vec![true, false]
+        );
+    }
+}
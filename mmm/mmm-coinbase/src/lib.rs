@@ -1,4 +1,11 @@
+pub mod auth;
+pub mod convert;
+pub mod decimal;
 pub mod high_availability;
+pub mod recorder;
+pub mod rest;
 pub mod serde;
+pub mod sink;
+pub mod user;
 pub mod util;
 pub mod websocket;
@@ -0,0 +1,535 @@
+use crate::high_availability::Loss;
+use crate::rest::{OrderRecord, Product, RestClient, RestError};
+use crate::sink::{Sink, SinkError};
+use crate::websocket::{Channel, ChannelType, Full, Subscribe};
+use async_compression::tokio::write::ZstdEncoder;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{broadcast, mpsc, oneshot},
+    task::JoinHandle,
+    time::{Duration, Instant},
+};
+
+#[derive(Error, Debug)]
+pub enum RecorderError {
+    #[error("io error. ({0:?})")]
+    Io(#[from] std::io::Error),
+    #[error("failed to upload recordings. ({0:?})")]
+    Sink(#[from] SinkError),
+    #[error("message stream closed.")]
+    StreamClosed,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    /// directory to write recordings into.
+    pub dest: PathBuf,
+    /// zstd compression level.
+    pub compression_level: u32,
+    /// interval of book snapshots per product.
+    pub save_interval: chrono::Duration,
+    /// number of messages between latency reports.
+    pub report_interval: u64,
+}
+
+impl RecorderConfig {
+    pub fn new(dest: PathBuf) -> Self {
+        Self {
+            dest,
+            compression_level: 10,
+            save_interval: chrono::Duration::minutes(15),
+            report_interval: 10_000_000,
+        }
+    }
+}
+
+/// Records the full channel into `dest/%Y-%m-%d/%H%M%S/{product_id}`.
+/// Each product has `full-{sequence}.json.zst` of (machine time, message) lines
+/// and `book/{sequence}.json` level 3 snapshots taken periodically and after every loss.
+/// A directory is rotated every day and, when closed, handed over to the sink if any.
+pub struct Recorder {
+    config: RecorderConfig,
+    rest: RestClient,
+    sink: Option<Arc<dyn Sink>>,
+}
+
+#[derive(Debug)]
+enum APIRequest {
+    SaveBook {
+        product_id: String,
+        minimum_sequence: Option<u64>,
+        out_folder: PathBuf,
+    },
+    GetProducts {
+        tx: oneshot::Sender<Vec<Product>>,
+    },
+    /// Answered once every request sent before is done.
+    Flush {
+        tx: oneshot::Sender<()>,
+    },
+}
+
+struct ProductWriter {
+    last_save: DateTime<Utc>,
+    encoder: ZstdEncoder<BufWriter<File>>,
+}
+
+impl Recorder {
+    pub fn new(config: RecorderConfig, rest: RestClient) -> Self {
+        Self {
+            config,
+            rest,
+            sink: None,
+        }
+    }
+
+    pub fn with_sink(mut self, sink: Arc<dyn Sink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// Records until `shutdown` resolves, then flushes every writer and pending book snapshot.
+    /// `cb_tx` and `cb_rx` are the ends of `high_availability::highly_available_receive`.
+    pub async fn run(
+        self,
+        cb_tx: broadcast::Sender<Subscribe>,
+        cb_rx: mpsc::UnboundedReceiver<Result<Full, Loss>>,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), RecorderError> {
+        let Recorder { config, rest, sink } = self;
+
+        let (api_tx, api_rx) = mpsc::unbounded_channel();
+        tokio::spawn(api_loop(rest, api_rx));
+
+        let (update_tx, update_rx) = mpsc::unbounded_channel();
+        tokio::spawn(update_subscription_loop(update_rx, api_tx.clone(), cb_tx));
+        update_tx.send(()).unwrap();
+
+        let mut receiver = Receiver {
+            config,
+            sink,
+            api_tx,
+            update_tx,
+            out_dir: PathBuf::new(),
+            prev_datetime: Utc::now(),
+            writers: HashMap::new(),
+            closing: Vec::new(),
+            cnt: 0,
+            latencies: Vec::new(),
+            last_loss: Instant::now(),
+        };
+        receiver.out_dir = receiver.create_out_dir(receiver.prev_datetime).await;
+        log::trace!("start_time: {:?}", receiver.prev_datetime);
+
+        let result = receiver.receive(cb_rx, shutdown).await;
+        log::info!("shutting down recorder.");
+        receiver.close().await?;
+        result
+    }
+}
+
+struct Receiver {
+    config: RecorderConfig,
+    sink: Option<Arc<dyn Sink>>,
+    api_tx: mpsc::UnboundedSender<APIRequest>,
+    update_tx: mpsc::UnboundedSender<()>,
+    out_dir: PathBuf,
+    prev_datetime: DateTime<Utc>,
+    writers: HashMap<String, ProductWriter>,
+    closing: Vec<JoinHandle<Result<(), RecorderError>>>,
+    cnt: u64,
+    latencies: Vec<i64>,
+    last_loss: Instant,
+}
+
+impl Receiver {
+    async fn create_out_dir(&self, datetime: DateTime<Utc>) -> PathBuf {
+        let out_dir = self
+            .config
+            .dest
+            .join(datetime.format("%Y-%m-%d").to_string())
+            .join(datetime.format("%H%M%S").to_string());
+        let _ = tokio::fs::create_dir_all(&out_dir).await;
+        out_dir
+    }
+
+    async fn receive(
+        &mut self,
+        mut cb_rx: mpsc::UnboundedReceiver<Result<Full, Loss>>,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), RecorderError> {
+        tokio::pin!(shutdown);
+        loop {
+            let msg = tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                msg = cb_rx.recv() => msg.ok_or(RecorderError::StreamClosed)?,
+            };
+            match msg {
+                Ok(full) => self.write(Utc::now(), full).await?,
+                Err(loss) => self.loss(loss),
+            }
+        }
+    }
+
+    async fn write(
+        &mut self,
+        machine_time: DateTime<Utc>,
+        full: Full,
+    ) -> Result<(), RecorderError> {
+        let time = *full.time();
+        if self.prev_datetime.date_naive() < time.date_naive() {
+            log::info!(
+                "date refreshed from {:?} to {:?}",
+                self.prev_datetime.date_naive(),
+                time.date_naive()
+            );
+            self.rotate(time).await;
+        }
+
+        let mut serialized = serde_json::to_vec(&(&machine_time, &full)).unwrap();
+        serialized.push(b'\n');
+        if !self.writers.contains_key(full.product_id()) {
+            let product_folder = self.out_dir.join(full.product_id());
+            tokio::fs::create_dir_all(product_folder.join("book")).await?;
+            let file =
+                File::create(product_folder.join(format!("full-{}.json.zst", full.sequence())))
+                    .await?;
+            let encoder = ZstdEncoder::with_quality(
+                BufWriter::new(file),
+                async_compression::Level::Precise(self.config.compression_level),
+            );
+            self.writers.insert(
+                full.product_id().to_string(),
+                ProductWriter {
+                    last_save: time,
+                    encoder,
+                },
+            );
+        }
+        let writer = self.writers.get_mut(full.product_id()).unwrap();
+        writer.encoder.write_all(&serialized).await?;
+
+        if (time - writer.last_save) > self.config.save_interval {
+            self.api_tx
+                .send(APIRequest::SaveBook {
+                    product_id: full.product_id().to_string(),
+                    minimum_sequence: None,
+                    out_folder: self.out_dir.join(full.product_id()).join("book"),
+                })
+                .unwrap();
+            writer.last_save += self.config.save_interval;
+        }
+
+        self.cnt += 1;
+        self.latencies
+            .push((machine_time - time).num_milliseconds());
+        if self.cnt.is_multiple_of(self.config.report_interval) {
+            let mut latencies = std::mem::take(&mut self.latencies);
+            let cnt = self.cnt;
+            tokio::task::spawn_blocking(move || {
+                latencies.sort_unstable();
+                log::info!(
+                    "[{}] cnt: {} latency(99%): {:?}ms",
+                    machine_time,
+                    cnt,
+                    latencies[(latencies.len() as f32 * 0.99) as usize]
+                );
+            });
+        }
+        Ok(())
+    }
+
+    fn loss(&mut self, loss: Loss) {
+        let Loss {
+            product_id,
+            sequence,
+            length,
+        } = loss;
+        if self.last_loss.elapsed() > Duration::from_secs(60) {
+            log::error!("loss occurred.");
+            self.last_loss = Instant::now();
+        }
+        log::trace!("data loss {} {} ({})", product_id, sequence, length);
+        self.api_tx
+            .send(APIRequest::SaveBook {
+                minimum_sequence: Some(sequence + length - 1),
+                out_folder: self.out_dir.join(&product_id).join("book"),
+                product_id,
+            })
+            .unwrap();
+        log::trace!("SaveBook request sent.");
+    }
+
+    /// Closes the current directory in background and opens a new one from `time`.
+    async fn rotate(&mut self, time: DateTime<Utc>) {
+        let writers = std::mem::take(&mut self.writers);
+        let out_dir = self.create_out_dir(time).await;
+        let closed_dir = std::mem::replace(&mut self.out_dir, out_dir);
+        self.closing.push(tokio::spawn(close_dir(
+            writers,
+            self.api_tx.clone(),
+            self.sink.clone(),
+            self.config.dest.clone(),
+            closed_dir,
+        )));
+        self.prev_datetime = time;
+        self.cnt = 0;
+        self.update_tx.send(()).unwrap();
+    }
+
+    async fn close(&mut self) -> Result<(), RecorderError> {
+        let writers = std::mem::take(&mut self.writers);
+        close_dir(
+            writers,
+            self.api_tx.clone(),
+            self.sink.clone(),
+            self.config.dest.clone(),
+            self.out_dir.clone(),
+        )
+        .await?;
+        for handle in self.closing.drain(..) {
+            handle.await.expect("closing task panicked.")?;
+        }
+        Ok(())
+    }
+}
+
+/// Flushes `writers` and book snapshots requested so far, then uploads `dir` if `sink` is given.
+async fn close_dir(
+    writers: HashMap<String, ProductWriter>,
+    api_tx: mpsc::UnboundedSender<APIRequest>,
+    sink: Option<Arc<dyn Sink>>,
+    dest: PathBuf,
+    dir: PathBuf,
+) -> Result<(), RecorderError> {
+    for (_, mut writer) in writers {
+        writer.encoder.shutdown().await?;
+    }
+    let (tx, rx) = oneshot::channel();
+    if api_tx.send(APIRequest::Flush { tx }).is_ok() {
+        let _ = rx.await;
+    }
+    if let Some(sink) = sink {
+        upload_dir(sink.as_ref(), &dest, &dir).await?;
+    }
+    Ok(())
+}
+
+/// Archives each `book` folder into `book.tar.zst`, puts every file of `dir` into `sink`
+/// keyed by its path relative to `dest` and removes `dir`. The parent is removed when left empty.
+pub async fn upload_dir(sink: &dyn Sink, dest: &Path, dir: &Path) -> Result<(), RecorderError> {
+    let mut product_dirs = tokio::fs::read_dir(dir).await?;
+    while let Some(product_dir) = product_dirs.next_entry().await? {
+        let product_dir = product_dir.path();
+        log::info!("uploading {:?}", product_dir);
+        let book_dir = product_dir.join("book");
+        if tokio::fs::metadata(&book_dir).await.is_ok() {
+            let archive = product_dir.join("book.tar.zst");
+            tokio::task::spawn_blocking(move || archive_book(&book_dir, &archive))
+                .await
+                .expect("archiving task panicked.")?;
+            tokio::fs::remove_dir_all(product_dir.join("book")).await?;
+        }
+
+        let mut files = tokio::fs::read_dir(&product_dir).await?;
+        while let Some(file) = files.next_entry().await? {
+            let path = file.path();
+            let key = path
+                .strip_prefix(dest)
+                .unwrap_or(&path)
+                .iter()
+                .map(|s| s.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            sink.put(&key, &path).await?;
+        }
+        tokio::fs::remove_dir_all(&product_dir).await?;
+    }
+    tokio::fs::remove_dir(dir).await?;
+    if let Some(parent) = dir.parent() {
+        // NOTE: fails while other directories of the date remain.
+        let _ = tokio::fs::remove_dir(parent).await;
+    }
+    Ok(())
+}
+
+/// Uploads every directory of `date` under `dest`, e.g. those left by a crashed recorder.
+pub async fn upload_date(
+    sink: &dyn Sink,
+    dest: &Path,
+    date: chrono::NaiveDate,
+) -> Result<(), RecorderError> {
+    let date_dir = dest.join(date.format("%Y-%m-%d").to_string());
+    let mut time_dirs = tokio::fs::read_dir(&date_dir).await?;
+    let mut dirs = Vec::new();
+    while let Some(time_dir) = time_dirs.next_entry().await? {
+        dirs.push(time_dir.path());
+    }
+    for dir in dirs {
+        upload_dir(sink, dest, &dir).await?;
+    }
+    Ok(())
+}
+
+fn archive_book(book_dir: &Path, archive: &Path) -> std::io::Result<()> {
+    let mut tar = tar::Builder::new(Vec::new());
+    tar.append_dir_all("book", book_dir)?;
+    let compressed = zstd::encode_all(tar.into_inner()?.as_slice(), 10)?;
+    std::fs::File::create(archive)?.write_all(&compressed)
+}
+
+async fn update_subscription_loop(
+    mut update_rx: mpsc::UnboundedReceiver<()>,
+    api_tx: mpsc::UnboundedSender<APIRequest>,
+    cb_tx: broadcast::Sender<Subscribe>,
+) {
+    let mut prev_pids = HashSet::new();
+
+    while let Some(()) = update_rx.recv().await {
+        let (tx, rx) = oneshot::channel();
+        if api_tx.send(APIRequest::GetProducts { tx }).is_err() {
+            break;
+        }
+        log::trace!("GetProducts request sent.");
+        let updated_pids = match rx.await {
+            Ok(products) => products.into_iter().map(|p| p.id).collect::<HashSet<_>>(),
+            Err(_) => break,
+        };
+        log::trace!("updated_pids: {:?}", updated_pids);
+
+        let new_pids: Vec<String> = updated_pids.difference(&prev_pids).cloned().collect();
+        if !new_pids.is_empty() {
+            log::info!("newly added product ids: {:?}", new_pids);
+            let subscribe = Subscribe::new(new_pids, vec![Channel::Name(ChannelType::Full)]);
+            if cb_tx.send(subscribe).is_err() {
+                break;
+            }
+            log::trace!("subscription request sent.");
+        } else {
+            log::info!("there are no new products to subscribe.")
+        }
+
+        let old_pids: Vec<&str> = prev_pids
+            .difference(&updated_pids)
+            .map(|x| x.as_str())
+            .collect::<Vec<&str>>();
+        // TODO: unsubscribe
+        if !old_pids.is_empty() {
+            log::info!("removed product ids: {:?}", old_pids);
+        }
+        prev_pids = updated_pids;
+    }
+}
+
+async fn save_book(
+    rest: &mut RestClient,
+    product_id: &str,
+    minimum_sequence: Option<u64>,
+    out_folder: &Path,
+) -> Result<(), RestError> {
+    loop {
+        let book = rest.book::<OrderRecord>(product_id).await?;
+        if let Some(min_seq) = minimum_sequence {
+            log::trace!("sequence check ({} <= {})", min_seq, book.sequence);
+            if book.sequence < min_seq {
+                log::debug!("sequence check failed.");
+                continue;
+            }
+        }
+
+        let out_path = out_folder.join(format!("{}.json", book.sequence));
+        let _ = tokio::fs::create_dir_all(&out_folder).await;
+        let saved = match File::create(out_path).await {
+            Ok(mut file) => file.write_all(&serde_json::to_vec(&book).unwrap()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            log::error!("failed to save book of {}. ({:?})", product_id, e);
+        }
+        return Ok(());
+    }
+}
+
+async fn api_loop(mut rest: RestClient, mut rx: mpsc::UnboundedReceiver<APIRequest>) {
+    while let Some(request) = rx.recv().await {
+        log::trace!("new request received. ({:?})", request);
+        match request {
+            APIRequest::SaveBook {
+                product_id,
+                minimum_sequence,
+                out_folder,
+            } => loop {
+                match save_book(&mut rest, &product_id, minimum_sequence, &out_folder).await {
+                    Ok(_) => break,
+                    Err(e) => log::error!("retry `save_book` with reason ({:?})", e),
+                }
+            },
+            APIRequest::GetProducts { tx } => loop {
+                match rest.products().await {
+                    Ok(products) => break tx.send(products).unwrap_or(()),
+                    Err(e) => log::error!("retry `get_products` with reason ({:?})", e),
+                }
+            },
+            APIRequest::Flush { tx } => tx.send(()).unwrap_or(()),
+        }
+        log::trace!("response sent.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::LocalSink;
+
+    #[tokio::test]
+    async fn upload_closed_dir() {
+        let root = std::env::temp_dir().join(format!("mmm-recorder-{}", std::process::id()));
+        let dest = root.join("dest");
+        let product_dir = dest.join("2021-08-25").join("120000").join("BTC-USD");
+        tokio::fs::create_dir_all(product_dir.join("book"))
+            .await
+            .unwrap();
+        tokio::fs::write(product_dir.join("full-1.json.zst"), b"full")
+            .await
+            .unwrap();
+        tokio::fs::write(product_dir.join("book").join("3.json"), b"{}")
+            .await
+            .unwrap();
+
+        let sink = LocalSink::new(root.join("archive"));
+        upload_date(
+            &sink,
+            &dest,
+            chrono::NaiveDate::from_ymd_opt(2021, 8, 25).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let archived = root
+            .join("archive")
+            .join("2021-08-25")
+            .join("120000")
+            .join("BTC-USD");
+        assert!(archived.join("full-1.json.zst").exists());
+        let tar =
+            zstd::decode_all(std::fs::File::open(archived.join("book.tar.zst")).unwrap()).unwrap();
+        let names = tar::Archive::new(tar.as_slice())
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"book/3.json".to_string()));
+        assert!(!dest.join("2021-08-25").exists());
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,363 @@
+use crate::decimal::{Decimal, DecimalError, TickedDecimal};
+use crate::util::RateLimit;
+use crate::websocket::Side;
+use mmm_core::tick::PriceTick;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tokio::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+type UtcDateTime = chrono::DateTime<chrono::Utc>;
+
+pub const REST_URL: &str = "https://api.exchange.coinbase.com";
+/// NOTE: public endpoints are limited to 10 requests per second per IP, bursting to 15.
+pub const PUBLIC_INTERVAL: Duration = Duration::from_millis(350);
+const USER_AGENT: &str = "mmm-coinbase";
+
+#[derive(Error, Debug)]
+pub enum RestError {
+    #[error("invalid request url. ({0:?})")]
+    InvalidUrl(#[source] url::ParseError),
+    #[error("failed to request coinbase api. ({0:?})")]
+    RequestFailed(#[source] reqwest::Error),
+    #[error("coinbase api responded with {status}. ({message})")]
+    Status { status: u16, message: String },
+    #[error("invalid response content found. ({0:?})")]
+    InvalidContent(#[source] serde_json::Error),
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
+pub struct Product {
+    pub id: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub quote_increment: Decimal,
+    pub base_increment: Decimal,
+    pub display_name: Option<String>,
+    pub min_market_funds: Option<Decimal>,
+    pub status: Option<String>,
+    pub status_message: Option<String>,
+    #[serde(default)]
+    pub post_only: bool,
+    #[serde(default)]
+    pub limit_only: bool,
+    #[serde(default)]
+    pub cancel_only: bool,
+    #[serde(default)]
+    pub trading_disabled: bool,
+}
+
+impl Product {
+    /// `price` on the tick of `quote_increment`.
+    pub fn price(&self, price: &Decimal) -> Result<TickedDecimal, DecimalError> {
+        Self::on_tick(price, &self.quote_increment)
+    }
+    /// `size` on the tick of `base_increment`.
+    pub fn size(&self, size: &Decimal) -> Result<TickedDecimal, DecimalError> {
+        Self::on_tick(size, &self.base_increment)
+    }
+
+    fn on_tick(value: &Decimal, increment: &Decimal) -> Result<TickedDecimal, DecimalError> {
+        let ticked = value.with_increment(increment)?;
+        match ticked.valid_price() {
+            true => Ok(ticked),
+            false => Err(DecimalError::NotMultiple(*value, *increment)),
+        }
+    }
+}
+
+/// A book entry of level 1 and 2. (price, size, num_orders)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct AggregatedRecord(pub Decimal, pub Decimal, pub u64);
+
+/// A book entry of level 3. (price, size, order_id)
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct OrderRecord(pub Decimal, pub Decimal, pub Uuid);
+
+pub trait BookRecord: DeserializeOwned {
+    const LEVEL: u8;
+}
+
+impl BookRecord for AggregatedRecord {
+    const LEVEL: u8 = 2;
+}
+
+impl BookRecord for OrderRecord {
+    const LEVEL: u8 = 3;
+}
+
+/// Book snapshot. Its sequence continues on the full channel.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Book<R> {
+    pub sequence: u64,
+    pub bids: Vec<R>,
+    pub asks: Vec<R>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Trade {
+    pub time: UtcDateTime,
+    pub trade_id: u64,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// side of the maker order as in `websocket::Match`.
+    pub side: Side,
+}
+
+/// (time, low, high, open, close, volume)
+/// NOTE: candles are given as numbers, not strings.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct Candle(pub i64, pub f64, pub f64, pub f64, pub f64, pub f64);
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum Granularity {
+    M1 = 60,
+    M5 = 300,
+    M15 = 900,
+    H1 = 3600,
+    H6 = 21600,
+    D1 = 86400,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ServerTime {
+    pub iso: UtcDateTime,
+    pub epoch: f64,
+}
+
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+/// Client of coinbase public REST api.
+pub struct RestClient {
+    base_url: Url,
+    http: reqwest::Client,
+    rate_limit: RateLimit,
+}
+
+impl RestClient {
+    pub fn new() -> Self {
+        Self::with_base_url(Url::parse(REST_URL).unwrap(), PUBLIC_INTERVAL)
+    }
+
+    /// `base_url` can point to a sandbox or a local stand-in server.
+    pub fn with_base_url(base_url: Url, interval: Duration) -> Self {
+        Self {
+            base_url,
+            http: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .unwrap(),
+            rate_limit: RateLimit::new(interval),
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &mut self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, RestError> {
+        let url = self.base_url.join(path).map_err(RestError::InvalidUrl)?;
+        self.rate_limit.wait().await;
+        log::trace!("GET {} {:?}", url, query);
+        let response = self
+            .http
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(RestError::RequestFailed)?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(RestError::RequestFailed)?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<ErrorMessage>(&body)
+                .map(|e| e.message)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+            return Err(RestError::Status {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        serde_json::from_slice(&body).map_err(RestError::InvalidContent)
+    }
+
+    pub async fn products(&mut self) -> Result<Vec<Product>, RestError> {
+        self.get("products", &[]).await
+    }
+
+    pub async fn product(&mut self, product_id: &str) -> Result<Product, RestError> {
+        self.get(&format!("products/{}", product_id), &[]).await
+    }
+
+    /// Best bid and ask only.
+    pub async fn book_level1(
+        &mut self,
+        product_id: &str,
+    ) -> Result<Book<AggregatedRecord>, RestError> {
+        self.book_at(product_id, 1).await
+    }
+
+    /// Level 2 with `AggregatedRecord` or level 3 with `OrderRecord`.
+    pub async fn book<R: BookRecord>(&mut self, product_id: &str) -> Result<Book<R>, RestError> {
+        self.book_at(product_id, R::LEVEL).await
+    }
+
+    async fn book_at<R: BookRecord>(
+        &mut self,
+        product_id: &str,
+        level: u8,
+    ) -> Result<Book<R>, RestError> {
+        self.get(
+            &format!("products/{}/book", product_id),
+            &[("level", level.to_string())],
+        )
+        .await
+    }
+
+    /// Latest trades, newest first.
+    pub async fn trades(&mut self, product_id: &str) -> Result<Vec<Trade>, RestError> {
+        self.get(&format!("products/{}/trades", product_id), &[])
+            .await
+    }
+
+    /// At most 300 candles, newest first.
+    pub async fn candles(
+        &mut self,
+        product_id: &str,
+        start: Option<UtcDateTime>,
+        end: Option<UtcDateTime>,
+        granularity: Granularity,
+    ) -> Result<Vec<Candle>, RestError> {
+        let mut query = vec![("granularity", (granularity as u32).to_string())];
+        if let Some(start) = start {
+            query.push(("start", start.to_rfc3339()));
+        }
+        if let Some(end) = end {
+            query.push(("end", end.to_rfc3339()));
+        }
+        self.get(&format!("products/{}/candles", product_id), &query)
+            .await
+    }
+
+    pub async fn time(&mut self) -> Result<ServerTime, RestError> {
+        self.get("time", &[]).await
+    }
+}
+
+impl Default for RestClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves canned responses by request path until dropped.
+    async fn stand_in(routes: Vec<(&'static str, u16, &'static str)>) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let target = request.split_whitespace().nth(1).unwrap_or("");
+                let (status, body) = routes
+                    .iter()
+                    .find(|(path, _, _)| target == *path)
+                    .map(|(_, status, body)| (*status, *body))
+                    .unwrap_or((404, r#"{"message":"NotFound"}"#));
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn public_endpoints() {
+        let url = stand_in(vec![
+            (
+                "/products",
+                200,
+                r#"[{"id":"BTC-USD","base_currency":"BTC","quote_currency":"USD","quote_increment":"0.01","base_increment":"0.00000001","display_name":"BTC/USD","min_market_funds":"1","margin_enabled":false,"post_only":false,"limit_only":false,"cancel_only":false,"status":"online","status_message":"","auction_mode":false}]"#,
+            ),
+            (
+                "/products/BTC-USD/book?level=3",
+                200,
+                r#"{"bids":[["295.96","0.05","3b0f1225-7f84-490b-a29f-0faef9de823a"]],"asks":[["295.97","5.72036512","da863862-25f4-4868-ac41-005d11ab0a5f"]],"sequence":3,"auction_mode":false,"auction":null}"#,
+            ),
+            (
+                "/products/BTC-USD/book?level=1",
+                200,
+                r#"{"bids":[["295.96","4.39088265",2]],"asks":[["295.97","25.23542881",12]],"sequence":3}"#,
+            ),
+            (
+                "/products/BTC-USD/trades",
+                200,
+                r#"[{"time":"2014-11-07T22:19:28.578544Z","trade_id":74,"price":"10.00000000","size":"0.01000000","side":"buy"}]"#,
+            ),
+            (
+                "/products/BTC-USD/candles?granularity=60",
+                200,
+                r#"[[1415398768,0.32,4.2,0.35,4.2,12.3]]"#,
+            ),
+            (
+                "/time",
+                200,
+                r#"{"iso":"2015-01-07T23:47:25.201Z","epoch":1420674445.201}"#,
+            ),
+        ])
+        .await;
+        let mut client = RestClient::with_base_url(url, Duration::from_millis(1));
+
+        let products = client.products().await.unwrap();
+        assert_eq!(products[0].id, "BTC-USD");
+        let price = products[0].price(&"27.2".parse().unwrap()).unwrap();
+        assert_eq!(price.to_string(), "27.20");
+
+        let book = client.book::<OrderRecord>("BTC-USD").await.unwrap();
+        assert_eq!(book.sequence, 3);
+        assert_eq!(book.asks[0].1.to_string(), "5.72036512");
+
+        let book = client.book_level1("BTC-USD").await.unwrap();
+        assert_eq!(book.asks[0].2, 12);
+
+        let trades = client.trades("BTC-USD").await.unwrap();
+        assert_eq!((trades[0].trade_id, trades[0].side), (74, Side::Buy));
+        assert_eq!(trades[0].price.to_string(), "10.00000000");
+
+        let candles = client
+            .candles("BTC-USD", None, None, Granularity::M1)
+            .await
+            .unwrap();
+        assert_eq!(candles[0].0, 1415398768);
+
+        assert_eq!(client.time().await.unwrap().iso.timestamp(), 1420674445);
+
+        match client.product("ETH-USD").await {
+            Err(RestError::Status { status, message }) => {
+                assert_eq!((status, message.as_str()), (404, "NotFound"))
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
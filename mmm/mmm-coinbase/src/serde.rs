@@ -1,29 +1,41 @@
-use mmm_core::{collections::book::OrderPrice, serde::empty_string_is_none};
+use mmm_core::{
+    collections::{book::OrderPrice, fee::Rate},
+    serde::empty_string_is_none,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::decimal::Decimal;
 use crate::websocket::{Decrement, OrderProfile, PartType};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FeeRates {
-    #[serde(default, with = "empty_string_is_none")]
-    pub maker: Option<String>,
-    #[serde(default, with = "empty_string_is_none")]
-    pub taker: Option<String>,
+    #[serde(default, rename = "maker_fee_rate", with = "empty_string_is_none")]
+    pub maker: Option<Decimal>,
+    #[serde(default, rename = "taker_fee_rate", with = "empty_string_is_none")]
+    pub taker: Option<Decimal>,
 }
 
 impl FeeRates {
-    fn maker(fee_rate: String) -> Self {
+    fn maker(fee_rate: Decimal) -> Self {
         Self {
             maker: Some(fee_rate),
             taker: None,
         }
     }
-    fn taker(fee_rate: String) -> Self {
+    fn taker(fee_rate: Decimal) -> Self {
         Self {
             maker: None,
             taker: Some(fee_rate),
         }
     }
+
+    /// Fee model charging these rates of the notional, a missing rate is zero.
+    pub fn fee_model(&self) -> Rate<Decimal> {
+        Rate {
+            maker: self.maker.unwrap_or_default(),
+            taker: self.taker.unwrap_or_default(),
+        }
+    }
 }
 
 serde_with::serde_conv!(
@@ -35,8 +47,8 @@ serde_with::serde_conv!(
                  part_type,
                  fee_rate,
             }: &OrderProfile| match part_type {
-                PartType::Maker => FeeRates::maker(fee_rate.to_string()),
-                PartType::Taker => FeeRates::taker(fee_rate.to_string()),
+                PartType::Maker => FeeRates::maker(*fee_rate),
+                PartType::Taker => FeeRates::taker(*fee_rate),
             },
         )
     },
@@ -48,7 +60,7 @@ serde_with::serde_conv!(
                 fee_rate,
             }),
             (None, Some(fee_rate)) => Some(OrderProfile {
-                part_type: PartType::Maker,
+                part_type: PartType::Taker,
                 fee_rate,
             }),
             (Some(_), Some(_)) => unreachable!(),
@@ -92,15 +104,15 @@ serde_with::serde_conv!(
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
 pub struct RawDecrement {
     #[serde(default, with = "empty_string_is_none")]
-    price: Option<String>,
+    price: Option<Decimal>,
     #[serde(default, with = "empty_string_is_none")]
-    old_size: Option<String>,
+    old_size: Option<Decimal>,
     #[serde(default, with = "empty_string_is_none")]
-    new_size: Option<String>,
+    new_size: Option<Decimal>,
     #[serde(default, with = "empty_string_is_none")]
-    old_funds: Option<String>,
+    old_funds: Option<Decimal>,
     #[serde(default, with = "empty_string_is_none")]
-    new_funds: Option<String>,
+    new_funds: Option<Decimal>,
 }
 
 serde_with::serde_conv!(
@@ -113,9 +125,9 @@ serde_with::serde_conv!(
                 old_size,
                 new_size,
             } => RawDecrement {
-                price: Some(price.to_string()),
-                old_size: Some(old_size.to_string()),
-                new_size: Some(new_size.to_string()),
+                price: Some(*price),
+                old_size: Some(*old_size),
+                new_size: Some(*new_size),
                 old_funds: None,
                 new_funds: None,
             },
@@ -126,13 +138,13 @@ serde_with::serde_conv!(
                 price: None,
                 old_size: None,
                 new_size: None,
-                old_funds: Some(old_funds.to_string()),
-                new_funds: Some(new_funds.to_string()),
+                old_funds: Some(*old_funds),
+                new_funds: Some(*new_funds),
             },
             Decrement::MarketSize { old_size, new_size } => RawDecrement {
                 price: None,
-                old_size: Some(old_size.to_string()),
-                new_size: Some(new_size.to_string()),
+                old_size: Some(*old_size),
+                new_size: Some(*new_size),
                 old_funds: None,
                 new_funds: None,
             },
@@ -166,14 +178,14 @@ serde_with::serde_conv!(
 
 serde_with::serde_conv!(
     pub(crate) OrderPriceConv,
-    OrderPrice<String>,
-    |order_price: &OrderPrice<String>| {
+    OrderPrice<Decimal>,
+    |order_price: &OrderPrice<Decimal>| {
         match order_price {
             OrderPrice::Market => None,
-            OrderPrice::Limit(price) => Some(price.to_string()),
+            OrderPrice::Limit(price) => Some(*price),
         }
     },
-    |price: String| -> Result<_, std::convert::Infallible> {
+    |price: Decimal| -> Result<_, std::convert::Infallible> {
         Ok(OrderPrice::Limit(price))
     }
 );
@@ -0,0 +1,266 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+pub const ACCESS_KEY_ENV: &str = "AWS_ACCESS_KEY_ID";
+pub const SECRET_KEY_ENV: &str = "AWS_SECRET_ACCESS_KEY";
+
+#[derive(Error, Debug)]
+pub enum SinkError {
+    #[error("io error. ({0:?})")]
+    Io(#[from] std::io::Error),
+    #[error("environment variable {0} is not set.")]
+    MissingEnv(&'static str),
+    #[error("invalid object url. ({0:?})")]
+    InvalidUrl(#[source] url::ParseError),
+    #[error("failed to request object store. ({0:?})")]
+    RequestFailed(#[source] reqwest::Error),
+    #[error("object store responded with {status}. ({message})")]
+    Status { status: u16, message: String },
+}
+
+/// Destination of finished recordings.
+/// `key` is a '/' separated path such as "2021-08-25/120000/BTC-USD/full-1.json.zst".
+#[async_trait]
+pub trait Sink: Send + Sync {
+    async fn put(&self, key: &str, path: &Path) -> Result<(), SinkError>;
+}
+
+/// Copies recordings under another directory, e.g. a mounted volume.
+#[derive(Debug, Clone)]
+pub struct LocalSink {
+    root: PathBuf,
+}
+
+impl LocalSink {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+#[async_trait]
+impl Sink for LocalSink {
+    async fn put(&self, key: &str, path: &Path) -> Result<(), SinkError> {
+        let target = key.split('/').fold(self.root.clone(), |p, s| p.join(s));
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(path, target).await?;
+        Ok(())
+    }
+}
+
+/// Uploads recordings to an S3 compatible object store with path-style `PUT` signed by AWS signature v4.
+/// `endpoint` can point to any compatible server, e.g. minio or a local stand-in.
+pub struct S3Sink {
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    http: reqwest::Client,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes an object key except unreserved characters and '/'.
+fn uri_encode(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+impl S3Sink {
+    pub fn new(
+        endpoint: Url,
+        bucket: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Self {
+        Self {
+            endpoint,
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Reads keys from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+    pub fn from_env(endpoint: Url, bucket: &str, region: &str) -> Result<Self, SinkError> {
+        let var = |name| std::env::var(name).map_err(|_| SinkError::MissingEnv(name));
+        Ok(Self::new(
+            endpoint,
+            bucket,
+            region,
+            &var(ACCESS_KEY_ENV)?,
+            &var(SECRET_KEY_ENV)?,
+        ))
+    }
+
+    /// (x-amz-date, authorization) of a `PUT` to `path` of a body with `payload_hash`.
+    fn authorization(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        host: &str,
+        path: &str,
+        payload_hash: &str,
+    ) -> (String, String) {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            path, host, payload_hash, amz_date, signed_headers, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+        let key = signing_key(&self.secret_key, &date, &self.region, "s3");
+        let signature = hex(&hmac_sha256(&key, &string_to_sign));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, signed_headers, signature
+        );
+        (amz_date, authorization)
+    }
+}
+
+#[async_trait]
+impl Sink for S3Sink {
+    async fn put(&self, key: &str, path: &Path) -> Result<(), SinkError> {
+        let body = tokio::fs::read(path).await?;
+        let payload_hash = hex(&Sha256::digest(&body));
+        let object_path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.bucket,
+            uri_encode(key)
+        );
+        let url = self
+            .endpoint
+            .join(&object_path)
+            .map_err(SinkError::InvalidUrl)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => String::new(),
+        };
+        let (amz_date, authorization) =
+            self.authorization(chrono::Utc::now(), &host, &object_path, &payload_hash);
+        log::trace!("PUT {}", url);
+        let response = self
+            .http
+            .put(url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .map_err(SinkError::RequestFailed)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(SinkError::Status {
+                status: status.as_u16(),
+                message: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn aws_signing_key() {
+        // NOTE: example from the AWS signature v4 documentation.
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            "20120215",
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            hex(&key),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(uri_encode("a b/c+d.zst"), "a%20b/c%2Bd.zst");
+    }
+
+    #[tokio::test]
+    async fn put_to_stand_in() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = vec![0; 4096];
+            while !String::from_utf8_lossy(&request).ends_with("payload") {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let dir = std::env::temp_dir().join(format!("mmm-sink-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("full-1.json.zst");
+        tokio::fs::write(&path, b"payload").await.unwrap();
+
+        let sink = S3Sink::new(endpoint, "bucket", "us-east-1", "ak", "sk");
+        sink.put("2021-08-25/120000/BTC-USD/full-1.json.zst", &path)
+            .await
+            .unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("PUT /bucket/2021-08-25/120000/BTC-USD/full-1.json.zst "));
+        assert!(request.contains("AWS4-HMAC-SHA256 Credential=ak/"));
+
+        let local = LocalSink::new(dir.join("archive"));
+        local.put("a/b.zst", &path).await.unwrap();
+        assert_eq!(
+            tokio::fs::read(dir.join("archive").join("a").join("b.zst"))
+                .await
+                .unwrap(),
+            b"payload"
+        );
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use crate::decimal::Decimal;
+use crate::websocket::{
+    Decrement, Decremented, Done, Full, MarketQuote, Match, OrderProfile, PartType, Quote, Received,
+};
+use mmm_core::collections::{
+    account::Account,
+    book::{Error, OrderPrice},
+    fee::{FeeModel, Rate},
+    Side,
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+type UtcDateTime = chrono::DateTime<chrono::Utc>;
+
+/// Keys of an account fed from the user channel.
+/// Orders are keyed by order id, fills by (trade id, order id) since both sides of a self-trade are ours,
+/// and cancels by the sequence of the message which reduced the order.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum EventId {
+    Order(Uuid),
+    Fill { trade_id: u64, order_id: Uuid },
+    Sequence(u64),
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct OrderInfo {
+    pub time: UtcDateTime,
+    pub client_oid: Option<Uuid>,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Fill {
+    pub product_id: String,
+    pub time: UtcDateTime,
+    pub trade_id: u64,
+    pub order_id: Uuid,
+    pub side: Side,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub part_type: PartType,
+    pub fee_rate: Option<Decimal>,
+}
+
+impl Fill {
+    pub fn order_profile(&self) -> Option<OrderProfile> {
+        self.fee_rate.map(|fee_rate| OrderProfile {
+            part_type: self.part_type,
+            fee_rate,
+        })
+    }
+
+    /// Fee of the fill at its fee rate, see `FeeRates::fee_model`.
+    pub fn fee(&self) -> Option<Decimal> {
+        let rate = self.fee_rate?;
+        let mut model = Rate {
+            maker: rate,
+            taker: rate,
+        };
+        Some(model.fee(self.side, &self.price, &self.size, self.part_type.into()))
+    }
+}
+
+pub type UserOrders = Account<EventId, Decimal, Decimal, OrderInfo>;
+
+/// Tracks our orders per product from the authenticated user channel.
+/// Orders received before subscribing are unknown and their messages are ignored.
+/// NOTE: market orders with funds only have no size to track and are ignored as well.
+#[derive(Debug, Default)]
+pub struct UserAccount {
+    accounts: HashMap<String, UserOrders>,
+    fills: Vec<Fill>,
+}
+
+impl UserAccount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(&self, product_id: &str) -> Option<&UserOrders> {
+        self.accounts.get(product_id)
+    }
+
+    pub fn product_ids(&self) -> impl Iterator<Item = &str> {
+        self.accounts.keys().map(String::as_str)
+    }
+
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+
+    pub fn handle(&mut self, full: &Full) {
+        match full {
+            Full::Received(received) => self.received(received),
+            Full::Open(_) => {}
+            Full::Match(m) => self.matched(m),
+            Full::Done(done) => self.done(done),
+            Full::Decremented(decremented) => self.decremented(decremented),
+        }
+    }
+
+    fn received(&mut self, received: &Received) {
+        let (price, size) = match &received.quote {
+            Quote::Limit(quote) => (OrderPrice::Limit(quote.price), quote.remaining_size),
+            Quote::Market(MarketQuote::Size { size } | MarketQuote::Both { size, .. }) => {
+                (OrderPrice::Market, *size)
+            }
+            Quote::Market(MarketQuote::Funds { .. }) => {
+                log::debug!("funds only market order is not tracked. {:?}", received);
+                return;
+            }
+        };
+        let info = OrderInfo {
+            time: received.time,
+            client_oid: received.client_oid,
+        };
+        if let Err(e) = self
+            .accounts
+            .entry(received.product_id.clone())
+            .or_default()
+            .order(
+                EventId::Order(received.order_id),
+                received.side.into(),
+                price,
+                size,
+                info,
+            )
+        {
+            log::debug!("{:?} {:?}", e, received);
+        }
+    }
+
+    fn matched(&mut self, m: &Match) {
+        let account = match self.accounts.get_mut(&m.product_id) {
+            Some(account) => account,
+            None => return,
+        };
+        for (order_id, part_type) in [
+            (m.maker_order_id, PartType::Maker),
+            (m.taker_order_id, PartType::Taker),
+        ] {
+            let id = EventId::Fill {
+                trade_id: m.trade_id,
+                order_id,
+            };
+            match account.execute(id, EventId::Order(order_id), m.price, m.size) {
+                Ok(order) => {
+                    let fee_rate = match &m.order_profile {
+                        Some(profile) if profile.part_type == part_type => Some(profile.fee_rate),
+                        _ => None,
+                    };
+                    self.fills.push(Fill {
+                        product_id: m.product_id.clone(),
+                        time: m.time,
+                        trade_id: m.trade_id,
+                        order_id,
+                        side: order.side,
+                        price: order.price,
+                        size: order.quantity,
+                        part_type,
+                        fee_rate,
+                    });
+                }
+                Err(Error::OrderNotFound) => {}
+                Err(e) => log::debug!("{:?} {:?}", e, m),
+            }
+        }
+    }
+
+    fn done(&mut self, done: &Done) {
+        if let Some(account) = self.accounts.get_mut(&done.product_id) {
+            // NOTE: a filled order can still have residual size, e.g. by self-trade prevention.
+            if let Ok(order) = account.cancel(
+                EventId::Sequence(done.sequence),
+                EventId::Order(done.order_id),
+                None,
+            ) {
+                log::debug!("{:?} cancelled {:?}", done.reason, order);
+            }
+        }
+    }
+
+    fn decremented(&mut self, decremented: &Decremented) {
+        let account = match self.accounts.get_mut(&decremented.product_id) {
+            Some(account) => account,
+            None => return,
+        };
+        let (old_size, new_size) = match &decremented.decrement {
+            Decrement::Limit {
+                old_size, new_size, ..
+            }
+            | Decrement::MarketSize { old_size, new_size } => (*old_size, *new_size),
+            Decrement::MarketFunds { .. } => return,
+        };
+        if old_size <= new_size {
+            return;
+        }
+        if let Err(e) = account.cancel(
+            EventId::Sequence(decremented.sequence),
+            EventId::Order(decremented.order_id),
+            Some(old_size - new_size),
+        ) {
+            log::debug!("{:?} {:?}", e, decremented);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn track_user_channel() {
+        let msgs = [
+            r#"{"type":"received","side":"buy","product_id":"BTC-USD","time":"2021-08-25T18:17:39.150151Z","sequence":10,"order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","order_type":"limit","size":"10.00","price":"100.5","client_oid":"","user_id":"u","profile_id":"p"}"#,
+            r#"{"type":"open","side":"buy","product_id":"BTC-USD","time":"2021-08-25T18:17:39.150151Z","sequence":11,"price":"100.5","order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","remaining_size":"10.00","user_id":"u","profile_id":"p"}"#,
+            r#"{"type":"match","trade_id":7,"maker_order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","taker_order_id":"61b0a035-f130-439c-8b8e-554f102d572d","side":"buy","size":"4.25","price":"100.5","product_id":"BTC-USD","sequence":12,"time":"2021-08-25T18:17:40.150151Z","user_id":"u","profile_id":"p","maker_user_id":"u","maker_profile_id":"p","maker_fee_rate":"0.004"}"#,
+            r#"{"type":"change","side":"buy","product_id":"BTC-USD","time":"2021-08-25T18:17:41.150151Z","sequence":13,"order_id":"abf98f5b-878e-4ccb-bbdc-3cb9c2ecdc67","price":"100.5","old_size":"5.75","new_size":"5","user_id":"u","profile_id":"p"}"#,
+        ];
+        let mut user = UserAccount::new();
+        for msg in msgs {
+            user.handle(&serde_json::from_str::<Full>(msg).unwrap());
+        }
+        let d = |s: &str| s.parse::<Decimal>().unwrap();
+
+        let fill = &user.fills()[0];
+        assert_eq!(user.fills().len(), 1);
+        assert_eq!(
+            (fill.side, fill.price, fill.size),
+            (Side::Bid, d("100.5"), d("4.25"))
+        );
+        assert_eq!(
+            fill.order_profile(),
+            Some(OrderProfile {
+                part_type: PartType::Maker,
+                fee_rate: d("0.004")
+            })
+        );
+        assert_eq!(fill.fee(), Some(d("1.7085")));
+
+        let account = user.account("BTC-USD").unwrap();
+        assert_eq!(account.executed().clone().total_bid_volume(), d("4.25"));
+        assert_eq!(account.cancelled().clone().total_bid_volume(), d("0.75"));
+        assert_eq!(account.pending().clone().total_bid_volume(), d("5"));
+    }
+}
@@ -1,10 +1,12 @@
 // TODO create every variant of MarketQuote in sandbox
+use crate::auth::{Auth, Credentials};
+use crate::decimal::Decimal;
 use crate::serde::{DecrementConv, OptOrderProfileConv, OrderPriceConv};
 use async_trait::async_trait;
 use futures::{future::ready, TryStream, TryStreamExt};
 use futures_util::{Sink, SinkExt, Stream, StreamExt};
 use mmm_core::{
-    collections::book::OrderPrice,
+    collections::{self, book::OrderPrice, fee::Liquidity},
     serde::{deny_empty_string, empty_string_is_none},
 };
 use serde::{Deserialize, Serialize};
@@ -29,6 +31,15 @@ pub enum Side {
     Sell,
 }
 
+impl From<Side> for collections::Side {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => collections::Side::Bid,
+            Side::Sell => collections::Side::Ask,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "snake_case")]
@@ -60,19 +71,26 @@ pub enum PartType {
     Taker,
 }
 
+impl From<PartType> for Liquidity {
+    fn from(part_type: PartType) -> Self {
+        match part_type {
+            PartType::Maker => Liquidity::Maker,
+            PartType::Taker => Liquidity::Taker,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct OrderProfile {
     pub part_type: PartType,
-    pub fee_rate: String,
+    pub fee_rate: Decimal,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
 pub struct LimitQuote {
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub price: String,
-    #[serde(deserialize_with = "deny_empty_string")]
+    pub price: Decimal,
     #[serde(alias = "size")]
-    pub remaining_size: String,
+    pub remaining_size: Decimal,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
@@ -82,20 +100,9 @@ pub enum MarketQuote {
     // NOTE: A market sell order can also specify the funds.
     // If funds is specified, it will limit the sell to the amount of funds specified.
     // You can use funds with sell orders to limit the amount of quote currency funds received.
-    Both {
-        #[serde(deserialize_with = "deny_empty_string")]
-        size: String,
-        #[serde(deserialize_with = "deny_empty_string")]
-        funds: String,
-    },
-    Size {
-        #[serde(deserialize_with = "deny_empty_string")]
-        size: String,
-    },
-    Funds {
-        #[serde(deserialize_with = "deny_empty_string")]
-        funds: String,
-    },
+    Both { size: Decimal, funds: Decimal },
+    Size { size: Decimal },
+    Funds { funds: Decimal },
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
@@ -117,20 +124,20 @@ pub enum Quote {
 // Change messages for limit orders will always have a price specified.
 pub enum Decrement {
     Limit {
-        price: String,
-        old_size: String,
-        new_size: String,
+        price: Decimal,
+        old_size: Decimal,
+        new_size: Decimal,
     },
     // CASE: (Side::Buy, MarketQuote::Funds)
     MarketFunds {
-        old_funds: String,
-        new_funds: String,
+        old_funds: Decimal,
+        new_funds: Decimal,
     },
     // CASE: (Side::Buy, MarketQuote::Both | MarketQuote::Size)
     // CASE: (Side::Sell, _)
     MarketSize {
-        old_size: String,
-        new_size: String,
+        old_size: Decimal,
+        new_size: Decimal,
     },
 }
 
@@ -167,8 +174,8 @@ pub struct Open {
     pub user_profile: Option<UserProfile>,
 }
 
-fn zero() -> String {
-    String::from("0")
+fn zero() -> Decimal {
+    Decimal::ZERO
 }
 
 #[skip_serializing_none]
@@ -182,12 +189,12 @@ pub struct Done {
     pub side: Side,
     pub reason: Reason,
     #[serde(default = "OrderPrice::market", with = "OrderPriceConv")]
-    pub price: OrderPrice<String>,
+    pub price: OrderPrice<Decimal>,
     // NOTE: market orders will not have a price and remaining_size field as they are never on the open order book at a given price.
     // BUG: `Done` message of market order which has both size and funds field has `remaining_size` field.
     // BUG: `Done` message of market order which has 0 size and 0 funds has `remaining_size` field with arbitrary value.
     #[serde(default = "zero")]
-    pub remaining_size: String,
+    pub remaining_size: Decimal,
     #[serde(flatten)]
     pub user_profile: Option<UserProfile>,
 }
@@ -203,10 +210,8 @@ pub struct Match {
     pub maker_order_id: Uuid,
     pub taker_order_id: Uuid,
     pub side: Side,
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub price: String,
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub size: String,
+    pub price: Decimal,
+    pub size: Decimal,
     #[serde(flatten)]
     pub user_profile: Option<UserProfile>,
     #[serde(with = "OptOrderProfileConv", flatten)]
@@ -284,12 +289,9 @@ pub struct Activate {
     pub stop_type: StopType,
     pub side: Side,
     // TODO find possible combinations of (stop_price, size, funds)
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub stop_price: String,
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub size: String,
-    #[serde(deserialize_with = "deny_empty_string")]
-    pub funds: String,
+    pub stop_price: Decimal,
+    pub size: Decimal,
+    pub funds: Decimal,
     pub private: bool,
     #[serde(flatten)]
     pub user_profile: Option<UserProfile>,
@@ -383,15 +385,95 @@ impl Channel {
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
-#[serde(deny_unknown_fields)]
-#[serde(tag = "type", rename = "subscribe")]
+#[serde(into = "SubscribeMessage", try_from = "SubscribeMessage")]
 pub struct Subscribe {
     pub product_ids: Vec<String>,
     pub channels: Vec<Channel>,
+    pub auth: Option<Auth>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SubscribeType {
+    Subscribe,
+}
+
+// Subscribe as sent, with the fields of `Auth` inline.
+// NOTE: serde takes neither a flattened `Auth` nor a struct tag as a known field with `deny_unknown_fields`.
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubscribeMessage {
+    r#type: SubscribeType,
+    product_ids: Vec<String>,
+    channels: Vec<Channel>,
+    signature: Option<String>,
+    key: Option<String>,
+    passphrase: Option<String>,
+    timestamp: Option<String>,
+}
+
+impl From<Subscribe> for SubscribeMessage {
+    fn from(subscribe: Subscribe) -> Self {
+        let (signature, key, passphrase, timestamp) = match subscribe.auth {
+            Some(auth) => (
+                Some(auth.signature),
+                Some(auth.key),
+                Some(auth.passphrase),
+                Some(auth.timestamp),
+            ),
+            None => (None, None, None, None),
+        };
+        Self {
+            r#type: SubscribeType::Subscribe,
+            product_ids: subscribe.product_ids,
+            channels: subscribe.channels,
+            signature,
+            key,
+            passphrase,
+            timestamp,
+        }
+    }
+}
+
+impl TryFrom<SubscribeMessage> for Subscribe {
+    type Error = CBError;
+    fn try_from(msg: SubscribeMessage) -> Result<Self, Self::Error> {
+        let auth = match (msg.signature, msg.key, msg.passphrase, msg.timestamp) {
+            (Some(signature), Some(key), Some(passphrase), Some(timestamp)) => Some(Auth {
+                signature,
+                key,
+                passphrase,
+                timestamp,
+            }),
+            (None, None, None, None) => None,
+            _ => {
+                return Err(CBError::InvalidMessageContent(
+                    "incomplete subscribe signature.".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            product_ids: msg.product_ids,
+            channels: msg.channels,
+            auth,
+        })
+    }
 }
 
 impl Subscribe {
+    pub fn new(product_ids: Vec<String>, channels: Vec<Channel>) -> Self {
+        Self {
+            product_ids,
+            channels,
+            auth: None,
+        }
+    }
+    /// Adds the channels and products of `other`, and takes its auth if it is signed.
     pub fn merge(&mut self, mut other: Subscribe) {
+        if other.auth.is_some() {
+            self.auth = other.auth;
+        }
         self.channels.append(&mut other.channels);
         self.channels.dedup();
         self.product_ids.append(&mut other.product_ids);
@@ -403,12 +485,25 @@ impl Subscribe {
 pub trait CBSink: Sink<TMessage> + Unpin + Send + Sync {
     async fn subscribe(&mut self, subscribe: &Subscribe) -> Result<(), CBError> {
         let sub_json = serde_json::to_string(subscribe).unwrap();
-        log::trace!("{:?}", sub_json);
+        // NOTE: Debug of Auth hides the signature and passphrase, the json does not.
+        log::trace!("{:?}", subscribe);
         self.send(TMessage::Text(sub_json))
             .await
             .map_err(|_| CBError::SendFailed)?;
         Ok(())
     }
+    /// Signs `subscribe` with a fresh timestamp before sending, required for the user channel.
+    async fn subscribe_authenticated(
+        &mut self,
+        subscribe: &Subscribe,
+        credentials: &Credentials,
+    ) -> Result<(), CBError> {
+        let subscribe = Subscribe {
+            auth: Some(credentials.auth()),
+            ..subscribe.clone()
+        };
+        self.subscribe(&subscribe).await
+    }
 }
 
 #[async_trait]
@@ -479,5 +574,35 @@ mod tests {
         let open = r#"{"type":"open","side":"sell","product_id":"ALGO-USD","time":"2021-08-25T18:30:31.402926Z","sequence":3091541928,"price":"1.0757","order_id":"b118f276-be5c-434b-870f-78fb8a30b553","remaining_size":"140"}"#;
         println!("{:?}", serde_json::from_str::<Open>(open).unwrap());
         serde_json::from_str::<CBMessage>(open).unwrap();
+
+        let stored =
+            serde_json::to_string(&serde_json::from_str::<Received>(received).unwrap()).unwrap();
+        assert!(stored.contains(r#""price":"2354.7""#));
+        assert!(stored.contains(r#""remaining_size":"6.18088976""#));
+    }
+
+    #[test]
+    fn subscribe_json() {
+        let mut subscribe = Subscribe::new(
+            vec!["BTC-USD".into()],
+            vec![Channel::Name(ChannelType::Full)],
+        );
+        let json = serde_json::to_string(&subscribe).unwrap();
+        assert!(!json.contains("signature"));
+        assert_eq!(serde_json::from_str::<Subscribe>(&json).unwrap(), subscribe);
+
+        subscribe.auth = Some(
+            Credentials::new("key", "c2VjcmV0", "pass")
+                .unwrap()
+                .auth_at(1),
+        );
+        let json = serde_json::to_string(&subscribe).unwrap();
+        assert!(json.contains(r#""key":"key""#));
+        assert_eq!(serde_json::from_str::<Subscribe>(&json).unwrap(), subscribe);
+
+        let unknown = r#"{"type":"subscribe","product_ids":[],"channels":[],"unknown":1}"#;
+        assert!(serde_json::from_str::<Subscribe>(unknown).is_err());
+        let unsigned = r#"{"type":"subscribe","product_ids":[],"channels":[],"key":"key"}"#;
+        assert!(serde_json::from_str::<Subscribe>(unsigned).is_err());
     }
 }
@@ -0,0 +1,124 @@
+//! Heavy-cancel workload on a few crowded price levels.
+//! It only uses the public `OrderBook` api, so the same file can be run on an older tree to compare.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use mmm_core::collections::{
+    book::{OrderBook, PriceTimePriority},
+    Side,
+};
+
+const LEVELS: u64 = 4;
+const RESTING: u64 = 20_000;
+const OPERATIONS: usize = 100_000;
+
+/// xorshift, to keep the workload the same between runs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Insert(u64, Side, u64, u64),
+    Cancel(u64),
+    Execute(Side, u64),
+}
+
+/// 90% of resting orders are cancelled from anywhere in the queue, the rest is executed from the top.
+fn workload() -> Vec<Op> {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut live = Vec::new();
+    let mut ops = Vec::with_capacity(OPERATIONS);
+    let mut next_id = 0;
+    while ops.len() < OPERATIONS {
+        let r = rng.next();
+        if live.len() < RESTING as usize || r.is_multiple_of(3) {
+            let side = if r.is_multiple_of(2) {
+                Side::Ask
+            } else {
+                Side::Bid
+            };
+            let price = match side {
+                Side::Ask => 1_000 + r % LEVELS,
+                Side::Bid => 1_000 - LEVELS + r % LEVELS,
+            };
+            ops.push(Op::Insert(next_id, side, price, 1 + r % 100));
+            live.push(next_id);
+            next_id += 1;
+        } else if !r.is_multiple_of(10) {
+            let index = (r as usize / 10) % live.len();
+            ops.push(Op::Cancel(live.swap_remove(index)));
+        } else {
+            let side = if r.is_multiple_of(4) {
+                Side::Ask
+            } else {
+                Side::Bid
+            };
+            ops.push(Op::Execute(side, 1 + r % 50));
+        }
+    }
+    ops
+}
+
+fn replay(ops: &[Op]) -> OrderBook<u64, u64, u64> {
+    let mut book = OrderBook::new();
+    for op in ops {
+        match *op {
+            Op::Insert(id, side, price, quantity) => {
+                book.insert(id, side, price, quantity, ()).unwrap();
+            }
+            Op::Cancel(id) => {
+                book.remove(&id);
+            }
+            Op::Execute(side, quantity) => {
+                let top = match side {
+                    Side::Ask => book.ask_top().map(|(k, _)| *k),
+                    Side::Bid => book.bid_top().map(|(k, _)| *k),
+                };
+                if let Some(id) = top {
+                    book.reduce(&id, quantity);
+                }
+            }
+        }
+    }
+    book
+}
+
+fn run(ops: &[Op]) -> u64 {
+    let book = replay(ops);
+    book.total_ask_volume() + book.total_bid_volume()
+}
+
+/// Lists every order of the crowded levels and the queue position of the last one,
+/// which walks the ids cancelled in the middle of the queue unless they are gone.
+fn depth(book: &mut OrderBook<u64, u64, u64>) -> usize {
+    let mut count = 0;
+    for level in 0..LEVELS {
+        let orders = book.ask_orders_at(1_000 + level, None, PriceTimePriority::BothAsc);
+        count += orders.orders.len();
+        if let Some((id, _)) = orders.orders.last() {
+            let id = *id;
+            count += book.queue_position(&id).map_or(0, |p| p.count);
+        }
+    }
+    count
+}
+
+fn order_queue(c: &mut Criterion) {
+    let ops = workload();
+    c.bench_function("heavy_cancel", |b| {
+        b.iter_batched(|| &ops, |ops| black_box(run(ops)), BatchSize::SmallInput)
+    });
+    let mut book = replay(&ops);
+    c.bench_function("depth_after_cancels", |b| {
+        b.iter(|| black_box(depth(&mut book)))
+    });
+}
+
+criterion_group!(benches, order_queue);
+criterion_main!(benches);
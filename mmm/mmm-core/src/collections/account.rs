@@ -57,14 +57,53 @@ impl<K, I> CancelInfo<K, I> {
 /// I: info
 pub type CancelOrder<K, L, Q, I> = Order<OrderPrice<L>, Q, CancelInfo<K, I>>;
 
-/// Account has three generic parameters: K, L, Q, I
+/// StopInfo has two generic parameters: L, I
+/// L: limit price
+/// I: info
+/// order_price is the price of the pending order once triggered, Market for a stop and Limit for a stop-limit.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct StopInfo<L, I> {
+    pub order_price: OrderPrice<L>,
+    pub info: I,
+}
+
+/// StopOrder has three generic parameters: L, Q, I
+/// L: stop price
+/// Q: volume
+/// I: info
+pub type StopOrder<L, Q, I> = Order<L, Q, StopInfo<L, I>>;
+
+/// TriggerInfo has three generic parameters: L, I, T
+/// L: limit price
+/// I: info
+/// T: time
+/// trigger_price is the last trade price which triggered the stop at `time`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct TriggerInfo<L, I, T> {
+    pub order_price: OrderPrice<L>,
+    pub trigger_price: L,
+    pub time: T,
+    pub info: I,
+}
+
+/// TriggerOrder has four generic parameters: L, Q, I, T
+/// L: stop price
+/// Q: volume
+/// I: info
+/// T: time
+pub type TriggerOrder<L, Q, I, T> = Order<L, Q, TriggerInfo<L, I, T>>;
+
+/// Account has five generic parameters: K, L, Q, I, T
 /// Pending and cancelled will have OrderPrice<L> as price since price L was not determined
+/// Stops wait for a trade at their stop price, then become pending with the same id.
+/// A bid(buy) stop triggers at or above its stop price, an ask(sell) stop at or below.
 /// K: origin id
 /// L: limit price
 /// Q: volume
 /// I: info
+/// T: time of triggers
 #[derive(Clone, Debug)]
-pub struct Account<K, L, Q, I = ()>
+pub struct Account<K, L, Q, I = (), T = u64>
 where
     K: Eq + Hash,
     L: Ord,
@@ -72,9 +111,11 @@ where
     pending: OrderBook<K, OrderPrice<L>, Q, I>,
     executed: OrderBook<K, L, Q, ExecInfo<K, L, I>>,
     cancelled: OrderBook<K, OrderPrice<L>, Q, CancelInfo<K, I>>,
+    stops: OrderBook<K, L, Q, StopInfo<L, I>>,
+    triggered: OrderBook<K, L, Q, TriggerInfo<L, I, T>>,
 }
 
-impl<K, L, Q, I> Default for Account<K, L, Q, I>
+impl<K, L, Q, I, T> Default for Account<K, L, Q, I, T>
 where
     K: Eq + Hash,
     L: Ord,
@@ -84,11 +125,13 @@ where
             pending: OrderBook::new(),
             executed: OrderBook::new(),
             cancelled: OrderBook::new(),
+            stops: OrderBook::new(),
+            triggered: OrderBook::new(),
         }
     }
 }
 
-impl<K, L, Q, I> Account<K, L, Q, I>
+impl<K, L, Q, I, T> Account<K, L, Q, I, T>
 where
     K: Eq + Hash,
     L: Ord,
@@ -105,14 +148,21 @@ where
     pub fn cancelled(&self) -> &OrderBook<K, OrderPrice<L>, Q, CancelInfo<K, I>> {
         &self.cancelled
     }
+    pub fn stops(&self) -> &OrderBook<K, L, Q, StopInfo<L, I>> {
+        &self.stops
+    }
+    pub fn triggered(&self) -> &OrderBook<K, L, Q, TriggerInfo<L, I, T>> {
+        &self.triggered
+    }
 }
 
-impl<K, L, Q, I> Account<K, L, Q, I>
+impl<K, L, Q, I, T> Account<K, L, Q, I, T>
 where
     K: Eq + Hash + Debug + Clone,
     L: Ord + Debug + Clone,
     Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
     I: Clone + Debug,
+    T: Clone + Debug,
 {
     pub fn order(
         &mut self,
@@ -122,24 +172,108 @@ where
         quantity: Q,
         info: I,
     ) -> std::result::Result<(), Error> {
+        if self.stops.get(&id).is_some() {
+            return Err(Error::KeyAlreadyExists);
+        }
         self.pending
             .insert(id, side, price, quantity, info)
             .and(Ok(()))
     }
 
+    /// Places a stop order, which becomes a pending order at `order_price` once triggered.
+    pub fn stop(
+        &mut self,
+        id: K,
+        side: Side,
+        stop_price: L,
+        order_price: OrderPrice<L>,
+        quantity: Q,
+        info: I,
+    ) -> Result<&StopOrder<L, Q, I>> {
+        // a triggered id is kept in `triggered`, so it can not trigger again.
+        if self.pending.get(&id).is_some() || self.triggered.get(&id).is_some() {
+            return Err(Error::KeyAlreadyExists);
+        }
+        let stop_info = StopInfo { order_price, info };
+        self.stops.insert(id, side, stop_price, quantity, stop_info)
+    }
+
+    /// Triggers stops reached by a trade at `last_price`, from the nearest stop price and in arrival order.
+    /// Returns ids of the triggered orders, now pending.
+    pub fn trigger(&mut self, last_price: &L, time: T) -> Vec<K> {
+        let mut triggered = Vec::new();
+        loop {
+            // bid stops are sorted from the highest, so the bottom is the nearest.
+            let ids: Vec<K> = match self.stops.bid_price_bottom() {
+                Some((price, order_queue)) if price <= last_price => {
+                    order_queue.order_ids().cloned().collect()
+                }
+                _ => match self.stops.ask_price_bottom() {
+                    Some((price, order_queue)) if price >= last_price => {
+                        order_queue.order_ids().cloned().collect()
+                    }
+                    _ => break,
+                },
+            };
+            for id in ids {
+                self.activate(id.clone(), last_price, time.clone());
+                triggered.push(id);
+            }
+        }
+        triggered
+    }
+
+    fn activate(&mut self, id: K, last_price: &L, time: T) {
+        let Order {
+            side,
+            price,
+            quantity,
+            info: StopInfo { order_price, info },
+        } = self.stops.remove(&id).unwrap();
+        let trigger_info = TriggerInfo {
+            order_price: order_price.clone(),
+            trigger_price: last_price.clone(),
+            time,
+            info: info.clone(),
+        };
+        // ids of stops are neither pending nor triggered, see `order` and `stop`.
+        self.pending
+            .insert(id.clone(), side, order_price, quantity.clone(), info)
+            .unwrap();
+        self.triggered
+            .insert(id, side, price, quantity, trigger_info)
+            .unwrap();
+    }
+
     pub fn cancel(
         &mut self,
         id: K,
         origin_id: K,
         quantity: Option<Q>,
     ) -> Result<&CancelOrder<K, L, Q, I>> {
-        let order = match quantity {
+        let order = match quantity.clone() {
             Some(quantity) => self
                 .pending
                 .reduce(&origin_id, quantity)
                 .map(OrderView::to_order),
             None => self.pending.remove(&origin_id),
         };
+        // a stop not triggered yet is cancelled at its order price.
+        let order = order.or_else(|| {
+            let stop = match quantity {
+                Some(quantity) => self
+                    .stops
+                    .reduce(&origin_id, quantity)
+                    .map(OrderView::to_order),
+                None => self.stops.remove(&origin_id),
+            }?;
+            Some(Order {
+                side: stop.side,
+                price: stop.info.order_price,
+                quantity: stop.quantity,
+                info: stop.info.info,
+            })
+        });
         match order {
             Some(Order {
                 side,
@@ -177,3 +311,55 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_trigger_into_pending() {
+        let mut account: Account<u64, u64, u64> = Account::new();
+        account
+            .stop(1, Side::Bid, 105, OrderPrice::Market, 10, ())
+            .unwrap();
+        account
+            .stop(2, Side::Bid, 103, OrderPrice::Limit(104), 20, ())
+            .unwrap();
+        account
+            .stop(3, Side::Ask, 95, OrderPrice::Market, 30, ())
+            .unwrap();
+        account
+            .stop(4, Side::Ask, 97, OrderPrice::Limit(96), 40, ())
+            .unwrap();
+        assert_eq!(
+            account.order(1, Side::Bid, OrderPrice::Limit(100), 1, ()),
+            Err(Error::KeyAlreadyExists)
+        );
+
+        assert!(account.trigger(&100, 1).is_empty());
+        assert_eq!(account.trigger(&105, 2), [2, 1]);
+        assert_eq!(
+            account.pending().get(&2).map(|o| o.price),
+            Some(OrderPrice::Limit(104))
+        );
+        let trigger_info = &account.triggered().get(&1).unwrap().info;
+        assert_eq!((trigger_info.trigger_price, trigger_info.time), (105, 2));
+
+        account.cancel(5, 4, Some(15)).unwrap();
+        assert_eq!(account.trigger(&96, 3), [4]);
+        assert_eq!(account.pending().get(&4).map(|o| o.quantity), Some(25));
+        assert_eq!(account.stops().get(&3).map(|o| o.quantity), Some(30));
+        account.cancel(6, 3, None).unwrap();
+        assert_eq!(
+            account.cancelled().get(&6).map(|o| o.price),
+            Some(OrderPrice::Market)
+        );
+        assert!(account.stops().get(&3).is_none());
+
+        account.cancel(7, 2, None).unwrap();
+        assert!(matches!(
+            account.stop(2, Side::Bid, 110, OrderPrice::Market, 10, ()),
+            Err(Error::KeyAlreadyExists)
+        ));
+    }
+}
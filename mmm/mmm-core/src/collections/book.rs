@@ -1,13 +1,14 @@
+use super::level::PriceLevelBook;
+use super::observer::{BookEvent, BookObserver};
 use super::Side;
 use serde::{Deserialize, Serialize};
 use std::borrow::Borrow;
-use std::collections::{hash_map, BTreeMap, VecDeque};
+use std::collections::{hash_map, BTreeMap};
 use std::fmt::Debug;
 use std::iter::Sum;
 use std::ops::{AddAssign, SubAssign};
 use std::{collections::HashMap, hash::Hash};
 use thiserror::Error;
-const GARBAGE_THRESHOLD: f32 = 0.5;
 
 #[derive(Error, PartialEq, Eq, Debug, Clone)]
 pub enum Error {
@@ -15,6 +16,8 @@ pub enum Error {
     KeyAlreadyExists,
     #[error("order id not found.")]
     OrderNotFound,
+    #[error("peak of a reserve order should be positive.")]
+    ZeroPeak,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -161,6 +164,42 @@ impl<P: Clone, Q: Clone, I: Clone> OrderView<'_, P, Q, I> {
     }
 }
 
+/// Visibility is how much of an order the book displays.
+/// quantity of the order is what is displayed, or all of it if hidden.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Visibility<Q> {
+    /// The whole quantity is displayed.
+    Lit,
+    /// At most `peak` is displayed and `reserve` is left behind.
+    /// When the displayed quantity is gone, a new peak is displayed at the back of the level.
+    Reserve { peak: Q, reserve: Q },
+    /// Nothing is displayed, the order rests only to be matched.
+    Hidden,
+}
+
+impl<Q> Visibility<Q> {
+    pub const fn is_hidden(&self) -> bool {
+        matches!(self, Self::Hidden)
+    }
+}
+
+/// OrderEntry is an order with the slot of its id in the OrderQueue of its price level.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct OrderEntry<P, Q, I> {
+    order: Order<P, Q, I>,
+    slot: usize,
+    visibility: Visibility<Q>,
+}
+
+impl<P, Q: Default + Clone, I> OrderEntry<P, Q, I> {
+    fn reserve(&self) -> Q {
+        match &self.visibility {
+            Visibility::Reserve { reserve, .. } => reserve.clone(),
+            Visibility::Lit | Visibility::Hidden => Q::default(),
+        }
+    }
+}
+
 /// OrderMap is a generic map of orders.
 /// It is used to keep track of existing orders.
 /// Value is the Order<P, Q, I> struct and key is generic K
@@ -171,35 +210,40 @@ impl<P: Clone, Q: Clone, I: Clone> OrderView<'_, P, Q, I> {
 /// let ordermap: OrderMap<i64, i64, i64, String> = OrderMap::new();
 ///
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct OrderMap<K: Eq + Hash, P, Q, I>(HashMap<K, Order<P, Q, I>>);
+pub struct OrderMap<K: Eq + Hash, P, Q, I>(HashMap<K, OrderEntry<P, Q, I>>);
 
 impl<K: Eq + Hash, P, Q, I> OrderMap<K, P, Q, I> {
     fn new() -> Self {
         Self(HashMap::new())
     }
-}
 
-/// BookCleaner can only be called through ::clean method.
-/// Since bookcleaner holds the orderqueue with a reference, it also has some lifetime 'a
-/// Calling ::clean with some ordermap will remove all orders in the orderqueue that are also included in the orderqueue.
-/// For example, it can be called to remove entire orders on ordermap from the orderqueue
-#[must_use = "this `BookCleaner` should be handled with `BookCleaner::clean`"]
-struct BookCleaner<'a, K, Q>(&'a mut OrderQueue<K, Q>);
-
-impl<K: Eq + Hash + Clone, Q> BookCleaner<'_, K, Q> {
-    fn clean<P, I>(self, order_map: &OrderMap<K, P, Q, I>) {
-        self.0.order_ids = self
-            .0
-            .order_ids
-            .iter()
-            .filter(|id| order_map.0.contains_key(id))
-            .cloned()
-            .collect();
+    fn get<R>(&self, id: &R) -> Option<&Order<P, Q, I>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq + ?Sized,
+    {
+        self.0.get(id).map(|entry| &entry.order)
     }
 }
 
+/// Slot of an OrderQueue.
+/// Occupied slots form a doubly linked list of order ids, vacant slots form a free list to be reused.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+enum Slot<K> {
+    Occupied {
+        id: K,
+        prev: Option<usize>,
+        next: Option<usize>,
+    },
+    Vacant {
+        next_free: Option<usize>,
+    },
+}
+
 /// OrderQueue keeps track of the order_ids(but not the information about each orders) and the total volume
 /// Imagine a queue for each price level in the entire orderbook.
+/// Order ids are linked in a slab of slots, so pushing, cancelling or executing any order is O(1)
+/// and iteration only visits orders that are still in the queue.
 /// It has generic: K, Q
 /// K: represent order_ids, very similar to key value in the order map
 /// Q: represents the volume
@@ -207,7 +251,11 @@ impl<K: Eq + Hash + Clone, Q> BookCleaner<'_, K, Q> {
 pub struct OrderQueue<K, Q> {
     count: usize,
     volume: Q,
-    order_ids: VecDeque<K>,
+    reserve: Q,
+    slots: Vec<Slot<K>>,
+    head: Option<usize>,
+    tail: Option<usize>,
+    free: Option<usize>,
 }
 
 impl<K, Q> OrderQueue<K, Q> {
@@ -218,7 +266,11 @@ impl<K, Q> OrderQueue<K, Q> {
         Self {
             count: 0,
             volume: Q::default(),
-            order_ids: VecDeque::new(),
+            reserve: Q::default(),
+            slots: Vec::new(),
+            head: None,
+            tail: None,
+            free: None,
         }
     }
 
@@ -230,8 +282,101 @@ impl<K, Q> OrderQueue<K, Q> {
         &self.volume
     }
 
-    pub fn order_ids(&self) -> &VecDeque<K> {
-        &self.order_ids
+    /// Reserve of the orders in the queue, which is not displayed.
+    pub fn reserve(&self) -> &Q {
+        &self.reserve
+    }
+
+    /// Order ids from the front of the queue.
+    pub fn order_ids(&self) -> OrderIds<'_, K> {
+        OrderIds {
+            slots: &self.slots,
+            front: self.head,
+            back: self.tail,
+            len: self.count,
+        }
+    }
+
+    pub fn front(&self) -> Option<&K> {
+        self.head.map(|slot| self.id(slot))
+    }
+
+    pub fn back(&self) -> Option<&K> {
+        self.tail.map(|slot| self.id(slot))
+    }
+
+    fn id(&self, slot: usize) -> &K {
+        match &self.slots[slot] {
+            Slot::Occupied { id, .. } => id,
+            Slot::Vacant { .. } => unreachable!("slot {} is vacant", slot),
+        }
+    }
+
+    fn prev(&self, slot: usize) -> Option<usize> {
+        match &self.slots[slot] {
+            Slot::Occupied { prev, .. } => *prev,
+            Slot::Vacant { .. } => unreachable!("slot {} is vacant", slot),
+        }
+    }
+
+    fn set_prev(&mut self, slot: usize, to: Option<usize>) {
+        if let Slot::Occupied { prev, .. } = &mut self.slots[slot] {
+            *prev = to;
+        }
+    }
+
+    fn set_next(&mut self, slot: usize, to: Option<usize>) {
+        if let Slot::Occupied { next, .. } = &mut self.slots[slot] {
+            *next = to;
+        }
+    }
+
+    /// Links `id` between `prev` and `next`, which must be adjacent.
+    fn link(&mut self, id: K, prev: Option<usize>, next: Option<usize>) -> usize {
+        let occupied = Slot::Occupied { id, prev, next };
+        let slot = match self.free {
+            Some(slot) => {
+                if let Slot::Vacant { next_free } = self.slots[slot] {
+                    self.free = next_free;
+                }
+                self.slots[slot] = occupied;
+                slot
+            }
+            None => {
+                self.slots.push(occupied);
+                self.slots.len() - 1
+            }
+        };
+        match prev {
+            Some(prev) => self.set_next(prev, Some(slot)),
+            None => self.head = Some(slot),
+        }
+        match next {
+            Some(next) => self.set_prev(next, Some(slot)),
+            None => self.tail = Some(slot),
+        }
+        slot
+    }
+
+    fn unlink(&mut self, slot: usize) -> K {
+        let vacant = Slot::Vacant {
+            next_free: self.free,
+        };
+        match std::mem::replace(&mut self.slots[slot], vacant) {
+            Slot::Occupied { id, prev, next } => {
+                self.free = Some(slot);
+                match prev {
+                    Some(prev) => self.set_next(prev, next),
+                    None => self.head = next,
+                }
+                match next {
+                    Some(next) => self.set_prev(next, prev),
+                    None => self.tail = prev,
+                }
+                id
+            }
+            Slot::Vacant { .. } => unreachable!("slot {} is vacant", slot),
+        }
     }
 }
 
@@ -239,23 +384,30 @@ impl<K, Q> OrderQueue<K, Q>
 where
     Q: AddAssign + SubAssign + Clone,
 {
-    fn push(&mut self, id: K, quantity: Q) {
-        self.order_ids.push_back(id);
+    fn push(&mut self, id: K, quantity: Q) -> usize {
+        let slot = self.link(id, self.tail, None);
         self.count += 1;
         self.volume += quantity;
+        slot
     }
 
-    fn sorted_insert_by_key(&mut self, id: K, quantity: Q)
+    fn sorted_insert_by_key(&mut self, id: K, quantity: Q) -> usize
     where
         K: Ord,
     {
-        let index = match self.order_ids.binary_search(&id) {
-            Ok(n) => n,
-            Err(n) => n,
-        };
-        self.order_ids.insert(index, id);
+        // late ids are usually close to the back.
+        let (mut prev, mut next) = (self.tail, None);
+        while let Some(slot) = prev {
+            if self.id(slot) <= &id {
+                break;
+            }
+            next = prev;
+            prev = self.prev(slot);
+        }
+        let slot = self.link(id, prev, next);
         self.count += 1;
         self.volume += quantity;
+        slot
     }
 
     fn reduce(&mut self, quantity: Q)
@@ -265,17 +417,65 @@ where
         self.volume -= quantity;
     }
 
-    // this operation does not remove `K` from `orders`
-    // removed order ids are lazily removed or garbage collected.
-    fn remove(&mut self, quantity: Q)
+    fn remove(&mut self, slot: usize, quantity: Q) -> K
     where
         Q: SubAssign,
     {
         self.count -= 1;
         self.reduce(quantity);
+        self.unlink(slot)
+    }
+}
+
+/// OrderIds iterates order ids of an OrderQueue in priority, from either end.
+#[derive(Clone, Debug)]
+pub struct OrderIds<'a, K> {
+    slots: &'a [Slot<K>],
+    front: Option<usize>,
+    back: Option<usize>,
+    len: usize,
+}
+
+impl<'a, K> Iterator for OrderIds<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        if self.len == 0 {
+            return None;
+        }
+        match &self.slots[self.front?] {
+            Slot::Occupied { id, next, .. } => {
+                self.front = *next;
+                self.len -= 1;
+                Some(id)
+            }
+            Slot::Vacant { .. } => None,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<K> DoubleEndedIterator for OrderIds<'_, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        match &self.slots[self.back?] {
+            Slot::Occupied { id, prev, .. } => {
+                self.back = *prev;
+                self.len -= 1;
+                Some(id)
+            }
+            Slot::Vacant { .. } => None,
+        }
     }
 }
 
+impl<K> ExactSizeIterator for OrderIds<'_, K> {}
+
 /// Bookstatus keeps track of the current book "status" but not to the individual order level.
 /// Order_ids are stored, but info about orders are not included in the bookstatus.
 /// Use OrderBook to store all the info about each orders
@@ -291,62 +491,67 @@ impl<K, P: Ord, Q> BookStatus<K, P, Q>
 where
     Q: AddAssign + SubAssign + Default + Clone + Sum,
 {
-    fn push(&mut self, id: K, price: P, quantity: Q) {
+    fn push(&mut self, id: K, price: P, quantity: Q) -> usize {
         self.0
             .entry(price)
             .or_insert_with(OrderQueue::new)
             .push(id, quantity)
     }
 
-    fn sorted_insert_by_key(&mut self, id: K, price: P, quantity: Q)
+    fn sorted_insert_by_key(&mut self, id: K, price: P, quantity: Q) -> usize
     where
         K: Ord,
     {
         self.0
             .entry(price)
             .or_insert_with(OrderQueue::new)
-            .sorted_insert_by_key(id, quantity);
+            .sorted_insert_by_key(id, quantity)
     }
 
-    fn top(&mut self) -> Option<(&P, &mut OrderQueue<K, Q>)> {
-        self.0.iter_mut().find(|(_, queue)| queue.count > 0)
+    fn top(&self) -> Option<(&P, &OrderQueue<K, Q>)> {
+        self.0.iter().next()
     }
 
-    fn bottom(&mut self) -> Option<(&P, &mut OrderQueue<K, Q>)> {
-        self.0.iter_mut().rev().find(|(_, queue)| queue.count > 0)
+    fn bottom(&self) -> Option<(&P, &OrderQueue<K, Q>)> {
+        self.0.iter().next_back()
     }
 
     fn reduce(&mut self, price: &P, quantity: Q) {
         self.0.get_mut(price).unwrap().reduce(quantity);
     }
 
-    fn remove(&mut self, price: &P, quantity: Q) -> Option<BookCleaner<K, Q>> {
+    /// Returns true if the level is emptied and removed.
+    fn remove(&mut self, price: &P, slot: usize, quantity: Q) -> bool {
         let order_queue = self.0.get_mut(price).unwrap();
-        order_queue.remove(quantity);
-
-        if order_queue.count == 0 {
+        order_queue.remove(slot, quantity);
+        let emptied = order_queue.count == 0;
+        if emptied {
             self.0.remove(price);
-            None
-        } else if order_queue.order_ids.len() > 100
-            && (order_queue.count as f32 / order_queue.order_ids.len() as f32) < GARBAGE_THRESHOLD
-        {
-            Some(BookCleaner(self.0.get_mut(price).unwrap()))
-        } else {
-            None
         }
+        emptied
     }
 
-    fn get_mut(&mut self, price: &P) -> Option<&mut OrderQueue<K, Q>> {
-        self.0.get_mut(price)
+    fn get(&self, price: &P) -> Option<&OrderQueue<K, Q>> {
+        self.0.get(price)
+    }
+
+    fn reserve_mut(&mut self, price: &P) -> &mut Q {
+        &mut self.0.get_mut(price).unwrap().reserve
+    }
+
+    fn reserve_at(&self, price: &P) -> Q {
+        self.get(price)
+            .map(|order_queue| order_queue.reserve.clone())
+            .unwrap_or_default()
     }
 
     pub fn volume_at(&mut self, price: P) -> Q {
-        self.get_mut(&price)
+        self.get(&price)
             .map(|order_group| order_group.volume.clone())
             .unwrap_or_else(Q::default)
     }
 
-    pub fn total_volume(&mut self) -> Q {
+    pub fn total_volume(&self) -> Q {
         self.0.iter().map(|(_, og)| og.volume.clone()).sum()
     }
 
@@ -392,6 +597,17 @@ pub struct Orders<'a, K, P, Q, I> {
     pub orders: Vec<(K, PartialOrder<'a, P, Q, I>)>,
 }
 
+/// QueuePosition is what stands ahead of an order at its price level.
+/// count: number of live orders ahead
+/// volume: total quantity of those orders
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub struct QueuePosition<Q> {
+    pub count: usize,
+    pub volume: Q,
+}
+
+type OrdersAhead<'a, K, P, Q, I> = Vec<(&'a K, &'a Order<P, Q, I>)>;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum PriceTimePriority {
     BothDesc,
@@ -400,11 +616,11 @@ pub enum PriceTimePriority {
 
 ///AskPrice
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize, Hash, PartialOrd, Ord)]
-struct AskPrice<P>(P);
+pub(crate) struct AskPrice<P>(pub(crate) P);
 
 ///AskPrice
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize, Hash)]
-struct BidPrice<P>(P);
+pub(crate) struct BidPrice<P>(pub(crate) P);
 
 impl<P: Ord> Ord for BidPrice<P> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
@@ -418,7 +634,7 @@ impl<P: Ord> PartialOrd for BidPrice<P> {
     }
 }
 
-trait SidePrice: Ord + Clone + Debug {
+pub(crate) trait SidePrice: Ord + Clone + Debug {
     type Price: Ord;
     fn new(price: Self::Price) -> Self;
     fn price(&self) -> &Self::Price;
@@ -449,52 +665,239 @@ impl<P: Ord + Clone + Debug> SidePrice for BidPrice<P> {
 /// OrderMap keeps track of the status for both ask and bid
 /// ask_status and bid_status will be a snaphost of the orderbook represented by type "BookStatus"
 /// order map will keep trach of each orders
+/// hidden orders are kept apart in hidden_ask_status and hidden_bid_status, see Visibility
+/// observer is notified of every change of the displayed book, see BookObserver
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct OrderBook<K: Eq + Hash, P: Ord, Q, I = ()> {
+pub struct OrderBook<K: Eq + Hash, P: Ord, Q, I = (), O = ()> {
     ask_status: BookStatus<K, AskPrice<P>, Q>,
     bid_status: BookStatus<K, BidPrice<P>, Q>,
+    hidden_ask_status: BookStatus<K, AskPrice<P>, Q>,
+    hidden_bid_status: BookStatus<K, BidPrice<P>, Q>,
     order_map: OrderMap<K, P, Q, I>,
+    #[serde(skip)]
+    observer: O,
 }
 
-impl<K: Eq + Hash, P: Ord, Q, I> Default for OrderBook<K, P, Q, I> {
+impl<K: Eq + Hash, P: Ord, Q, I, O: Default> Default for OrderBook<K, P, Q, I, O> {
     fn default() -> Self {
+        Self::with_observer(O::default())
+    }
+}
+
+impl<K: Eq + Hash, P: Ord, Q, I, O: Default> OrderBook<K, P, Q, I, O> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Eq + Hash, P: Ord, Q, I, O> OrderBook<K, P, Q, I, O> {
+    pub fn with_observer(observer: O) -> Self {
         Self {
             ask_status: BookStatus(BTreeMap::new()),
             bid_status: BookStatus(BTreeMap::new()),
+            hidden_ask_status: BookStatus(BTreeMap::new()),
+            hidden_bid_status: BookStatus(BTreeMap::new()),
             order_map: OrderMap::new(),
+            observer,
         }
     }
-}
 
-impl<K: Eq + Hash, P: Ord, Q, I> OrderBook<K, P, Q, I> {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Number of orders in the book, hidden ones included.
+    pub fn len(&self) -> usize {
+        self.order_map.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order_map.0.is_empty()
     }
 }
 
-impl<K, P, Q, I> OrderBook<K, P, Q, I>
+impl<K, P, Q, I, O> OrderBook<K, P, Q, I, O>
 where
     K: Eq + Hash + Clone + Debug,
     P: Ord + Debug + Clone,
     Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
     I: Debug,
+    O: BookObserver<K, P, Q>,
 {
+    fn notify_bbo<S: SidePrice<Price = P>>(
+        book_status: &BookStatus<K, S, Q>,
+        observer: &mut O,
+        side: Side,
+    ) {
+        let top = book_status.top();
+        observer.on_event(BookEvent::BboChanged {
+            side,
+            price: top.map(|(p, _)| p.price()),
+            volume: top.map(|(_, q)| q.volume.clone()).unwrap_or_default(),
+        });
+    }
+
+    /// Adds an order to its level with `push` and notifies `observer`.
+    fn join_level<S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        observer: &mut O,
+        id: &K,
+        order: &Order<P, Q, I>,
+        push: impl FnOnce(&mut BookStatus<K, S, Q>) -> usize,
+    ) -> usize {
+        let (side, price) = (order.side, &order.price);
+        let level = S::new(price.clone());
+        let created = book_status.get(&level).is_none();
+        let at_top = book_status.top().is_none_or(|(top, _)| &level <= top);
+        let slot = push(book_status);
+        if created {
+            observer.on_event(BookEvent::LevelCreated { side, price });
+        }
+        observer.on_event(BookEvent::OrderAdded {
+            id,
+            side,
+            price,
+            quantity: order.quantity.clone(),
+        });
+        if at_top {
+            Self::notify_bbo(book_status, observer, side);
+        }
+        slot
+    }
+
+    fn leave_level<S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        observer: &mut O,
+        id: &K,
+        entry: &OrderEntry<P, Q, I>,
+    ) {
+        let order = &entry.order;
+        let level = S::new(order.price.clone());
+        let at_top = book_status.top().is_some_and(|(top, _)| top == &level);
+        *book_status.reserve_mut(&level) -= entry.reserve();
+        let emptied = book_status.remove(&level, entry.slot, order.quantity.clone());
+        observer.on_event(BookEvent::OrderRemoved {
+            id,
+            side: order.side,
+            price: &order.price,
+            quantity: order.quantity.clone(),
+        });
+        if emptied {
+            observer.on_event(BookEvent::LevelEmptied {
+                side: order.side,
+                price: &order.price,
+            });
+        }
+        if at_top {
+            Self::notify_bbo(book_status, observer, order.side);
+        }
+    }
+
+    /// `order` has already been reduced by `quantity`.
+    fn reduce_level<S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        observer: &mut O,
+        id: &K,
+        order: &Order<P, Q, I>,
+        quantity: Q,
+    ) {
+        let level = S::new(order.price.clone());
+        book_status.reduce(&level, quantity.clone());
+        observer.on_event(BookEvent::OrderReduced {
+            id,
+            side: order.side,
+            price: &order.price,
+            quantity,
+            remaining: order.quantity.clone(),
+        });
+        if book_status.top().is_some_and(|(top, _)| top == &level) {
+            Self::notify_bbo(book_status, observer, order.side);
+        }
+    }
+
+    /// Displayed quantity of `entry` was `displayed` and its reserve was `reserve`, neither has grown.
+    fn amend_level<S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        observer: &mut O,
+        id: &K,
+        entry: &OrderEntry<P, Q, I>,
+        displayed: Q,
+        reserve: Q,
+    ) {
+        let order = &entry.order;
+        let level = S::new(order.price.clone());
+        *book_status.reserve_mut(&level) -= reserve;
+        *book_status.reserve_mut(&level) += entry.reserve();
+        if order.quantity < displayed {
+            let mut quantity = displayed;
+            quantity -= order.quantity.clone();
+            Self::reduce_level(book_status, observer, id, order, quantity);
+        }
+    }
+
+    /// Quantity of hidden `order` was `hidden` and has not grown, observer is not notified.
+    fn amend_hidden<S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        order: &Order<P, Q, I>,
+        hidden: Q,
+    ) {
+        if order.quantity < hidden {
+            let mut quantity = hidden;
+            quantity -= order.quantity.clone();
+            book_status.reduce(&S::new(order.price.clone()), quantity);
+        }
+    }
+
     fn _insert<'a, S: SidePrice<Price = P>>(
         book_status: &mut BookStatus<K, S, Q>,
         order_map: &'a mut OrderMap<K, P, Q, I>,
+        observer: &mut O,
         id: K,
-        side: Side,
-        price: P,
-        quantity: Q,
-        info: I,
+        order: Order<P, Q, I>,
+        visibility: Visibility<Q>,
     ) -> Result<&'a Order<P, Q, I>>
     where
         Q: Default,
     {
         match order_map.0.entry(id.clone()) {
             hash_map::Entry::Vacant(entry) => {
-                book_status.push(id, S::new(price.clone()), quantity.clone());
-                Ok(entry.insert(Order::new(side, price, quantity, info)))
+                let level = S::new(order.price.clone());
+                let slot = Self::join_level(book_status, observer, entry.key(), &order, |b| {
+                    b.push(id, level.clone(), order.quantity.clone())
+                });
+                let entry = entry.insert(OrderEntry {
+                    order,
+                    slot,
+                    visibility,
+                });
+                *book_status.reserve_mut(&level) += entry.reserve();
+                Ok(&entry.order)
+            }
+            hash_map::Entry::Occupied(_) => Err(Error::KeyAlreadyExists),
+        }
+    }
+
+    /// Hidden orders are not displayed, so observer is not notified.
+    fn insert_hidden_at<'a, S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        order_map: &'a mut OrderMap<K, P, Q, I>,
+        id: K,
+        order: Order<P, Q, I>,
+    ) -> Result<&'a Order<P, Q, I>> {
+        match order_map.0.entry(id.clone()) {
+            hash_map::Entry::Vacant(entry) => {
+                let slot =
+                    book_status.push(id, S::new(order.price.clone()), order.quantity.clone());
+                let entry = entry.insert(OrderEntry {
+                    order,
+                    slot,
+                    visibility: Visibility::Hidden,
+                });
+                Ok(&entry.order)
             }
             hash_map::Entry::Occupied(_) => Err(Error::KeyAlreadyExists),
         }
@@ -524,11 +927,10 @@ where
         Self::_insert(
             &mut self.ask_status,
             &mut self.order_map,
+            &mut self.observer,
             id,
-            Side::Ask,
-            price,
-            quantity,
-            info,
+            Order::new(Side::Ask, price, quantity, info),
+            Visibility::Lit,
         )
     }
     pub fn insert_bid(&mut self, id: K, price: P, quantity: Q, info: I) -> Result<&Order<P, Q, I>>
@@ -538,22 +940,93 @@ where
         Self::_insert(
             &mut self.bid_status,
             &mut self.order_map,
+            &mut self.observer,
             id,
-            Side::Bid,
-            price,
-            quantity,
-            info,
+            Order::new(Side::Bid, price, quantity, info),
+            Visibility::Lit,
         )
     }
 
-    fn sorted_insert_by_key<'a, S: SidePrice<Price = P>>(
-        book_status: &mut BookStatus<K, S, Q>,
-        order_map: &'a mut OrderMap<K, P, Q, I>,
+    /// Inserts a reserve (iceberg) order of `quantity` in total, displaying at most `peak` at a time.
+    pub fn insert_reserve(
+        &mut self,
         id: K,
         side: Side,
         price: P,
         quantity: Q,
+        peak: Q,
         info: I,
+    ) -> Result<&Order<P, Q, I>>
+    where
+        Q: Default,
+    {
+        if peak == Q::default() {
+            return Err(Error::ZeroPeak);
+        }
+        let displayed = peak.clone().min(quantity.clone());
+        let mut reserve = quantity;
+        reserve -= displayed.clone();
+        let order = Order::new(side, price, displayed, info);
+        self.insert_entry(id, order, Visibility::Reserve { peak, reserve })
+    }
+
+    /// Inserts an order which is not displayed at all.
+    pub fn insert_hidden(
+        &mut self,
+        id: K,
+        side: Side,
+        price: P,
+        quantity: Q,
+        info: I,
+    ) -> Result<&Order<P, Q, I>>
+    where
+        Q: Default,
+    {
+        let order = Order::new(side, price, quantity, info);
+        self.insert_entry(id, order, Visibility::Hidden)
+    }
+
+    fn insert_entry(
+        &mut self,
+        id: K,
+        order: Order<P, Q, I>,
+        visibility: Visibility<Q>,
+    ) -> Result<&Order<P, Q, I>>
+    where
+        Q: Default,
+    {
+        match (order.side, visibility.is_hidden()) {
+            (Side::Ask, false) => Self::_insert(
+                &mut self.ask_status,
+                &mut self.order_map,
+                &mut self.observer,
+                id,
+                order,
+                visibility,
+            ),
+            (Side::Bid, false) => Self::_insert(
+                &mut self.bid_status,
+                &mut self.order_map,
+                &mut self.observer,
+                id,
+                order,
+                visibility,
+            ),
+            (Side::Ask, true) => {
+                Self::insert_hidden_at(&mut self.hidden_ask_status, &mut self.order_map, id, order)
+            }
+            (Side::Bid, true) => {
+                Self::insert_hidden_at(&mut self.hidden_bid_status, &mut self.order_map, id, order)
+            }
+        }
+    }
+
+    fn sorted_insert_by_key<'a, S: SidePrice<Price = P>>(
+        book_status: &mut BookStatus<K, S, Q>,
+        order_map: &'a mut OrderMap<K, P, Q, I>,
+        observer: &mut O,
+        id: K,
+        order: Order<P, Q, I>,
     ) -> Result<&'a Order<P, Q, I>>
     where
         K: Ord,
@@ -561,8 +1034,17 @@ where
     {
         match order_map.0.entry(id.clone()) {
             hash_map::Entry::Vacant(entry) => {
-                book_status.sorted_insert_by_key(id, S::new(price.clone()), quantity.clone());
-                Ok(entry.insert(Order::new(side, price, quantity, info)))
+                let slot = Self::join_level(book_status, observer, entry.key(), &order, |b| {
+                    b.sorted_insert_by_key(id, S::new(order.price.clone()), order.quantity.clone())
+                });
+                let visibility = Visibility::Lit;
+                Ok(&entry
+                    .insert(OrderEntry {
+                        order,
+                        slot,
+                        visibility,
+                    })
+                    .order)
             }
             hash_map::Entry::Occupied(_) => Err(Error::KeyAlreadyExists),
         }
@@ -582,11 +1064,9 @@ where
         Self::sorted_insert_by_key(
             &mut self.ask_status,
             &mut self.order_map,
+            &mut self.observer,
             id,
-            Side::Ask,
-            price,
-            quantity,
-            info,
+            Order::new(Side::Ask, price, quantity, info),
         )
     }
 
@@ -604,11 +1084,9 @@ where
         Self::sorted_insert_by_key(
             &mut self.bid_status,
             &mut self.order_map,
+            &mut self.observer,
             id,
-            Side::Bid,
-            price,
-            quantity,
-            info,
+            Order::new(Side::Bid, price, quantity, info),
         )
     }
 
@@ -617,27 +1095,59 @@ where
         K: Borrow<R>,
         R: Hash + Eq,
     {
-        let removed = self.order_map.0.remove(id);
-        if let Some(Order {
-            side,
-            price,
-            quantity,
-            ..
-        }) = &removed
-        {
-            let bc = match side {
-                Side::Ask => self
-                    .ask_status
-                    .remove(&AskPrice::new(price.clone()), quantity.clone()),
-                Side::Bid => self
-                    .bid_status
-                    .remove(&BidPrice::new(price.clone()), quantity.clone()),
-            };
-            if let Some(bc) = bc {
-                bc.clean(&self.order_map);
+        self.remove_entry(id).map(|(_, entry)| entry.order)
+    }
+
+    fn remove_entry<R>(&mut self, id: &R) -> Option<(K, OrderEntry<P, Q, I>)>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let (id, entry) = self.order_map.0.remove_entry(id)?;
+        let OrderEntry { order, slot, .. } = &entry;
+        match (order.side, entry.visibility.is_hidden()) {
+            (Side::Ask, false) => {
+                Self::leave_level(&mut self.ask_status, &mut self.observer, &id, &entry)
+            }
+            (Side::Bid, false) => {
+                Self::leave_level(&mut self.bid_status, &mut self.observer, &id, &entry)
+            }
+            (Side::Ask, true) => {
+                let level = AskPrice(order.price.clone());
+                self.hidden_ask_status
+                    .remove(&level, *slot, order.quantity.clone());
+            }
+            (Side::Bid, true) => {
+                let level = BidPrice(order.price.clone());
+                self.hidden_bid_status
+                    .remove(&level, *slot, order.quantity.clone());
             }
         }
-        removed
+        Some((id, entry))
+    }
+
+    /// The displayed quantity of a reserve order is gone after `quantity` is reduced,
+    /// the rest of `quantity` is taken from its reserve and a new peak is displayed at the back of the level.
+    fn replenish<R>(&mut self, id: &R, quantity: Q) -> Option<OrderView<'_, P, Q, I>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let (id, mut entry) = self.remove_entry(id)?;
+        let Visibility::Reserve { peak, reserve } = &mut entry.visibility else {
+            unreachable!("{:?} is not a reserve order", id);
+        };
+        *reserve += entry.order.quantity.clone();
+        *reserve -= quantity.clone();
+        entry.order.quantity = peak.clone().min(reserve.clone());
+        *reserve -= entry.order.quantity.clone();
+        let order = self.insert_entry(id, entry.order, entry.visibility).ok()?;
+        Some(OrderView::Partial(PartialOrder {
+            side: &order.side,
+            price: &order.price,
+            quantity,
+            info: &order.info,
+        }))
     }
 
     pub fn reduce<R: ?Sized>(&mut self, id: &R, quantity: Q) -> Option<OrderView<P, Q, I>>
@@ -645,30 +1155,146 @@ where
         K: Borrow<R>,
         R: Hash + Eq + Clone,
     {
-        let order = self.order_map.0.get(id)?;
+        let entry = self.order_map.0.get(id)?;
+        let mut total = entry.order.quantity.clone();
+        total += entry.reserve();
 
-        Some(if order.quantity <= quantity {
+        if total <= quantity {
             let removed = self.remove(id).unwrap();
-            OrderView::Full(removed)
-        } else {
-            let order = self.order_map.0.get_mut(id).unwrap();
-            order.quantity -= quantity.clone();
-            let partial = PartialOrder {
-                side: &order.side,
-                price: &order.price,
-                quantity: quantity.clone(),
-                info: &order.info,
+            return Some(OrderView::Full(removed));
+        } else if entry.order.quantity <= quantity {
+            return self.replenish(id, quantity);
+        }
+        self.order_map.0.get_mut(id).unwrap().order.quantity -= quantity.clone();
+        let (
+            k,
+            OrderEntry {
+                order, visibility, ..
+            },
+        ) = self.order_map.0.get_key_value(id).unwrap();
+        match (order.side, visibility.is_hidden()) {
+            (Side::Ask, true) => self
+                .hidden_ask_status
+                .reduce(&AskPrice(order.price.clone()), quantity.clone()),
+            (Side::Bid, true) => self
+                .hidden_bid_status
+                .reduce(&BidPrice(order.price.clone()), quantity.clone()),
+            (Side::Ask, false) => Self::reduce_level(
+                &mut self.ask_status,
+                &mut self.observer,
+                k,
+                order,
+                quantity.clone(),
+            ),
+            (Side::Bid, false) => Self::reduce_level(
+                &mut self.bid_status,
+                &mut self.observer,
+                k,
+                order,
+                quantity.clone(),
+            ),
+        }
+        Some(OrderView::Partial(PartialOrder {
+            side: &order.side,
+            price: &order.price,
+            quantity,
+            info: &order.info,
+        }))
+    }
+
+    /// Whether modifying `id` to `price` and `quantity` keeps time priority by the usual rule:
+    /// reducing the size keeps it, changing the price or increasing the size loses it.
+    /// `quantity` includes the reserve of a reserve order, as in `modify`.
+    /// Returns None if `id` is not in the book.
+    pub fn keeps_priority<R>(&self, id: &R, price: &P, quantity: &Q) -> Option<bool>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let entry = self.order_map.0.get(id)?;
+        let mut total = entry.order.quantity.clone();
+        total += entry.reserve();
+        Some(&entry.order.price == price && quantity <= &total)
+    }
+
+    /// Changes price and quantity of `id`, `quantity` includes the reserve of a reserve order.
+    /// The order keeps its place in the queue if `keep_priority` and `keeps_priority` holds,
+    /// otherwise it joins the back of the level at `price`.
+    /// `keep_priority` is false when the venue moves the order back for another reason.
+    /// A zero `quantity` removes the order and returns None.
+    pub fn modify<R>(
+        &mut self,
+        id: &R,
+        price: P,
+        quantity: Q,
+        keep_priority: bool,
+    ) -> Result<Option<&Order<P, Q, I>>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let keeps_priority =
+            (self.keeps_priority(id, &price, &quantity)).ok_or(Error::OrderNotFound)?;
+        if quantity == Q::default() {
+            self.remove(id);
+            return Ok(None);
+        }
+        if !keep_priority || !keeps_priority {
+            let (
+                id,
+                OrderEntry {
+                    order, visibility, ..
+                },
+            ) = self.remove_entry(id).unwrap();
+            let (displayed, visibility) = match visibility {
+                Visibility::Reserve { peak, .. } => {
+                    let displayed = peak.clone().min(quantity.clone());
+                    let mut reserve = quantity;
+                    reserve -= displayed.clone();
+                    (displayed, Visibility::Reserve { peak, reserve })
+                }
+                visibility @ (Visibility::Lit | Visibility::Hidden) => (quantity, visibility),
             };
-            match order.side {
-                Side::Ask => self
-                    .ask_status
-                    .reduce(&AskPrice::new(order.price.clone()), quantity),
-                Side::Bid => self
-                    .bid_status
-                    .reduce(&BidPrice::new(order.price.clone()), quantity),
+            let order = Order::new(order.side, price, displayed, order.info);
+            return self.insert_entry(id, order, visibility).map(Some);
+        }
+
+        let entry = self.order_map.0.get_mut(id).unwrap();
+        let (displayed, reserve) = (entry.order.quantity.clone(), entry.reserve());
+        match &mut entry.visibility {
+            Visibility::Reserve { reserve, .. } => {
+                entry.order.quantity = quantity.clone().min(displayed.clone());
+                *reserve = quantity;
+                *reserve -= entry.order.quantity.clone();
             }
-            OrderView::Partial(partial)
-        })
+            Visibility::Lit | Visibility::Hidden => entry.order.quantity = quantity,
+        }
+        let (k, entry) = self.order_map.0.get_key_value(id).unwrap();
+        match (entry.order.side, entry.visibility.is_hidden()) {
+            (Side::Ask, false) => Self::amend_level(
+                &mut self.ask_status,
+                &mut self.observer,
+                k,
+                entry,
+                displayed,
+                reserve,
+            ),
+            (Side::Bid, false) => Self::amend_level(
+                &mut self.bid_status,
+                &mut self.observer,
+                k,
+                entry,
+                displayed,
+                reserve,
+            ),
+            (Side::Ask, true) => {
+                Self::amend_hidden(&mut self.hidden_ask_status, &entry.order, displayed)
+            }
+            (Side::Bid, true) => {
+                Self::amend_hidden(&mut self.hidden_bid_status, &entry.order, displayed)
+            }
+        }
+        Ok(Some(&entry.order))
     }
 
     pub fn get_ask_order_queue(&mut self, price: P) -> Option<&OrderQueue<K, Q>> {
@@ -687,14 +1313,62 @@ where
         self.bid_status.volume_at(BidPrice(price))
     }
 
-    pub fn total_ask_volume(&mut self) -> Q {
+    pub fn total_ask_volume(&self) -> Q {
         self.ask_status.total_volume()
     }
 
-    pub fn total_bid_volume(&mut self) -> Q {
+    pub fn total_bid_volume(&self) -> Q {
         self.bid_status.total_volume()
     }
 
+    /// Volume not displayed at a price, reserves of reserve orders and hidden orders.
+    fn hidden_volume_at<S: SidePrice<Price = P>>(
+        book_status: &BookStatus<K, S, Q>,
+        hidden_status: &BookStatus<K, S, Q>,
+        price: &S,
+    ) -> Q {
+        let mut volume = book_status.reserve_at(price);
+        if let Some(order_queue) = hidden_status.get(price) {
+            volume += order_queue.volume.clone();
+        }
+        volume
+    }
+
+    pub fn ask_hidden_volume_at(&self, price: P) -> Q {
+        Self::hidden_volume_at(&self.ask_status, &self.hidden_ask_status, &AskPrice(price))
+    }
+
+    pub fn bid_hidden_volume_at(&self, price: P) -> Q {
+        Self::hidden_volume_at(&self.bid_status, &self.hidden_bid_status, &BidPrice(price))
+    }
+
+    fn total_hidden_volume<S: SidePrice<Price = P>>(
+        book_status: &BookStatus<K, S, Q>,
+        hidden_status: &BookStatus<K, S, Q>,
+    ) -> Q {
+        (book_status.0.values())
+            .map(|og| og.reserve.clone())
+            .chain(hidden_status.0.values().map(|og| og.volume.clone()))
+            .sum()
+    }
+
+    pub fn total_ask_hidden_volume(&self) -> Q {
+        Self::total_hidden_volume(&self.ask_status, &self.hidden_ask_status)
+    }
+
+    pub fn total_bid_hidden_volume(&self) -> Q {
+        Self::total_hidden_volume(&self.bid_status, &self.hidden_bid_status)
+    }
+
+    /// Prices of hidden orders, best first. Reserves are at the displayed prices.
+    pub fn sorted_hidden_ask_prices(&self) -> impl Iterator<Item = &P> {
+        self.hidden_ask_status.sorted_prices().map(SidePrice::price)
+    }
+
+    pub fn sorted_hidden_bid_prices(&self) -> impl Iterator<Item = &P> {
+        self.hidden_bid_status.sorted_prices().map(SidePrice::price)
+    }
+
     pub fn sorted_ask_prices(&self) -> impl Iterator<Item = &P> {
         self.ask_status.sorted_prices().map(SidePrice::price)
     }
@@ -703,96 +1377,90 @@ where
         self.bid_status.sorted_prices().map(SidePrice::price)
     }
 
+    /// Aggregated depth of the book, see PriceLevelBook.
+    pub fn price_levels(&self) -> PriceLevelBook<P, Q> {
+        let mut levels = PriceLevelBook::new();
+        for (price, order_queue) in self.ask_status.0.iter() {
+            levels.set(Side::Ask, price.0.clone(), order_queue.volume.clone());
+        }
+        for (price, order_queue) in self.bid_status.0.iter() {
+            levels.set(Side::Bid, price.0.clone(), order_queue.volume.clone());
+        }
+        levels
+    }
+
     fn price_top<S: SidePrice<Price = P>>(
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
     ) -> Option<(&P, &OrderQueue<K, Q>)> {
         book_status
             .top()
-            .map(|(p, order_queue)| (p.price(), &*order_queue))
+            .map(|(p, order_queue)| (p.price(), order_queue))
     }
 
     pub fn ask_price_top(&mut self) -> Option<(&P, &OrderQueue<K, Q>)> {
-        Self::price_top(&mut self.ask_status)
+        Self::price_top(&self.ask_status)
     }
 
     pub fn bid_price_top(&mut self) -> Option<(&P, &OrderQueue<K, Q>)> {
-        Self::price_top(&mut self.bid_status)
+        Self::price_top(&self.bid_status)
     }
 
     fn price_bottom<S: SidePrice<Price = P>>(
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
     ) -> Option<(&P, &OrderQueue<K, Q>)> {
         book_status
             .bottom()
-            .map(|(p, order_queue)| (p.price(), &*order_queue))
+            .map(|(p, order_queue)| (p.price(), order_queue))
     }
 
     pub fn ask_price_bottom(&mut self) -> Option<(&P, &OrderQueue<K, Q>)> {
-        Self::price_bottom(&mut self.ask_status)
+        Self::price_bottom(&self.ask_status)
     }
 
     pub fn bid_price_bottom(&mut self) -> Option<(&P, &OrderQueue<K, Q>)> {
-        Self::price_bottom(&mut self.bid_status)
+        Self::price_bottom(&self.bid_status)
     }
 
     fn top<'a, 'b, S: SidePrice<Price = P>>(
-        book_status: &'a mut BookStatus<K, S, Q>,
+        book_status: &'a BookStatus<K, S, Q>,
         order_map: &'b OrderMap<K, P, Q, I>,
     ) -> Option<(&'a K, &'b Order<P, Q, I>)> {
-        let order_queue = book_status.top()?.1;
-        loop {
-            let front_key = order_queue
-                .order_ids
-                .front()
-                .expect("inconsistency between self.book_status and self.order_map");
-            let front_order = order_map.0.get(front_key);
-            if front_order.is_none() {
-                order_queue.order_ids.pop_front();
-            } else {
-                let k = order_queue.order_ids.front().unwrap();
-                return front_order.map(|o| (k, o));
-            }
-        }
+        let k = book_status.top()?.1.front()?;
+        let order = order_map
+            .get(k)
+            .expect("inconsistency between self.book_status and self.order_map");
+        Some((k, order))
     }
 
     pub fn ask_top(&mut self) -> Option<(&K, &Order<P, Q, I>)> {
-        Self::top(&mut self.ask_status, &self.order_map)
+        Self::top(&self.ask_status, &self.order_map)
     }
 
     pub fn bid_top(&mut self) -> Option<(&K, &Order<P, Q, I>)> {
-        Self::top(&mut self.bid_status, &self.order_map)
+        Self::top(&self.bid_status, &self.order_map)
     }
 
     fn bottom<'a, 'b, S: SidePrice<Price = P>>(
-        book_status: &'a mut BookStatus<K, S, Q>,
+        book_status: &'a BookStatus<K, S, Q>,
         order_map: &'b OrderMap<K, P, Q, I>,
     ) -> Option<(&'a K, &'b Order<P, Q, I>)> {
-        let order_queue = book_status.bottom()?.1;
-        loop {
-            let back_key = order_queue
-                .order_ids
-                .back()
-                .expect("inconsistency between self.book_status and self.order_map");
-            let back_order = order_map.0.get(back_key);
-            if back_order.is_none() {
-                order_queue.order_ids.pop_back();
-            } else {
-                let k = order_queue.order_ids.back().unwrap();
-                return back_order.map(|o| (k, o));
-            }
-        }
+        let k = book_status.bottom()?.1.back()?;
+        let order = order_map
+            .get(k)
+            .expect("inconsistency between self.book_status and self.order_map");
+        Some((k, order))
     }
 
     pub fn ask_bottom(&mut self) -> Option<(&K, &Order<P, Q, I>)> {
-        Self::bottom(&mut self.ask_status, &self.order_map)
+        Self::bottom(&self.ask_status, &self.order_map)
     }
 
     pub fn bid_bottom(&mut self) -> Option<(&K, &Order<P, Q, I>)> {
-        Self::bottom(&mut self.bid_status, &self.order_map)
+        Self::bottom(&self.bid_status, &self.order_map)
     }
 
     fn list_orders<'a, S: SidePrice<Price = P>>(
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
         order_map: &'a OrderMap<K, P, Q, I>,
         prices: &[S],
         mut quantity: Option<Q>,
@@ -826,7 +1494,7 @@ where
             prices.reverse()
         }
         Self::list_orders(
-            &mut self.ask_status,
+            &self.ask_status,
             &self.order_map,
             &*prices,
             quantity,
@@ -844,7 +1512,7 @@ where
             prices.reverse()
         }
         Self::list_orders(
-            &mut self.bid_status,
+            &self.bid_status,
             &self.order_map,
             &*prices,
             quantity,
@@ -860,7 +1528,7 @@ where
     ) -> Orders<K, P, Q, I> {
         Self::list_orders_at(
             &self.order_map,
-            &mut self.ask_status,
+            &self.ask_status,
             &AskPrice(price),
             quantity,
             sort_by,
@@ -875,19 +1543,58 @@ where
     ) -> Orders<K, P, Q, I> {
         Self::list_orders_at(
             &self.order_map,
-            &mut self.bid_status,
+            &self.bid_status,
             &BidPrice(price),
             quantity,
             sort_by,
         )
     }
 
-    pub fn get<R>(&mut self, id: &R) -> Option<&Order<P, Q, I>>
+    /// Hidden orders at `price`, matched after the displayed orders at the price.
+    pub fn hidden_ask_orders_at(
+        &self,
+        price: P,
+        quantity: Option<Q>,
+        sort_by: PriceTimePriority,
+    ) -> Orders<'_, K, P, Q, I> {
+        Self::list_orders_at(
+            &self.order_map,
+            &self.hidden_ask_status,
+            &AskPrice(price),
+            quantity,
+            sort_by,
+        )
+    }
+
+    pub fn hidden_bid_orders_at(
+        &self,
+        price: P,
+        quantity: Option<Q>,
+        sort_by: PriceTimePriority,
+    ) -> Orders<'_, K, P, Q, I> {
+        Self::list_orders_at(
+            &self.order_map,
+            &self.hidden_bid_status,
+            &BidPrice(price),
+            quantity,
+            sort_by,
+        )
+    }
+
+    pub fn get<R>(&self, id: &R) -> Option<&Order<P, Q, I>>
     where
         K: Borrow<R>,
         R: Hash + Eq,
     {
-        self.order_map.0.get(id)
+        self.order_map.get(id)
+    }
+
+    pub fn visibility<R>(&self, id: &R) -> Option<&Visibility<Q>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        self.order_map.0.get(id).map(|e| &e.visibility)
     }
 
     pub fn get_info_mut<R>(&mut self, id: &R) -> Option<&mut I>
@@ -895,66 +1602,45 @@ where
         K: Borrow<R>,
         R: Hash + Eq,
     {
-        self.order_map.0.get_mut(id).map(|o| &mut o.info)
+        self.order_map.0.get_mut(id).map(|e| &mut e.order.info)
     }
 
     fn list_orders_at<'a, S: SidePrice<Price = P>>(
         order_map: &'a OrderMap<K, P, Q, I>,
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
         price: &S,
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
     ) -> Orders<'a, K, P, Q, I> {
-        type FnPop<K> = fn(&mut VecDeque<K>) -> Option<K>;
-        type FnPush<K> = fn(&mut VecDeque<K>, K);
-
-        if let Some(order_group) = book_status.get_mut(price) {
+        if let Some(order_group) = book_status.get(price) {
             let mut quantity = quantity.unwrap_or_else(|| order_group.volume.clone());
             let mut orders = Vec::new();
-            let order_ids = &mut order_group.order_ids;
-            let mut valids = Vec::new();
-
-            let (fn_pop, fn_push): (FnPop<K>, FnPush<K>) =
-                if let PriceTimePriority::BothDesc = sort_by {
-                    (VecDeque::pop_front, VecDeque::push_front)
-                } else {
-                    (VecDeque::pop_back, VecDeque::push_back)
+            let mut order_ids = order_group.order_ids();
+            while quantity > Q::default() {
+                let id = match sort_by {
+                    PriceTimePriority::BothDesc => order_ids.next(),
+                    PriceTimePriority::BothAsc => order_ids.next_back(),
                 };
-            let orders = loop {
-                if quantity > Q::default() {
-                    if let Some(id) = fn_pop(order_ids) {
-                        if let Some(order) = order_map.0.get(&id) {
-                            valids.push(id.clone());
-                            let partial_quantitiy = order.quantity.clone().min(quantity.clone());
-                            quantity -= partial_quantitiy.clone();
-                            orders.push((
-                                id,
-                                PartialOrder {
-                                    side: &order.side,
-                                    price: &order.price,
-                                    quantity: partial_quantitiy,
-                                    info: &order.info,
-                                },
-                            ))
-                        }
-                    } else {
-                        break Orders {
-                            deficit: quantity,
-                            orders,
-                        };
-                    };
-                } else {
-                    break Orders {
-                        deficit: Q::default(),
-                        orders,
-                    };
-                }
-            };
-
-            for id in valids.into_iter().rev() {
-                fn_push(order_ids, id);
+                let (id, order) = match id {
+                    Some(id) => (id, &order_map.0[id].order),
+                    None => break,
+                };
+                let partial_quantitiy = order.quantity.clone().min(quantity.clone());
+                quantity -= partial_quantitiy.clone();
+                orders.push((
+                    id.clone(),
+                    PartialOrder {
+                        side: &order.side,
+                        price: &order.price,
+                        quantity: partial_quantitiy,
+                        info: &order.info,
+                    },
+                ))
+            }
+            Orders {
+                deficit: quantity.max(Q::default()),
+                orders,
             }
-            orders
         } else {
             Orders {
                 deficit: quantity.unwrap_or_default(),
@@ -963,34 +1649,108 @@ where
         }
     }
 
+    /// Orders ahead of the order in `slot` at its price level, front first.
+    fn queue_ahead<'a, S: SidePrice<Price = P>>(
+        book_status: &'a BookStatus<K, S, Q>,
+        order_map: &'a OrderMap<K, P, Q, I>,
+        price: &P,
+        slot: usize,
+    ) -> OrdersAhead<'a, K, P, Q, I> {
+        let order_queue = &book_status.0[&S::new(price.clone())];
+        let mut ahead = Vec::new();
+        let mut prev = order_queue.prev(slot);
+        while let Some(slot) = prev {
+            let k = order_queue.id(slot);
+            ahead.push((k, &order_map.0[k].order));
+            prev = order_queue.prev(slot);
+        }
+        ahead.reverse();
+        ahead
+    }
+
+    pub(crate) fn orders_ahead<R>(&self, id: &R) -> Option<OrdersAhead<'_, K, P, Q, I>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let OrderEntry {
+            order,
+            slot,
+            visibility,
+        } = self.order_map.0.get(id)?;
+        let (ask_status, bid_status) = match visibility {
+            Visibility::Hidden => (&self.hidden_ask_status, &self.hidden_bid_status),
+            Visibility::Lit | Visibility::Reserve { .. } => (&self.ask_status, &self.bid_status),
+        };
+        Some(match order.side {
+            Side::Ask => Self::queue_ahead(ask_status, &self.order_map, &order.price, *slot),
+            Side::Bid => Self::queue_ahead(bid_status, &self.order_map, &order.price, *slot),
+        })
+    }
+
+    /// Number and volume of orders ahead of `id` at its price level.
+    /// It walks the queue of the level, use `QueueTracker` to follow positions as the book changes.
+    pub fn queue_position<R>(&self, id: &R) -> Option<QueuePosition<Q>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        let ahead = self.orders_ahead(id)?;
+        Some(QueuePosition {
+            count: ahead.len(),
+            volume: ahead.into_iter().map(|(_, o)| o.quantity.clone()).sum(),
+        })
+    }
+
     fn book_status_check<S: SidePrice<Price = P>>(
         book_status: &BookStatus<K, S, Q>,
         order_map: &OrderMap<K, P, Q, I>,
+        side: Side,
+        hidden: bool,
     ) {
-        for order_queue in book_status.0.values() {
+        for (price, order_queue) in book_status.0.iter() {
             let count = order_queue.count();
             let volume = order_queue.volume();
+            let mut reserve = Q::default();
             let orders = order_queue
                 .order_ids()
-                .iter()
-                .filter_map(|k| order_map.0.get(k).map(|o| o.quantity.clone()))
+                .map(|k| {
+                    let entry = &order_map.0[k];
+                    let order = &entry.order;
+                    assert_eq!((order.side, &order.price), (side, price.price()));
+                    assert_eq!(order_queue.id(entry.slot), k);
+                    assert_eq!(entry.visibility.is_hidden(), hidden);
+                    reserve += entry.reserve();
+                    order.quantity.clone()
+                })
                 .collect::<Vec<_>>();
             assert_eq!(count, orders.len());
             assert_eq!(volume, &orders.into_iter().sum::<Q>());
+            assert_eq!(order_queue.reserve(), &reserve);
         }
     }
     pub fn integrity_check(&self) {
-        Self::book_status_check(&self.ask_status, &self.order_map);
-        Self::book_status_check(&self.bid_status, &self.order_map);
+        Self::book_status_check(&self.ask_status, &self.order_map, Side::Ask, false);
+        Self::book_status_check(&self.bid_status, &self.order_map, Side::Bid, false);
+        Self::book_status_check(&self.hidden_ask_status, &self.order_map, Side::Ask, true);
+        Self::book_status_check(&self.hidden_bid_status, &self.order_map, Side::Bid, true);
+        let count: usize = (self.ask_status.0.values())
+            .chain(self.bid_status.0.values())
+            .chain(self.hidden_ask_status.0.values())
+            .chain(self.hidden_bid_status.0.values())
+            .map(OrderQueue::count)
+            .sum();
+        assert_eq!(count, self.order_map.0.len());
     }
 }
 
-impl<K, L, Q, I> OrderBook<K, OrderPrice<L>, Q, I>
+impl<K, L, Q, I, O> OrderBook<K, OrderPrice<L>, Q, I, O>
 where
     K: Eq + Hash + Clone + Debug,
     L: Ord + Debug + Clone,
     Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
     I: Debug,
+    O: BookObserver<K, OrderPrice<L>, Q>,
 {
     pub fn ask_market_volume(&mut self) -> Q {
         self.ask_status.market_volume()
@@ -1009,7 +1769,7 @@ where
     }
 
     fn limit_orders<'a, S: SidePrice<Price = OrderPrice<L>>>(
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
         order_map: &'a OrderMap<K, OrderPrice<L>, Q, I>,
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
@@ -1030,7 +1790,7 @@ where
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
     ) -> Orders<K, OrderPrice<L>, Q, I> {
-        Self::limit_orders(&mut self.ask_status, &self.order_map, quantity, sort_by)
+        Self::limit_orders(&self.ask_status, &self.order_map, quantity, sort_by)
     }
 
     pub fn bid_limit_orders(
@@ -1038,11 +1798,11 @@ where
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
     ) -> Orders<K, OrderPrice<L>, Q, I> {
-        Self::limit_orders(&mut self.bid_status, &self.order_map, quantity, sort_by)
+        Self::limit_orders(&self.bid_status, &self.order_map, quantity, sort_by)
     }
 
     fn market_orders<'a, S: SidePrice<Price = OrderPrice<L>>>(
-        book_status: &mut BookStatus<K, S, Q>,
+        book_status: &BookStatus<K, S, Q>,
         order_map: &'a OrderMap<K, OrderPrice<L>, Q, I>,
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
@@ -1061,7 +1821,7 @@ where
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
     ) -> Orders<K, OrderPrice<L>, Q, I> {
-        Self::market_orders(&mut self.ask_status, &self.order_map, quantity, sort_by)
+        Self::market_orders(&self.ask_status, &self.order_map, quantity, sort_by)
     }
 
     pub fn bid_market_orders(
@@ -1069,6 +1829,244 @@ where
         quantity: Option<Q>,
         sort_by: PriceTimePriority,
     ) -> Orders<K, OrderPrice<L>, Q, I> {
-        Self::market_orders(&mut self.bid_status, &self.order_map, quantity, sort_by)
+        Self::market_orders(&self.bid_status, &self.order_map, quantity, sort_by)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order_queue_links() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        for id in [1, 2, 3, 4] {
+            book.insert_ask(id, 100, id * 10, ()).unwrap();
+        }
+        book.remove(&2);
+        book.reduce(&4, 40);
+        // slot of 2 is reused for 5, which still joins at the back.
+        book.insert_ask(5, 100, 50, ()).unwrap();
+        book.sorted_insert_ask_by_key(0, 100, 5, ()).unwrap();
+        book.sorted_insert_ask_by_key(4, 100, 1, ()).unwrap();
+        book.integrity_check();
+
+        let order_queue = book.get_ask_order_queue(100).unwrap();
+        let ids: Vec<_> = order_queue.order_ids().cloned().collect();
+        assert_eq!(ids, [0, 1, 3, 4, 5]);
+        let ids: Vec<_> = order_queue.order_ids().rev().cloned().collect();
+        assert_eq!(ids, [5, 4, 3, 1, 0]);
+        assert_eq!((order_queue.count(), *order_queue.volume()), (5, 96));
+
+        let Orders { deficit, orders } =
+            book.ask_orders_at(100, Some(20), PriceTimePriority::BothDesc);
+        let orders: Vec<_> = orders.iter().map(|(k, o)| (*k, *o.quantity())).collect();
+        assert_eq!((deficit, orders), (0, vec![(0, 5), (1, 10), (3, 5)]));
+        let Orders { deficit, orders } =
+            book.ask_orders_at(100, Some(200), PriceTimePriority::BothAsc);
+        assert_eq!((deficit, orders.len(), orders[0].0), (104, 5, 5));
+
+        for id in [0, 1, 3, 4, 5] {
+            book.remove(&id);
+        }
+        assert!(book.get_ask_order_queue(100).is_none());
+        assert_eq!(book.ask_top(), None);
+        book.integrity_check();
+    }
+
+    #[test]
+    fn reserve_and_hidden_orders() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        book.insert_ask(1, 100, 10, ()).unwrap();
+        book.insert_reserve(2, Side::Ask, 100, 50, 20, ()).unwrap();
+        book.insert_ask(3, 100, 5, ()).unwrap();
+        book.insert_hidden(4, Side::Ask, 100, 40, ()).unwrap();
+        book.insert_hidden(5, Side::Ask, 99, 7, ()).unwrap();
+        assert_eq!(
+            book.insert_reserve(6, Side::Ask, 100, 50, 0, ()),
+            Err(Error::ZeroPeak)
+        );
+        book.integrity_check();
+        assert_eq!(book.ask_price_top().map(|(p, _)| *p), Some(100));
+        assert_eq!(book.sorted_hidden_ask_prices().next(), Some(&99));
+        assert_eq!(
+            (book.ask_volume_at(100), book.ask_hidden_volume_at(100)),
+            (35, 70)
+        );
+        assert_eq!(book.total_ask_hidden_volume(), 77);
+
+        // 20 displayed and 5 of the reserve are executed, the next peak loses priority.
+        book.reduce(&2, 25);
+        book.integrity_check();
+        let ids: Vec<_> = (book.get_ask_order_queue(100).unwrap().order_ids())
+            .cloned()
+            .collect();
+        assert_eq!(ids, [1, 3, 2]);
+        assert_eq!(
+            book.visibility(&2),
+            Some(&Visibility::Reserve {
+                peak: 20,
+                reserve: 5
+            })
+        );
+        book.reduce(&2, 20);
+        assert_eq!(book.get(&2).map(|o| o.quantity), Some(5));
+        assert!(matches!(book.reduce(&2, 5), Some(OrderView::Full(_))));
+
+        book.reduce(&4, 10);
+        let Orders { orders, .. } =
+            book.hidden_ask_orders_at(100, None, PriceTimePriority::BothDesc);
+        let orders: Vec<_> = orders.iter().map(|(k, o)| (*k, *o.quantity())).collect();
+        assert_eq!(orders, [(4, 30)]);
+        assert_eq!(book.queue_position(&4).map(|p| p.count), Some(0));
+        book.remove(&4);
+        assert_eq!(book.ask_hidden_volume_at(100), 0);
+        book.integrity_check();
+    }
+
+    #[test]
+    fn cancel_in_the_middle_of_the_queue() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        for id in [1, 2, 3, 4, 5] {
+            book.insert_bid(id, 100, 10, ()).unwrap();
+        }
+        let ids = |book: &mut OrderBook<u64, u64, u64>| -> Vec<u64> {
+            let order_queue = book.get_bid_order_queue(100).unwrap();
+            order_queue.order_ids().cloned().collect()
+        };
+        book.remove(&3);
+        assert_eq!(ids(&mut book), [1, 2, 4, 5]);
+        assert_eq!(
+            book.queue_position(&4),
+            Some(QueuePosition {
+                count: 2,
+                volume: 20
+            })
+        );
+        // a partial cancel keeps the place, a full one leaves it
+        book.reduce(&2, 4);
+        book.reduce(&4, 10);
+        assert_eq!(ids(&mut book), [1, 2, 5]);
+        assert_eq!(
+            book.queue_position(&5),
+            Some(QueuePosition {
+                count: 2,
+                volume: 16
+            })
+        );
+        let order_queue = book.get_bid_order_queue(100).unwrap();
+        assert_eq!(
+            (order_queue.front(), order_queue.back()),
+            (Some(&1), Some(&5))
+        );
+        assert_eq!((order_queue.count(), *order_queue.volume()), (3, 26));
+
+        // new orders still join at the back, whatever slot they reuse
+        book.insert_bid(3, 100, 1, ()).unwrap();
+        book.insert_bid(6, 100, 1, ()).unwrap();
+        assert_eq!(ids(&mut book), [1, 2, 5, 3, 6]);
+        let ids: Vec<_> = (book.get_bid_order_queue(100).unwrap().order_ids().rev())
+            .cloned()
+            .collect();
+        assert_eq!(ids, [6, 3, 5, 2, 1]);
+        book.integrity_check();
+    }
+
+    #[test]
+    fn reserve_replenishes_at_the_back() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        book.insert_reserve(1, Side::Ask, 100, 35, 10, ()).unwrap();
+        book.insert_ask(2, 100, 10, ()).unwrap();
+        let status = |book: &mut OrderBook<u64, u64, u64>| {
+            let ids: Vec<_> = (book.get_ask_order_queue(100).unwrap().order_ids())
+                .cloned()
+                .collect();
+            let volumes = (book.ask_volume_at(100), book.ask_hidden_volume_at(100));
+            (ids, book.get(&1).map(|o| o.quantity), volumes)
+        };
+        assert_eq!(status(&mut book), (vec![1, 2], Some(10), (20, 25)));
+
+        // a partial fill of the peak keeps the place
+        book.reduce(&1, 4);
+        assert_eq!(status(&mut book), (vec![1, 2], Some(6), (16, 25)));
+        // the peak is filled exactly, a new peak is displayed behind 2
+        book.reduce(&1, 6);
+        assert_eq!(status(&mut book), (vec![2, 1], Some(10), (20, 15)));
+        assert_eq!(book.queue_position(&1).map(|p| p.count), Some(1));
+        // beyond the peak, the rest comes out of the reserve
+        book.reduce(&1, 13);
+        assert_eq!(status(&mut book), (vec![2, 1], Some(10), (20, 2)));
+        // the last peak is what is left of the reserve
+        book.reduce(&1, 10);
+        assert_eq!(status(&mut book), (vec![2, 1], Some(2), (12, 0)));
+        assert_eq!(
+            book.visibility(&1),
+            Some(&Visibility::Reserve {
+                peak: 10,
+                reserve: 0
+            })
+        );
+        assert!(matches!(book.reduce(&1, 2), Some(OrderView::Full(_))));
+        assert_eq!(book.total_ask_hidden_volume(), 0);
+        book.integrity_check();
+    }
+
+    #[test]
+    fn modify_keeps_or_loses_priority() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        for id in [1, 2, 3] {
+            book.insert_bid(id, 100, 10, ()).unwrap();
+        }
+        let ids = |book: &mut OrderBook<u64, u64, u64>| -> Vec<u64> {
+            let order_queue = book.get_bid_order_queue(100).unwrap();
+            order_queue.order_ids().cloned().collect()
+        };
+        assert_eq!(book.keeps_priority(&1, &100, &5), Some(true));
+        book.modify(&1, 100, 5, true).unwrap();
+        assert_eq!(ids(&mut book), [1, 2, 3]);
+        assert_eq!(book.keeps_priority(&1, &100, &8), Some(false));
+        // a size-up loses priority even if the venue asks to keep it.
+        book.modify(&1, 100, 8, true).unwrap();
+        assert_eq!(
+            (ids(&mut book), book.bid_volume_at(100)),
+            (vec![2, 3, 1], 28)
+        );
+        book.modify(&2, 100, 10, false).unwrap();
+        assert_eq!(ids(&mut book), [3, 1, 2]);
+        book.modify(&3, 101, 10, true).unwrap();
+        assert_eq!(book.bid_top().map(|(k, _)| *k), Some(3));
+        assert_eq!(book.modify(&1, 100, 0, true), Ok(None));
+        assert_eq!(book.modify(&1, 100, 5, true), Err(Error::OrderNotFound));
+        book.integrity_check();
+
+        book.insert_reserve(4, Side::Bid, 100, 30, 10, ()).unwrap();
+        book.insert_bid(6, 100, 10, ()).unwrap();
+        // the size of a reserve order includes its reserve, not only the displayed peak.
+        assert_eq!(book.keeps_priority(&4, &100, &15), Some(true));
+        assert_eq!(book.keeps_priority(&4, &100, &31), Some(false));
+        assert_eq!(book.keeps_priority(&7, &100, &1), None);
+        book.modify(&4, 100, 15, true).unwrap();
+        assert_eq!(ids(&mut book), [2, 4, 6]);
+        assert_eq!(
+            (
+                book.get(&4).map(|o| o.quantity),
+                book.bid_hidden_volume_at(100)
+            ),
+            (Some(10), 5)
+        );
+        book.modify(&4, 100, 6, true).unwrap();
+        assert_eq!(
+            (
+                book.get(&4).map(|o| o.quantity),
+                book.bid_hidden_volume_at(100)
+            ),
+            (Some(6), 0)
+        );
+        book.insert_hidden(5, Side::Bid, 99, 10, ()).unwrap();
+        book.modify(&5, 99, 20, true).unwrap();
+        assert_eq!(book.bid_hidden_volume_at(99), 20);
+        book.modify(&5, 98, 20, true).unwrap();
+        assert_eq!(book.sorted_hidden_bid_prices().next(), Some(&98));
+        book.integrity_check();
     }
 }
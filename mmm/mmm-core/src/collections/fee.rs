@@ -0,0 +1,120 @@
+use super::Side;
+use serde::{Deserialize, Serialize};
+use std::ops::{AddAssign, Mul};
+
+/// Liquidity of an execution, whether the order added to the book or removed from it.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// FeeModel decides the fee of an execution, a negative fee is a rebate.
+/// It is given every execution in order, so a model may keep state such as traded volume.
+/// V: value, price and quantity of the execution as well as the fee
+pub trait FeeModel<V> {
+    fn fee(&mut self, side: Side, price: &V, quantity: &V, liquidity: Liquidity) -> V;
+}
+
+/// No fees.
+impl<V: Default> FeeModel<V> for () {
+    #[inline]
+    fn fee(&mut self, _side: Side, _price: &V, _quantity: &V, _liquidity: Liquidity) -> V {
+        V::default()
+    }
+}
+
+/// PerShare charges a fixed amount per share, as equity venues do for adding and removing liquidity.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct PerShare<V> {
+    pub maker: V,
+    pub taker: V,
+}
+
+impl<V: Mul<Output = V> + Clone> FeeModel<V> for PerShare<V> {
+    fn fee(&mut self, _side: Side, _price: &V, quantity: &V, liquidity: Liquidity) -> V {
+        let rate = match liquidity {
+            Liquidity::Maker => self.maker.clone(),
+            Liquidity::Taker => self.taker.clone(),
+        };
+        quantity.clone() * rate
+    }
+}
+
+/// Rate charges a fraction of the notional, e.g. Coinbase fee rates.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Rate<V> {
+    pub maker: V,
+    pub taker: V,
+}
+
+impl<V: Mul<Output = V> + Clone> FeeModel<V> for Rate<V> {
+    fn fee(&mut self, _side: Side, price: &V, quantity: &V, liquidity: Liquidity) -> V {
+        let rate = match liquidity {
+            Liquidity::Maker => self.maker.clone(),
+            Liquidity::Taker => self.taker.clone(),
+        };
+        price.clone() * quantity.clone() * rate
+    }
+}
+
+/// Tiered picks the model of the highest tier whose volume has been traded before an execution,
+/// as in the add/remove liquidity tiers of NASDAQ or ARCA.
+/// tiers are (minimum traded volume, model) in increasing volume, the first one applies from the start.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Tiered<V, F> {
+    tiers: Vec<(V, F)>,
+    volume: V,
+}
+
+impl<V: Default, F> Tiered<V, F> {
+    pub fn new(tiers: Vec<(V, F)>) -> Self {
+        Self {
+            tiers,
+            volume: V::default(),
+        }
+    }
+
+    /// Starts from `volume` already traded, e.g. earlier in the month.
+    pub fn with_volume(tiers: Vec<(V, F)>, volume: V) -> Self {
+        Self { tiers, volume }
+    }
+
+    pub fn volume(&self) -> &V {
+        &self.volume
+    }
+}
+
+impl<V, F> FeeModel<V> for Tiered<V, F>
+where
+    V: AddAssign + Default + PartialOrd + Clone,
+    F: FeeModel<V>,
+{
+    fn fee(&mut self, side: Side, price: &V, quantity: &V, liquidity: Liquidity) -> V {
+        let volume = &self.volume;
+        let fee = match self.tiers.iter_mut().rev().find(|(min, _)| min <= volume) {
+            Some((_, model)) => model.fee(side, price, quantity, liquidity),
+            None => V::default(),
+        };
+        self.volume += quantity.clone();
+        fee
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_follow_volume() {
+        let per_share = |maker, taker| PerShare { maker, taker };
+        let mut model = Tiered::new(vec![(0, per_share(-20, 30)), (1000, per_share(-25, 30))]);
+        assert_eq!(model.fee(Side::Bid, &100, &600, Liquidity::Maker), -12000);
+        assert_eq!(model.fee(Side::Bid, &100, &600, Liquidity::Taker), 18000);
+        assert_eq!(model.fee(Side::Ask, &100, &100, Liquidity::Maker), -2500);
+        assert_eq!(*model.volume(), 1300);
+
+        let mut rate = Rate { maker: 1, taker: 2 };
+        assert_eq!(rate.fee(Side::Ask, &100, &3, Liquidity::Taker), 600);
+    }
+}
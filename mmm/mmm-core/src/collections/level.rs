@@ -0,0 +1,298 @@
+use super::book::{AskPrice, BidPrice, SidePrice};
+use super::observer::{BookEvent, BookObserver};
+use super::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{Add, AddAssign, Div, Rem, Sub, SubAssign};
+
+/// Levels keeps the volume of each price of a side, best price first.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+struct Levels<S: Ord, Q>(BTreeMap<S, Q>);
+
+impl<S: SidePrice, Q> Levels<S, Q>
+where
+    Q: AddAssign + SubAssign + Default + Clone + Ord,
+{
+    fn set(&mut self, price: S::Price, quantity: Q) {
+        if quantity == Q::default() {
+            self.0.remove(&S::new(price));
+        } else {
+            self.0.insert(S::new(price), quantity);
+        }
+    }
+
+    fn increment(&mut self, price: S::Price, quantity: Q) {
+        *self.0.entry(S::new(price)).or_default() += quantity;
+    }
+
+    fn decrement(&mut self, price: S::Price, quantity: Q) {
+        let price = S::new(price);
+        if let Some(volume) = self.0.get_mut(&price) {
+            if *volume <= quantity {
+                self.0.remove(&price);
+            } else {
+                *volume -= quantity;
+            }
+        }
+    }
+
+    fn volume_at(&self, price: S::Price) -> Q {
+        self.0.get(&S::new(price)).cloned().unwrap_or_default()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&S::Price, &Q)> {
+        self.0.iter().map(|(p, q)| (p.price(), q))
+    }
+}
+
+/// PriceLevelBook keeps aggregated depth, the volume at each price, without individual orders.
+/// It is maintained from L2 updates with `set`, or from an OrderBook by observing it.
+/// PriceLevelBook has generics: P, Q
+/// P: price
+/// Q: quantity
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct PriceLevelBook<P: Ord, Q> {
+    ask_levels: Levels<AskPrice<P>, Q>,
+    bid_levels: Levels<BidPrice<P>, Q>,
+}
+
+impl<P: Ord, Q> Default for PriceLevelBook<P, Q> {
+    fn default() -> Self {
+        Self {
+            ask_levels: Levels(BTreeMap::new()),
+            bid_levels: Levels(BTreeMap::new()),
+        }
+    }
+}
+
+impl<P, Q> PriceLevelBook<P, Q>
+where
+    P: Ord + Clone + Debug,
+    Q: AddAssign + SubAssign + Default + Clone + Ord,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the volume at `price`, as in an L2 update. Zero removes the level.
+    pub fn set(&mut self, side: Side, price: P, quantity: Q) {
+        match side {
+            Side::Ask => self.ask_levels.set(price, quantity),
+            Side::Bid => self.bid_levels.set(price, quantity),
+        }
+    }
+
+    pub fn increment(&mut self, side: Side, price: P, quantity: Q) {
+        match side {
+            Side::Ask => self.ask_levels.increment(price, quantity),
+            Side::Bid => self.bid_levels.increment(price, quantity),
+        }
+    }
+
+    /// Decreases the volume at `price`, removing the level if nothing remains.
+    pub fn decrement(&mut self, side: Side, price: P, quantity: Q) {
+        match side {
+            Side::Ask => self.ask_levels.decrement(price, quantity),
+            Side::Bid => self.bid_levels.decrement(price, quantity),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ask_levels.0.clear();
+        self.bid_levels.0.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ask_levels.0.is_empty() && self.bid_levels.0.is_empty()
+    }
+
+    pub fn ask_volume_at(&self, price: P) -> Q {
+        self.ask_levels.volume_at(price)
+    }
+
+    pub fn bid_volume_at(&self, price: P) -> Q {
+        self.bid_levels.volume_at(price)
+    }
+
+    /// Ask levels, lowest price first.
+    pub fn ask_levels(&self) -> impl Iterator<Item = (&P, &Q)> {
+        self.ask_levels.iter()
+    }
+
+    /// Bid levels, highest price first.
+    pub fn bid_levels(&self) -> impl Iterator<Item = (&P, &Q)> {
+        self.bid_levels.iter()
+    }
+
+    pub fn best_ask(&self) -> Option<(&P, &Q)> {
+        self.ask_levels().next()
+    }
+
+    pub fn best_bid(&self) -> Option<(&P, &Q)> {
+        self.bid_levels().next()
+    }
+
+    /// Ask levels priced at or below `limit`.
+    pub fn ask_levels_within<'a>(&'a self, limit: &'a P) -> impl Iterator<Item = (&'a P, &'a Q)> {
+        self.ask_levels().take_while(move |(p, _)| *p <= limit)
+    }
+
+    /// Bid levels priced at or above `limit`.
+    pub fn bid_levels_within<'a>(&'a self, limit: &'a P) -> impl Iterator<Item = (&'a P, &'a Q)> {
+        self.bid_levels().take_while(move |(p, _)| *p >= limit)
+    }
+
+    /// Volumes of the best `level` prices of each side, keyed by "Ask" and "Bid".
+    pub fn level_summary(&self, level: usize) -> HashMap<String, HashMap<P, Q>>
+    where
+        P: Hash,
+    {
+        let mut summary = HashMap::new();
+        summary.insert("Ask".to_string(), collect(self.ask_levels().take(level)));
+        summary.insert("Bid".to_string(), collect(self.bid_levels().take(level)));
+        summary
+    }
+}
+
+impl<P, Q> PriceLevelBook<P, Q>
+where
+    P: Ord
+        + Clone
+        + Debug
+        + Hash
+        + Add<Output = P>
+        + Sub<Output = P>
+        + Div<Output = P>
+        + Rem<Output = P>
+        + AddAssign
+        + From<u8>,
+    Q: AddAssign + SubAssign + Default + Clone + Ord,
+{
+    /// Mid price rounded down, the mark of a position.
+    pub fn mid(&self) -> Option<P> {
+        let (a, b) = (self.best_ask()?.0, self.best_bid()?.0);
+        Some((a.clone() + b.clone()) / P::from(2))
+    }
+
+    /// Ask and bid limits `spread` away from the mid price, or from the best price of a one-sided book.
+    /// The bid limit is rounded up when the mid price falls between ticks.
+    pub fn spread_limit(&self, spread: P) -> (Option<P>, Option<P>) {
+        match (self.best_ask(), self.best_bid()) {
+            (None, None) => (None, None),
+            (None, Some((b, _))) => (None, Some(b.clone() - spread)),
+            (Some((a, _)), None) => (Some(a.clone() + spread), None),
+            (Some((a, _)), Some((b, _))) => {
+                let sum = a.clone() + b.clone();
+                let mid = sum.clone() / P::from(2);
+                let ask_limit = mid.clone() + spread.clone();
+                let mut bid_limit = mid - spread;
+                if sum % P::from(2) != P::from(0) {
+                    bid_limit += P::from(1);
+                }
+                (Some(ask_limit), Some(bid_limit))
+            }
+        }
+    }
+
+    /// Volumes of the prices within `spread` of the mid price, keyed by "Ask" and "Bid".
+    pub fn spread_summary(&self, spread: P) -> HashMap<String, HashMap<P, Q>> {
+        let (ask_limit, bid_limit) = self.spread_limit(spread);
+        let mut summary = HashMap::new();
+        summary.insert(
+            "Ask".to_string(),
+            ask_limit
+                .map(|limit| collect(self.ask_levels_within(&limit)))
+                .unwrap_or_default(),
+        );
+        summary.insert(
+            "Bid".to_string(),
+            bid_limit
+                .map(|limit| collect(self.bid_levels_within(&limit)))
+                .unwrap_or_default(),
+        );
+        summary
+    }
+}
+
+fn collect<'a, P, Q>(levels: impl Iterator<Item = (&'a P, &'a Q)>) -> HashMap<P, Q>
+where
+    P: Eq + Hash + Clone + 'a,
+    Q: Clone + 'a,
+{
+    levels.map(|(p, q)| (p.clone(), q.clone())).collect()
+}
+
+/// An OrderBook with a PriceLevelBook observer keeps its depth up to date.
+impl<K, P, Q> BookObserver<K, P, Q> for PriceLevelBook<P, Q>
+where
+    P: Ord + Clone + Debug,
+    Q: AddAssign + SubAssign + Default + Clone + Ord,
+{
+    fn on_event(&mut self, event: BookEvent<K, P, Q>) {
+        match event {
+            BookEvent::OrderAdded {
+                side,
+                price,
+                quantity,
+                ..
+            } => self.increment(side, price.clone(), quantity),
+            BookEvent::OrderReduced {
+                side,
+                price,
+                quantity,
+                ..
+            }
+            | BookEvent::OrderRemoved {
+                side,
+                price,
+                quantity,
+                ..
+            } => self.decrement(side, price.clone(), quantity),
+            BookEvent::LevelCreated { .. }
+            | BookEvent::LevelEmptied { .. }
+            | BookEvent::BboChanged { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::book::OrderBook;
+
+    #[test]
+    fn levels_follow_order_book() {
+        let mut book: OrderBook<u64, u64, u64, (), PriceLevelBook<u64, u64>> = OrderBook::new();
+        book.insert(1, Side::Ask, 103, 10, ()).unwrap();
+        book.insert(2, Side::Ask, 101, 20, ()).unwrap();
+        book.insert(3, Side::Ask, 101, 5, ()).unwrap();
+        book.insert(4, Side::Bid, 99, 30, ()).unwrap();
+        book.insert(5, Side::Bid, 96, 40, ()).unwrap();
+        book.reduce(&2, 15);
+        book.remove(&5);
+        assert_eq!(book.observer(), &book.price_levels());
+
+        let levels = book.observer();
+        assert_eq!(levels.best_ask(), Some((&101, &10)));
+        assert_eq!(levels.best_bid(), Some((&99, &30)));
+        assert_eq!(levels.bid_volume_at(96), 0);
+        assert_eq!(
+            levels.level_summary(1)["Ask"],
+            HashMap::from_iter([(101, 10)])
+        );
+        // mid is 100, so asks up to 102 and bids from 98 are within 2.
+        assert_eq!(levels.mid(), Some(100));
+        assert_eq!(levels.spread_limit(2), (Some(102), Some(98)));
+        assert_eq!(levels.spread_summary(2)["Ask"].len(), 1);
+        assert_eq!(levels.spread_summary(2)["Bid"].len(), 1);
+
+        let mut levels = levels.clone();
+        levels.set(Side::Ask, 101, 0);
+        levels.decrement(Side::Bid, 99, 50);
+        assert_eq!(levels.ask_levels().collect::<Vec<_>>(), [(&103, &10)]);
+        assert_eq!(levels.spread_limit(2), (Some(105), None));
+        assert!(levels.spread_summary(2)["Bid"].is_empty());
+    }
+}
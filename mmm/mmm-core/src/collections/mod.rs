@@ -1,5 +1,11 @@
 pub mod account;
 pub mod book;
+pub mod fee;
+pub mod level;
+pub mod observer;
+pub mod pnl;
+pub mod position;
+pub mod risk;
 
 use serde::{Deserialize, Serialize};
 
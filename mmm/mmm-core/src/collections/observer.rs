@@ -0,0 +1,175 @@
+use super::Side;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// BookEvent is a change of an OrderBook given to its BookObserver, right after the change.
+/// It has generics: K, P, Q
+/// K: order id
+/// P: price
+/// Q: quantity
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum BookEvent<'a, K, P, Q> {
+    /// An order joined its level with `quantity`.
+    OrderAdded {
+        id: &'a K,
+        side: Side,
+        price: &'a P,
+        quantity: Q,
+    },
+    /// An order lost `quantity` and `remaining` is left.
+    OrderReduced {
+        id: &'a K,
+        side: Side,
+        price: &'a P,
+        quantity: Q,
+        remaining: Q,
+    },
+    /// An order left the book with `quantity` not yet filled or cancelled.
+    OrderRemoved {
+        id: &'a K,
+        side: Side,
+        price: &'a P,
+        quantity: Q,
+    },
+    /// The first order of a price level arrived.
+    LevelCreated { side: Side, price: &'a P },
+    /// The last order of a price level left.
+    LevelEmptied { side: Side, price: &'a P },
+    /// Price or volume of the best level of `side` changed, `price` is None if the side is empty.
+    BboChanged {
+        side: Side,
+        price: Option<&'a P>,
+        volume: Q,
+    },
+}
+
+/// BookObserver subscribes to the changes of an OrderBook.
+/// The book owns its observer, `()` observes nothing.
+pub trait BookObserver<K, P, Q> {
+    fn on_event(&mut self, event: BookEvent<K, P, Q>);
+}
+
+impl<K, P, Q> BookObserver<K, P, Q> for () {
+    #[inline]
+    fn on_event(&mut self, _event: BookEvent<K, P, Q>) {}
+}
+
+/// BookChanges collects levels touched since it was last taken.
+/// Consumers can skip recomputing snapshots while it is empty.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BookChanges<P: Eq + Hash> {
+    pub levels: HashSet<(Side, P)>,
+    pub bbo_changed: bool,
+}
+
+impl<P: Eq + Hash> Default for BookChanges<P> {
+    fn default() -> Self {
+        Self {
+            levels: HashSet::new(),
+            bbo_changed: false,
+        }
+    }
+}
+
+impl<P: Eq + Hash> BookChanges<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Changes so far, leaving this empty.
+    pub fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+}
+
+impl<K, P: Eq + Hash + Clone, Q> BookObserver<K, P, Q> for BookChanges<P> {
+    fn on_event(&mut self, event: BookEvent<K, P, Q>) {
+        match event {
+            BookEvent::OrderAdded { side, price, .. }
+            | BookEvent::OrderReduced { side, price, .. }
+            | BookEvent::OrderRemoved { side, price, .. } => {
+                self.levels.insert((side, price.clone()));
+            }
+            BookEvent::BboChanged { .. } => self.bbo_changed = true,
+            BookEvent::LevelCreated { .. } | BookEvent::LevelEmptied { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::book::OrderBook;
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl BookObserver<u64, u64, u64> for Log {
+        fn on_event(&mut self, event: BookEvent<u64, u64, u64>) {
+            self.0.push(format!("{:?}", event));
+        }
+    }
+
+    #[test]
+    fn events_in_order() {
+        let mut book: OrderBook<u64, u64, u64, (), Log> = OrderBook::new();
+        book.insert(1, Side::Bid, 100, 10, ()).unwrap();
+        book.insert(2, Side::Bid, 99, 20, ()).unwrap();
+        book.reduce(&2, 5);
+        book.reduce(&1, 10);
+        assert_eq!(
+            book.observer().0,
+            [
+                "LevelCreated { side: Bid, price: 100 }",
+                "OrderAdded { id: 1, side: Bid, price: 100, quantity: 10 }",
+                "BboChanged { side: Bid, price: Some(100), volume: 10 }",
+                "LevelCreated { side: Bid, price: 99 }",
+                "OrderAdded { id: 2, side: Bid, price: 99, quantity: 20 }",
+                "OrderReduced { id: 2, side: Bid, price: 99, quantity: 5, remaining: 15 }",
+                "OrderRemoved { id: 1, side: Bid, price: 100, quantity: 10 }",
+                "LevelEmptied { side: Bid, price: 100 }",
+                "BboChanged { side: Bid, price: Some(99), volume: 15 }",
+            ]
+        );
+
+        let mut book: OrderBook<u64, u64, u64, (), BookChanges<u64>> = OrderBook::new();
+        book.insert(1, Side::Ask, 100, 10, ()).unwrap();
+        book.insert(2, Side::Ask, 101, 10, ()).unwrap();
+        let changes = book.observer_mut().take();
+        assert_eq!(changes.levels.len(), 2);
+        assert!(changes.bbo_changed && book.observer().is_empty());
+        book.remove(&2);
+        assert!(!book.observer().bbo_changed);
+    }
+
+    #[test]
+    fn replenish_and_modify_events_in_order() {
+        let mut book: OrderBook<u64, u64, u64, (), Log> = OrderBook::new();
+        book.insert_reserve(1, Side::Ask, 100, 25, 10, ()).unwrap();
+        book.insert(2, Side::Ask, 100, 5, ()).unwrap();
+        book.insert_hidden(3, Side::Ask, 99, 5, ()).unwrap();
+        book.observer_mut().0.clear();
+
+        // the reserve leaves and joins the back with a new peak, the hidden order sends nothing
+        book.reduce(&1, 12);
+        book.reduce(&3, 5);
+        book.modify(&2, 101, 5, true).unwrap();
+        assert_eq!(
+            book.observer().0,
+            [
+                "OrderRemoved { id: 1, side: Ask, price: 100, quantity: 10 }",
+                "BboChanged { side: Ask, price: Some(100), volume: 5 }",
+                "OrderAdded { id: 1, side: Ask, price: 100, quantity: 10 }",
+                "BboChanged { side: Ask, price: Some(100), volume: 15 }",
+                "OrderRemoved { id: 2, side: Ask, price: 100, quantity: 5 }",
+                "BboChanged { side: Ask, price: Some(100), volume: 10 }",
+                "LevelCreated { side: Ask, price: 101 }",
+                "OrderAdded { id: 2, side: Ask, price: 101, quantity: 5 }",
+            ]
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use super::account::ExecOrder;
+use super::fee::{FeeModel, Liquidity};
+use super::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::iter::Sum;
+use std::ops::{AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// Lot is an open part of a position, opened at `price`.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Lot<V> {
+    pub price: V,
+    pub quantity: V,
+}
+
+/// Position is the inventory of a symbol with its open lots.
+/// Lots are closed first in first out, so PnL is exact without division.
+/// quantity is positive when long and negative when short, realized PnL is before fees.
+/// V: value
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Default)]
+pub struct Position<V> {
+    quantity: V,
+    lots: VecDeque<Lot<V>>,
+    realized: V,
+    fees: V,
+}
+
+impl<V> Position<V>
+where
+    V: Default
+        + Clone
+        + Ord
+        + Sub<Output = V>
+        + Mul<Output = V>
+        + Neg<Output = V>
+        + AddAssign
+        + SubAssign
+        + Sum,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn quantity(&self) -> &V {
+        &self.quantity
+    }
+
+    /// Open lots, the oldest first.
+    pub fn lots(&self) -> impl Iterator<Item = &Lot<V>> {
+        self.lots.iter()
+    }
+
+    pub fn realized(&self) -> &V {
+        &self.realized
+    }
+
+    pub fn fees(&self) -> &V {
+        &self.fees
+    }
+
+    /// Cost of the open lots, negative when short.
+    pub fn cost_basis(&self) -> V {
+        let cost: V = (self.lots.iter())
+            .map(|lot| lot.price.clone() * lot.quantity.clone())
+            .sum();
+        if self.quantity < V::default() {
+            -cost
+        } else {
+            cost
+        }
+    }
+
+    pub fn average_cost(&self) -> Option<V>
+    where
+        V: Div<Output = V>,
+    {
+        (self.quantity != V::default()).then(|| self.cost_basis() / self.quantity.clone())
+    }
+
+    /// PnL of the open lots marked at `mark`.
+    pub fn unrealized(&self, mark: &V) -> V {
+        mark.clone() * self.quantity.clone() - self.cost_basis()
+    }
+
+    /// Realized and unrealized PnL after fees.
+    pub fn pnl(&self, mark: &V) -> V {
+        let mut pnl = self.unrealized(mark);
+        pnl += self.realized.clone();
+        pnl -= self.fees.clone();
+        pnl
+    }
+
+    /// An execution of `quantity` at `price` closes lots of the other direction first,
+    /// and the rest opens a new lot.
+    pub fn fill(&mut self, side: Side, price: V, quantity: V, fee: V) {
+        let zero = V::default();
+        let buy = side == Side::Bid;
+        self.fees += fee;
+        let mut remaining = quantity;
+        while remaining > zero && self.quantity != zero && (self.quantity > zero) != buy {
+            let lot = self.lots.front_mut().unwrap();
+            let closed = lot.quantity.clone().min(remaining.clone());
+            let gain = match buy {
+                true => lot.price.clone() - price.clone(),
+                false => price.clone() - lot.price.clone(),
+            };
+            self.realized += gain * closed.clone();
+            lot.quantity -= closed.clone();
+            if lot.quantity == zero {
+                self.lots.pop_front();
+            }
+            remaining -= closed.clone();
+            match buy {
+                true => self.quantity += closed,
+                false => self.quantity -= closed,
+            }
+        }
+        if remaining > zero {
+            match buy {
+                true => self.quantity += remaining.clone(),
+                false => self.quantity -= remaining.clone(),
+            }
+            self.lots.push_back(Lot {
+                price,
+                quantity: remaining,
+            });
+        }
+    }
+}
+
+/// Portfolio keeps a position per symbol and charges executions with its FeeModel.
+/// It has generics: S, V, F
+/// S: symbol
+/// V: value
+/// F: fee model, `()` charges nothing
+#[derive(Clone, Debug)]
+pub struct Portfolio<S, V, F = ()> {
+    positions: HashMap<S, Position<V>>,
+    fee_model: F,
+}
+
+impl<S, V, F: Default> Default for Portfolio<S, V, F> {
+    fn default() -> Self {
+        Self::with_fee_model(F::default())
+    }
+}
+
+impl<S, V, F> Portfolio<S, V, F> {
+    pub fn with_fee_model(fee_model: F) -> Self {
+        Self {
+            positions: HashMap::new(),
+            fee_model,
+        }
+    }
+
+    pub fn fee_model(&self) -> &F {
+        &self.fee_model
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&S, &Position<V>)> {
+        self.positions.iter()
+    }
+}
+
+impl<S, V, F> Portfolio<S, V, F>
+where
+    S: Eq + Hash,
+    V: Default
+        + Clone
+        + Ord
+        + Sub<Output = V>
+        + Mul<Output = V>
+        + Neg<Output = V>
+        + AddAssign
+        + SubAssign
+        + Sum,
+    F: FeeModel<V>,
+{
+    pub fn new() -> Self
+    where
+        F: Default,
+    {
+        Self::default()
+    }
+
+    pub fn position(&self, symbol: &S) -> Option<&Position<V>> {
+        self.positions.get(symbol)
+    }
+
+    pub fn execute(
+        &mut self,
+        symbol: S,
+        side: Side,
+        price: V,
+        quantity: V,
+        liquidity: Liquidity,
+    ) -> &Position<V> {
+        let fee = self.fee_model.fee(side, &price, &quantity, liquidity);
+        let position = self.positions.entry(symbol).or_default();
+        position.fill(side, price, quantity, fee);
+        position
+    }
+
+    /// Applies an execution recorded by `Account`.
+    pub fn apply<K, L, Q, I>(
+        &mut self,
+        symbol: S,
+        order: &ExecOrder<K, L, Q, I>,
+        liquidity: Liquidity,
+    ) -> &Position<V>
+    where
+        L: Clone + Into<V>,
+        Q: Clone + Into<V>,
+    {
+        let (price, quantity) = (order.price.clone().into(), order.quantity.clone().into());
+        self.execute(symbol, order.side, price, quantity, liquidity)
+    }
+
+    pub fn realized(&self) -> V {
+        self.positions.values().map(|p| p.realized.clone()).sum()
+    }
+
+    pub fn fees(&self) -> V {
+        self.positions.values().map(|p| p.fees.clone()).sum()
+    }
+
+    /// Unrealized PnL with `mark` of each symbol, e.g. the mid of its book.
+    /// Symbols without a mark are left out.
+    pub fn unrealized(&self, mut mark: impl FnMut(&S) -> Option<V>) -> V {
+        (self.positions.iter())
+            .filter_map(|(symbol, position)| Some(position.unrealized(&mark(symbol)?)))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::fee::PerShare;
+
+    #[test]
+    fn fifo_pnl_with_fees() {
+        let fee_model = PerShare {
+            maker: -2i64,
+            taker: 3,
+        };
+        let mut portfolio = Portfolio::with_fee_model(fee_model);
+        portfolio.execute("A", Side::Bid, 100, 10, Liquidity::Maker);
+        portfolio.execute("A", Side::Bid, 110, 10, Liquidity::Taker);
+        let position = portfolio.execute("A", Side::Ask, 120, 15, Liquidity::Taker);
+        // 10 at 100 and 5 at 110 are closed.
+        assert_eq!((*position.quantity(), *position.realized()), (5, 250));
+        assert_eq!(
+            (position.cost_basis(), position.average_cost()),
+            (550, Some(110))
+        );
+        assert_eq!(*position.fees(), -20 + 30 + 45);
+
+        // flips to short 5 at 90.
+        let position = portfolio.execute("A", Side::Ask, 90, 10, Liquidity::Maker);
+        assert_eq!((*position.quantity(), *position.realized()), (-5, 150));
+        assert_eq!(position.unrealized(&80), 50);
+        assert_eq!(position.pnl(&80), 150 + 50 - 35);
+
+        portfolio.execute("B", Side::Bid, 10, 1, Liquidity::Maker);
+        assert_eq!(portfolio.unrealized(|s| (*s == "A").then_some(80)), 50);
+        assert_eq!(portfolio.fees(), 35 - 2);
+    }
+}
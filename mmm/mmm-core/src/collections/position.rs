@@ -0,0 +1,303 @@
+use super::book::{OrderBook, QueuePosition};
+use super::observer::{BookEvent, BookObserver};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::iter::Sum;
+use std::ops::{AddAssign, SubAssign};
+
+/// TrackedOrder keeps the orders ahead of a tracked order with their remaining quantity.
+#[derive(Clone, Debug)]
+struct TrackedOrder<K, Q> {
+    quantity: Q,
+    position: QueuePosition<Q>,
+    ahead: HashMap<K, Q>,
+}
+
+/// QueueTracker follows queue positions of a few orders, e.g. our own, as the book changes.
+/// As the observer of the book it follows it by itself, see `OrderBook::track`,
+/// otherwise it is fed with the same changes applied to the book.
+/// Each change costs O(tracked orders).
+/// New orders join at the back of a level, so insertions never move a tracked order.
+/// A tracked order that leaves its place, e.g. a replenished reserve, stops being tracked.
+/// Hidden orders are not tracked, as the book sends no events for them.
+/// K: order id
+/// Q: quantity
+#[derive(Clone, Debug)]
+pub struct QueueTracker<K, Q> {
+    tracked: HashMap<K, TrackedOrder<K, Q>>,
+}
+
+impl<K, Q> Default for QueueTracker<K, Q> {
+    fn default() -> Self {
+        Self {
+            tracked: HashMap::new(),
+        }
+    }
+}
+
+impl<K, Q> QueueTracker<K, Q>
+where
+    K: Eq + Hash + Clone + Debug,
+    Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts tracking `id` from its current position in `book`.
+    /// Returns None if `id` is not in `book` or is hidden.
+    pub fn track<P, I, O>(
+        &mut self,
+        book: &OrderBook<K, P, Q, I, O>,
+        id: K,
+    ) -> Option<&QueuePosition<Q>>
+    where
+        P: Ord + Debug + Clone,
+        I: Debug,
+        O: BookObserver<K, P, Q>,
+    {
+        let (quantity, ahead) = Self::queue_of(book, &id)?;
+        self.insert(id, quantity, ahead)
+    }
+
+    // quantity of `id` and remaining quantity of each order ahead of it, None if hidden
+    fn queue_of<P, I, O>(book: &OrderBook<K, P, Q, I, O>, id: &K) -> Option<(Q, HashMap<K, Q>)>
+    where
+        P: Ord + Debug + Clone,
+        I: Debug,
+        O: BookObserver<K, P, Q>,
+    {
+        if book.visibility(id)?.is_hidden() {
+            return None;
+        }
+        let quantity = book.get(id)?.quantity.clone();
+        let ahead = book
+            .orders_ahead(id)?
+            .into_iter()
+            .map(|(k, o)| (k.clone(), o.quantity.clone()))
+            .collect();
+        Some((quantity, ahead))
+    }
+
+    fn insert(&mut self, id: K, quantity: Q, ahead: HashMap<K, Q>) -> Option<&QueuePosition<Q>> {
+        let position = QueuePosition {
+            count: ahead.len(),
+            volume: ahead.values().cloned().sum(),
+        };
+        let tracked = TrackedOrder {
+            quantity,
+            position,
+            ahead,
+        };
+        self.tracked.insert(id.clone(), tracked);
+        self.position(&id)
+    }
+
+    pub fn untrack<R>(&mut self, id: &R) -> Option<QueuePosition<Q>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        self.tracked.remove(id).map(|t| t.position)
+    }
+
+    pub fn position<R>(&self, id: &R) -> Option<&QueuePosition<Q>>
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        self.tracked.get(id).map(|t| &t.position)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&K, &QueuePosition<Q>)> {
+        self.tracked.iter().map(|(k, t)| (k, &t.position))
+    }
+
+    /// Order `id` was reduced by `quantity`, as in `OrderBook::reduce`.
+    /// It is removed if nothing remains.
+    pub fn reduced<R>(&mut self, id: &R, quantity: Q)
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        if let Some(tracked) = self.tracked.get_mut(id) {
+            if tracked.quantity <= quantity {
+                self.tracked.remove(id);
+            } else {
+                tracked.quantity -= quantity.clone();
+            }
+        }
+        for tracked in self.tracked.values_mut() {
+            if let Some(remaining) = tracked.ahead.get_mut(id) {
+                if *remaining <= quantity {
+                    tracked.position.volume -= remaining.clone();
+                    tracked.position.count -= 1;
+                    tracked.ahead.remove(id);
+                } else {
+                    *remaining -= quantity.clone();
+                    tracked.position.volume -= quantity.clone();
+                }
+            }
+        }
+    }
+
+    /// Order `id` was removed, as in `OrderBook::remove`.
+    /// A tracked order stops being tracked.
+    pub fn removed<R>(&mut self, id: &R)
+    where
+        K: Borrow<R>,
+        R: Hash + Eq,
+    {
+        self.tracked.remove(id);
+        for tracked in self.tracked.values_mut() {
+            if let Some(remaining) = tracked.ahead.remove(id) {
+                tracked.position.volume -= remaining;
+                tracked.position.count -= 1;
+            }
+        }
+    }
+}
+
+impl<K, P, Q> BookObserver<K, P, Q> for QueueTracker<K, Q>
+where
+    K: Eq + Hash + Clone + Debug,
+    Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
+{
+    fn on_event(&mut self, event: BookEvent<K, P, Q>) {
+        match event {
+            BookEvent::OrderReduced { id, quantity, .. } => self.reduced(id, quantity),
+            BookEvent::OrderRemoved { id, .. } => self.removed(id),
+            BookEvent::OrderAdded { .. }
+            | BookEvent::LevelCreated { .. }
+            | BookEvent::LevelEmptied { .. }
+            | BookEvent::BboChanged { .. } => {}
+        }
+    }
+}
+
+impl<K, P, Q, I> OrderBook<K, P, Q, I, QueueTracker<K, Q>>
+where
+    K: Eq + Hash + Clone + Debug,
+    P: Ord + Debug + Clone,
+    Q: AddAssign + SubAssign + Default + Clone + Sum + Debug + Ord,
+    I: Debug,
+{
+    /// Starts tracking `id` in the QueueTracker observing the book, from its current position.
+    /// Returns None if `id` is not in the book or is hidden.
+    pub fn track(&mut self, id: K) -> Option<&QueuePosition<Q>> {
+        let (quantity, ahead) = QueueTracker::queue_of(self, &id)?;
+        self.observer_mut().insert(id, quantity, ahead)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collections::Side;
+
+    #[test]
+    fn tracker_follows_queue_position() {
+        let mut book: OrderBook<u64, u64, u64> = OrderBook::new();
+        for (id, quantity) in [(1, 10), (2, 20), (3, 30), (4, 40)] {
+            book.insert(id, Side::Bid, 100, quantity, ()).unwrap();
+        }
+        book.insert(5, Side::Bid, 99, 50, ()).unwrap();
+        let mut tracker = QueueTracker::new();
+        tracker.track(&book, 3);
+        tracker.track(&book, 4);
+        assert_eq!(
+            book.queue_position(&3),
+            Some(QueuePosition {
+                count: 2,
+                volume: 30
+            })
+        );
+
+        // 1 is removed, then reused behind 4.
+        book.remove(&1);
+        tracker.removed(&1);
+        book.insert(1, Side::Bid, 100, 5, ()).unwrap();
+        book.reduce(&2, 15);
+        tracker.reduced(&2, 15);
+        book.reduce(&3, 10);
+        tracker.reduced(&3, 10);
+        for id in [3, 4] {
+            assert_eq!(tracker.position(&id), book.queue_position(&id).as_ref());
+        }
+        assert_eq!(
+            tracker.position(&4),
+            Some(&QueuePosition {
+                count: 2,
+                volume: 25
+            })
+        );
+
+        book.reduce(&2, 5);
+        tracker.reduced(&2, 5);
+        assert_eq!(tracker.position(&3), Some(&QueuePosition::default()));
+        book.reduce(&3, 20);
+        tracker.reduced(&3, 20);
+        assert_eq!(tracker.position(&3), None);
+        assert_eq!(tracker.position(&4), book.queue_position(&4).as_ref());
+        assert_eq!(book.queue_position(&1).map(|p| p.count), Some(1));
+    }
+
+    #[test]
+    fn tracker_observes_the_book() {
+        let mut book: OrderBook<u64, u64, u64, (), QueueTracker<u64, u64>> = OrderBook::new();
+        for (id, quantity) in [(1, 10), (2, 20), (3, 30)] {
+            book.insert(id, Side::Ask, 100, quantity, ()).unwrap();
+        }
+        book.insert_reserve(4, Side::Ask, 100, 40, 10, ()).unwrap();
+        assert_eq!(book.track(5), None);
+        book.track(3);
+        book.track(4);
+
+        book.reduce(&1, 4);
+        book.remove(&2);
+        book.modify(&1, 100, 2, true).unwrap();
+        for id in [3, 4] {
+            assert_eq!(
+                book.observer().position(&id),
+                book.queue_position(&id).as_ref()
+            );
+        }
+        assert_eq!(
+            book.observer().position(&4),
+            Some(&QueuePosition {
+                count: 2,
+                volume: 32
+            })
+        );
+
+        // the displayed 10 of 4 is filled and the reserve joins the back.
+        book.reduce(&4, 10);
+        assert_eq!(book.observer().position(&4), None);
+        book.reduce(&1, 2);
+        assert_eq!(
+            book.observer().position(&3),
+            Some(&QueuePosition::default())
+        );
+        assert_eq!(book.observer().positions().count(), 1);
+    }
+
+    #[test]
+    fn hidden_orders_are_not_tracked() {
+        let mut book: OrderBook<u64, u64, u64, (), QueueTracker<u64, u64>> = OrderBook::new();
+        book.insert_hidden(1, Side::Bid, 100, 10, ()).unwrap();
+        book.insert_hidden(2, Side::Bid, 100, 20, ()).unwrap();
+        book.insert(3, Side::Bid, 100, 30, ()).unwrap();
+        assert_eq!(book.track(2), None);
+        assert_eq!(QueueTracker::new().track(&book, 2), None);
+        assert_eq!(book.track(3), Some(&QueuePosition::default()));
+
+        book.remove(&1);
+        assert_eq!(book.observer().positions().count(), 1);
+        assert_eq!(
+            book.observer().position(&3),
+            book.queue_position(&3).as_ref()
+        );
+    }
+}
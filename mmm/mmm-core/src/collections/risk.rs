@@ -0,0 +1,353 @@
+use super::account::Account;
+use super::book::{Error, OrderPrice};
+use super::Side;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, SubAssign};
+use thiserror::Error;
+
+/// Rejection of an order by the pre-trade checks, or by the account itself.
+/// L: limit price
+/// Q: volume
+/// V: value, of notional and PnL
+#[derive(Error, PartialEq, Eq, Debug, Clone)]
+pub enum Rejection<L: Debug, Q: Debug, V: Debug> {
+    #[error("daily PnL {pnl:?} breaches the loss limit {limit:?}.")]
+    DailyLoss { pnl: V, limit: V },
+    #[error("order size {quantity:?} exceeds {limit:?}.")]
+    OrderSize { quantity: Q, limit: Q },
+    #[error("{count} open orders reach the limit {limit}.")]
+    OpenOrders { count: usize, limit: usize },
+    #[error("short sale at {price:?} is not above the best bid {best_bid:?} under the short sale restriction.")]
+    ShortSale {
+        price: OrderPrice<L>,
+        best_bid: Option<L>,
+    },
+    #[error("price {price:?} is outside the LULD band [{lower:?}, {upper:?}].")]
+    PriceBand { price: L, lower: L, upper: L },
+    #[error(
+        "price {price:?} is more than {collar:?} away from the reference price {reference:?}."
+    )]
+    PriceCollar { price: L, reference: L, collar: L },
+    #[error("no reference price to value a market order.")]
+    NoReferencePrice,
+    #[error("notional {notional:?} exceeds {limit:?}.")]
+    Notional { notional: V, limit: V },
+    #[error(transparent)]
+    Account(#[from] Error),
+}
+
+/// RiskLimits are the configurable limits, a limit left `None` is not checked.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct RiskLimits<L, Q, V> {
+    pub max_order_size: Option<Q>,
+    pub max_notional: Option<V>,
+    pub max_open_orders: Option<usize>,
+    /// A limit price must be within this distance of the reference price.
+    pub price_collar: Option<L>,
+    /// Largest loss of the day, as a positive value.
+    pub max_daily_loss: Option<V>,
+}
+
+impl<L, Q, V> Default for RiskLimits<L, Q, V> {
+    fn default() -> Self {
+        Self {
+            max_order_size: None,
+            max_notional: None,
+            max_open_orders: None,
+            price_collar: None,
+            max_daily_loss: None,
+        }
+    }
+}
+
+/// MarketState is what the checks know of the market, kept up to date by the caller from the feed.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone)]
+pub struct MarketState<L> {
+    pub last_price: Option<L>,
+    pub best_bid: Option<L>,
+    pub best_ask: Option<L>,
+    /// LULD price band as (lower, upper).
+    pub price_band: Option<(L, L)>,
+    /// Whether the Reg SHO short sale price test restriction (Rule 201) is in effect,
+    /// e.g. after an ITCH RegShoRestriction with an action other than None,
+    /// or a TAQ SecurityStatus with ssr_state ShortSaleRestrictionInEffect.
+    pub short_sale_restricted: bool,
+}
+
+impl<L> Default for MarketState<L> {
+    fn default() -> Self {
+        Self {
+            last_price: None,
+            best_bid: None,
+            best_ask: None,
+            price_band: None,
+            short_sale_restricted: false,
+        }
+    }
+}
+
+impl<L: Clone> MarketState<L> {
+    /// Last trade price, or the opposite side of the NBBO which the order would trade against.
+    pub fn reference_price(&self, side: Side) -> Option<L> {
+        let opposite = match side {
+            Side::Ask => &self.best_bid,
+            Side::Bid => &self.best_ask,
+        };
+        self.last_price.clone().or_else(|| opposite.clone())
+    }
+}
+
+/// RiskCheck sits between a strategy and its Account, rejecting orders that break its limits.
+/// It has generics: L, Q, V
+/// L: limit price
+/// Q: volume
+/// V: value, notional is price times quantity in V
+#[derive(Clone, Debug, Default)]
+pub struct RiskCheck<L, Q, V> {
+    limits: RiskLimits<L, Q, V>,
+    market: MarketState<L>,
+    /// position of the account, positive when long.
+    position: V,
+    /// PnL of the day so far, e.g. from a Portfolio marked to market.
+    daily_pnl: V,
+}
+
+impl<L, Q, V> RiskCheck<L, Q, V>
+where
+    L: Ord + Debug + Clone + Add<Output = L> + Into<V>,
+    Q: Ord + Debug + Clone + Into<V>,
+    V: Ord + Debug + Clone + Default + Add<Output = V> + Mul<Output = V>,
+{
+    pub fn new(limits: RiskLimits<L, Q, V>) -> Self {
+        Self {
+            limits,
+            market: MarketState::default(),
+            position: V::default(),
+            daily_pnl: V::default(),
+        }
+    }
+
+    pub fn limits(&self) -> &RiskLimits<L, Q, V> {
+        &self.limits
+    }
+
+    pub fn limits_mut(&mut self) -> &mut RiskLimits<L, Q, V> {
+        &mut self.limits
+    }
+
+    pub fn market(&self) -> &MarketState<L> {
+        &self.market
+    }
+
+    pub fn market_mut(&mut self) -> &mut MarketState<L> {
+        &mut self.market
+    }
+
+    pub fn set_position(&mut self, position: V) {
+        self.position = position;
+    }
+
+    pub fn set_daily_pnl(&mut self, daily_pnl: V) {
+        self.daily_pnl = daily_pnl;
+    }
+
+    /// Checks a new order of the account.
+    /// A sell of more than the position held, less the sells resting in the account, is a short sale.
+    pub fn check<K, I, T>(
+        &self,
+        account: &Account<K, L, Q, I, T>,
+        side: Side,
+        price: &OrderPrice<L>,
+        quantity: &Q,
+    ) -> Result<(), Rejection<L, Q, V>>
+    where
+        K: Eq + Hash + Clone + Debug,
+        Q: AddAssign + SubAssign + Default + Sum,
+        I: Debug,
+    {
+        let limits = &self.limits;
+        let market = &self.market;
+        if let Some(limit) = &limits.max_daily_loss {
+            if self.daily_pnl.clone() + limit.clone() <= V::default() {
+                return Err(Rejection::DailyLoss {
+                    pnl: self.daily_pnl.clone(),
+                    limit: limit.clone(),
+                });
+            }
+        }
+        if let Some(limit) = &limits.max_order_size {
+            if quantity > limit {
+                return Err(Rejection::OrderSize {
+                    quantity: quantity.clone(),
+                    limit: limit.clone(),
+                });
+            }
+        }
+        if let Some(limit) = limits.max_open_orders {
+            let count = account.pending().len() + account.stops().len();
+            if count >= limit {
+                return Err(Rejection::OpenOrders { count, limit });
+            }
+        }
+        let short = side == Side::Ask
+            && account.pending().total_ask_volume().into() + quantity.clone().into()
+                > self.position;
+        if short && market.short_sale_restricted {
+            let above_bid = match (price, &market.best_bid) {
+                (OrderPrice::Market, _) => false,
+                (OrderPrice::Limit(_), None) => true,
+                (OrderPrice::Limit(p), Some(b)) => p > b,
+            };
+            if !above_bid {
+                return Err(Rejection::ShortSale {
+                    price: price.clone(),
+                    best_bid: market.best_bid.clone(),
+                });
+            }
+        }
+        let reference = market.reference_price(side);
+        if let OrderPrice::Limit(p) = price {
+            if let Some((lower, upper)) = &market.price_band {
+                if p < lower || p > upper {
+                    return Err(Rejection::PriceBand {
+                        price: p.clone(),
+                        lower: lower.clone(),
+                        upper: upper.clone(),
+                    });
+                }
+            }
+            if let (Some(collar), Some(reference)) = (&limits.price_collar, &reference) {
+                // compared without subtraction, so that unsigned prices do not underflow.
+                if p.clone() > reference.clone() + collar.clone()
+                    || p.clone() + collar.clone() < *reference
+                {
+                    return Err(Rejection::PriceCollar {
+                        price: p.clone(),
+                        reference: reference.clone(),
+                        collar: collar.clone(),
+                    });
+                }
+            }
+        }
+        if let Some(limit) = &limits.max_notional {
+            let price = match price {
+                OrderPrice::Limit(p) => p.clone(),
+                OrderPrice::Market => reference.ok_or(Rejection::NoReferencePrice)?,
+            };
+            let notional = price.into() * quantity.clone().into();
+            if notional > *limit {
+                return Err(Rejection::Notional {
+                    notional,
+                    limit: limit.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Places the order in `account` only if it passes `check`.
+    pub fn order<K, I, T>(
+        &self,
+        account: &mut Account<K, L, Q, I, T>,
+        id: K,
+        side: Side,
+        price: OrderPrice<L>,
+        quantity: Q,
+        info: I,
+    ) -> Result<(), Rejection<L, Q, V>>
+    where
+        K: Eq + Hash + Debug + Clone,
+        Q: AddAssign + SubAssign + Default + Sum,
+        I: Clone + Debug,
+        T: Clone + Debug,
+    {
+        self.check(account, side, &price, &quantity)?;
+        Ok(account.order(id, side, price, quantity, info)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_breaking_limits_are_rejected() {
+        let limits = RiskLimits {
+            max_order_size: Some(100),
+            max_notional: Some(5000),
+            max_open_orders: Some(2),
+            price_collar: Some(5),
+            max_daily_loss: Some(1000),
+        };
+        let mut risk: RiskCheck<u64, u64, i128> = RiskCheck::new(limits);
+        let mut account: Account<u64, u64, u64> = Account::new();
+        let limit = OrderPrice::Limit;
+        assert_eq!(
+            risk.order(&mut account, 1, Side::Bid, OrderPrice::Market, 10, ()),
+            Err(Rejection::NoReferencePrice)
+        );
+        let market = risk.market_mut();
+        market.best_bid = Some(49);
+        market.best_ask = Some(51);
+        market.price_band = Some((45, 60));
+
+        assert_eq!(
+            risk.check(&account, Side::Bid, &limit(50), &101),
+            Err(Rejection::OrderSize {
+                quantity: 101,
+                limit: 100
+            })
+        );
+        assert_eq!(
+            risk.check(&account, Side::Bid, &limit(57), &1),
+            Err(Rejection::PriceCollar {
+                price: 57,
+                reference: 51,
+                collar: 5
+            })
+        );
+        assert!(matches!(
+            risk.check(&account, Side::Ask, &limit(44), &1),
+            Err(Rejection::PriceBand { .. })
+        ));
+        assert!(matches!(
+            risk.check(&account, Side::Bid, &OrderPrice::Market, &99),
+            Err(Rejection::Notional { notional: 5049, .. })
+        ));
+
+        risk.market_mut().short_sale_restricted = true;
+        risk.set_position(10);
+        assert!(matches!(
+            risk.check(&account, Side::Ask, &limit(49), &20),
+            Err(Rejection::ShortSale { .. })
+        ));
+        // selling what is held is not a short sale.
+        risk.order(&mut account, 1, Side::Ask, limit(49), 10, ())
+            .unwrap();
+        // but selling it twice is.
+        assert!(matches!(
+            risk.check(&account, Side::Ask, &limit(49), &1),
+            Err(Rejection::ShortSale { .. })
+        ));
+        risk.order(&mut account, 2, Side::Ask, limit(50), 20, ())
+            .unwrap();
+        assert_eq!(
+            risk.order(&mut account, 3, Side::Bid, limit(50), 1, ()),
+            Err(Rejection::OpenOrders { count: 2, limit: 2 })
+        );
+        account.cancel(4, 2, None).unwrap();
+        assert_eq!(
+            risk.order(&mut account, 1, Side::Bid, limit(50), 1, ()),
+            Err(Rejection::Account(Error::KeyAlreadyExists))
+        );
+
+        risk.set_daily_pnl(-1000);
+        assert!(matches!(
+            risk.check(&account, Side::Bid, &limit(50), &1),
+            Err(Rejection::DailyLoss { .. })
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod krx;
+
 use std::ops::{AddAssign, Rem, SubAssign};
 
 pub enum Direction {
@@ -0,0 +1,150 @@
+use super::{Direction, PriceTick};
+use serde::{Deserialize, Serialize};
+use std::ops::{AddAssign, Rem, SubAssign};
+
+/// Tick tables of the Korea Exchange, as (lower bound of a price range, tick) from the top.
+const KOSPI: [(i64, i64); 7] = [
+    (500_000, 1000),
+    (100_000, 500),
+    (50_000, 100),
+    (10_000, 50),
+    (5_000, 10),
+    (1_000, 5),
+    (0, 1),
+];
+const KOSDAQ: [(i64, i64); 5] = [(50_000, 100), (10_000, 50), (5_000, 10), (1_000, 5), (0, 1)];
+/// KOSPI and KOSDAQ share this table from 2023-01-25.
+const UNIFIED: [(i64, i64); 7] = [
+    (500_000, 1000),
+    (200_000, 500),
+    (50_000, 100),
+    (20_000, 50),
+    (5_000, 10),
+    (2_000, 5),
+    (0, 1),
+];
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub enum KrxTable {
+    Kospi,
+    Kosdaq,
+    #[default]
+    Unified,
+}
+
+impl KrxTable {
+    /// The table of a market, `unified` for trading days from 2023-01-25.
+    pub fn new(is_kosdaq: bool, unified: bool) -> Self {
+        match (unified, is_kosdaq) {
+            (true, _) => KrxTable::Unified,
+            (false, false) => KrxTable::Kospi,
+            (false, true) => KrxTable::Kosdaq,
+        }
+    }
+
+    /// Tick of the price range `price` falls in.
+    pub fn tick(&self, price: i64) -> i64 {
+        let table: &[(i64, i64)] = match self {
+            KrxTable::Kospi => &KOSPI,
+            KrxTable::Kosdaq => &KOSDAQ,
+            KrxTable::Unified => &UNIFIED,
+        };
+        (table.iter())
+            .find(|(lower, _)| price >= *lower)
+            .map_or(1, |(_, tick)| *tick)
+    }
+
+    /// The highest valid price at or below `price`.
+    pub fn floor(&self, price: i64) -> i64 {
+        price - price.rem_euclid(self.tick(price))
+    }
+
+    /// The lowest valid price above `price`.
+    pub fn upper(&self, price: i64) -> i64 {
+        let floor = self.floor(price);
+        floor + self.tick(floor)
+    }
+
+    /// The highest valid price below `price`.
+    pub fn lower(&self, price: i64) -> i64 {
+        self.floor(price - 1)
+    }
+
+    pub fn is_valid(&self, price: i64) -> bool {
+        self.floor(price) == price
+    }
+}
+
+/// KrxPrice is a price in won, ticked by its table.
+/// Prices are equal by value, whatever their table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct KrxPrice {
+    pub price: i64,
+    pub table: KrxTable,
+}
+
+impl KrxPrice {
+    pub fn new(price: i64, table: KrxTable) -> Self {
+        Self { price, table }
+    }
+}
+
+impl PartialEq for KrxPrice {
+    fn eq(&self, other: &Self) -> bool {
+        self.price == other.price
+    }
+}
+
+impl Eq for KrxPrice {}
+
+impl AddAssign for KrxPrice {
+    fn add_assign(&mut self, rhs: Self) {
+        self.price += rhs.price;
+    }
+}
+
+impl SubAssign for KrxPrice {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.price -= rhs.price;
+    }
+}
+
+impl Rem for KrxPrice {
+    type Output = Self;
+    fn rem(mut self, rhs: Self) -> Self {
+        self.price %= rhs.price;
+        self
+    }
+}
+
+impl PriceTick for KrxPrice {
+    fn tickify(&self, direction: Direction) -> Self {
+        let price = match direction {
+            Direction::Up | Direction::None => self.price,
+            Direction::Down => self.price - 1,
+        };
+        KrxPrice::new(self.table.tick(price), self.table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kospi_kosdaq_and_unified_ticks() {
+        let kospi = KrxTable::new(false, false);
+        let kosdaq = KrxTable::new(true, false);
+        let unified = KrxTable::new(true, true);
+        assert_eq!((kospi.tick(150_000), kosdaq.tick(150_000)), (500, 100));
+        assert_eq!(unified.tick(150_000), 100);
+        assert_eq!((unified.upper(1_999), unified.lower(2_000)), (2_000, 1_999));
+        assert_eq!((unified.upper(2_003), unified.lower(2_003)), (2_005, 2_000));
+        assert!(kospi.is_valid(4_995) && !kospi.is_valid(4_999));
+
+        let price = KrxPrice::new(10_000, KrxTable::Kospi);
+        assert!(price.valid_price());
+        assert_eq!(price.ceiling_price().price, 10_050);
+        assert_eq!(price.floor_price().price, 9_990);
+    }
+}
@@ -10,6 +10,14 @@ impl<const R: u64> RadixCode<R> {
     pub fn new(str_code: &str) -> Option<Self> {
         Some(Self(code_to_num::<R>(str_code)?))
     }
+
+    pub const fn from_value(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub const fn value(&self) -> u64 {
+        self.0
+    }
 }
 
 fn code_to_num<const R: u64>(str_code: &str) -> Option<u64> {
@@ -38,3 +46,17 @@ impl<const R: u64> std::fmt::Display for RadixCode<R> {
         f.write_str(&str_code)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpha_numeric_round_trip() {
+        let code = AlphaNumeric::new("AAPL").unwrap();
+        assert_eq!(code.to_string(), "AAPL");
+        assert_eq!(AlphaNumeric::from_value(code.value()), code);
+        assert_eq!(AlphaNumeric::new("BRK.B"), None);
+        assert_eq!(AlphaNumeric::new("ZZZZZZZZZZZZZ"), None);
+    }
+}
@@ -0,0 +1,58 @@
+//! Replays a preprocessed ITCH day through `NasdaqOrderBook`.
+//! Set MMM_NASDAQ_BENCH to `{symbol}.bin.zst` files written by `preprocess`, separated by ','.
+//! Run it on two trees with the same files to compare order book implementations.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use mmm_nasdaq::{
+    book::{Message, NasdaqOrderBook},
+    data::{file_date, load, NUM_FIELDS},
+};
+use mmm_us::util::Time;
+use std::path::Path;
+
+const BENCH_ENV: &str = "MMM_NASDAQ_BENCH";
+
+fn replay(c: &mut Criterion) {
+    let paths = match std::env::var(BENCH_ENV) {
+        Ok(paths) => paths,
+        Err(_) => {
+            eprintln!("{} is not set, nothing to replay.", BENCH_ENV);
+            return;
+        }
+    };
+    let mut group = c.benchmark_group("replay");
+    group.sample_size(10);
+    for path in paths.split(',') {
+        let date = match file_date(Path::new(path)) {
+            Some(date) => date,
+            None => {
+                eprintln!("no trading day in the file name {}, skipped.", path);
+                continue;
+            }
+        };
+        let midnight = Time::from_local_nanos(date, 0);
+        let messages: Vec<Message> = load(path, NUM_FIELDS)
+            .iter()
+            .map(|msg| Message::decode_since(msg, midnight))
+            .collect();
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || NasdaqOrderBook::new(false),
+                |mut book| {
+                    for msg in &messages {
+                        let _ = book.handle(msg);
+                    }
+                    black_box(book)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, replay);
+criterion_main!(benches);
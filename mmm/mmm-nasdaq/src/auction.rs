@@ -0,0 +1,44 @@
+use itchy::{CrossType, ImbalanceDirection, ImbalanceIndicator};
+use mmm_us::auction::{AuctionType, ImbalanceSnapshot};
+use mmm_us::price::PriceBasis;
+use mmm_us::Side;
+
+/// Auction of the NOII and cross trade messages of `cross_type`.
+pub fn auction_type(cross_type: CrossType) -> AuctionType {
+    match cross_type {
+        CrossType::Opening => AuctionType::Opening,
+        CrossType::Closing => AuctionType::Closing,
+        CrossType::IpoOrHalted => AuctionType::Reopening,
+        CrossType::Intraday | CrossType::ExtendedTradingClose => AuctionType::Other,
+    }
+}
+
+/// Snapshot of a NOII message, with the (upper, lower) collar of the last LULD Auction Collar message.
+/// NOII publishes no indicative price other than the near price.
+pub fn imbalance_snapshot(
+    time_ns: u64,
+    imbalance: &ImbalanceIndicator,
+    collar: Option<(u64, u64)>,
+) -> ImbalanceSnapshot {
+    ImbalanceSnapshot {
+        time_ns,
+        paired_shares: imbalance.paired_shares,
+        imbalance_shares: imbalance.imbalance_shares,
+        imbalance_side: match imbalance.imbalance_direction {
+            ImbalanceDirection::Buy => Some(Side::Bid),
+            ImbalanceDirection::Sell => Some(Side::Ask),
+            ImbalanceDirection::NoImbalance | ImbalanceDirection::InsufficientOrders => None,
+        },
+        reference_price: price(&imbalance.current_ref_price),
+        near_price: price(&imbalance.near_price),
+        far_price: price(&imbalance.far_price),
+        indicative_price: None,
+        upper_collar: collar.map(|(upper, _)| upper),
+        lower_collar: collar.map(|(_, lower)| lower),
+    }
+}
+
+// 0 when not applicable, e.g. the far and near price before the auction collects interest
+fn price(price: &PriceBasis) -> Option<u64> {
+    Some(price.inner()).filter(|price| *price > 0)
+}
@@ -1,9 +1,16 @@
 use std::path::PathBuf;
 
+use anyhow::anyhow;
+use chrono::NaiveDate;
 use mmm_nasdaq::{
+    constants::SESSION_SCHEDULE,
     book::{NasdaqOrderBook,Message},
-    data::{load, process_file,  NUM_FIELDS},
+    data::{file_date, load, process_file_with_schedule, securities_path, symbols_path,  NUM_FIELDS},
 };
+use mmm_us::calendar::TradingCalendar;
+use mmm_us::util::Time;
+use mmm_us::security::{load_securities, SecurityMaster, SECURITY_MASTER_FILE};
+use mmm_us::symbol::{load_symbols, SymbolRegistry, SYMBOL_REGISTRY_FILE};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use structopt::StructOpt;
 
@@ -16,6 +23,11 @@ enum Opt {
         out_dir: PathBuf,
         #[structopt(long)]
         no_cache: bool,
+        /// trading day of the files, to find its holiday or early close
+        #[structopt(long)]
+        date: Option<NaiveDate>,
+        #[structopt(long, default_value = "1000000000")]
+        interval_ns: u64,
     },
     Recon {
         #[structopt(name = "FILE", parse(from_os_str))]
@@ -25,15 +37,18 @@ enum Opt {
     },
 }
 
-pub fn reconstruct(path: PathBuf, validation: bool) {
-    let msgs = load(&path, NUM_FIELDS).into_iter().map(|bytes| Message::from(&*bytes));
+pub fn reconstruct(path: PathBuf, validation: bool) -> anyhow::Result<()> {
+    let date = file_date(&path).ok_or_else(|| anyhow!("no trading day in {:?}", path))?;
+    let midnight = Time::from_local_nanos(date, 0);
+    let msgs = load(&path, NUM_FIELDS).into_iter().map(|bytes| Message::decode_since(&bytes, midnight));
     let mut engine = NasdaqOrderBook::new(validation);
 
     for msg in msgs {
         if engine.handle(&msg).is_err() {
-            return;
+            return Ok(());
         };
     }
+    Ok(())
 }
 
 fn main() -> anyhow::Result<()> {
@@ -43,16 +58,56 @@ fn main() -> anyhow::Result<()> {
             files,
             out_dir,
             no_cache,
+            date,
+            interval_ns,
         } => {
+            let venue = SESSION_SCHEDULE.with_interval(interval_ns);
+            let schedule = match date {
+                Some(date) => TradingCalendar::us_equity()
+                    .schedule(date, venue)
+                    .ok_or_else(|| anyhow!("{} is not a trading day", date))?,
+                None => venue,
+            };
             if no_cache {
                 let _ = std::fs::remove_dir_all(&out_dir);
             }
             let _ = std::fs::create_dir_all(&out_dir);
 
             files
+                .clone()
                 .into_par_iter()
-                .map(|path| process_file(path, out_dir.clone(), false))
-                .collect::<Vec<_>>();
+                .map(|path| process_file_with_schedule(path, out_dir.clone(), false, schedule))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            // the security master and symbol registry of the output directory keep every preprocessed day
+            if let Some(date) = date {
+                let master_path = out_dir.join(SECURITY_MASTER_FILE);
+                let mut master = if master_path.exists() {
+                    SecurityMaster::load(&master_path)?
+                } else {
+                    SecurityMaster::new()
+                };
+                let registry_path = out_dir.join(SYMBOL_REGISTRY_FILE);
+                let mut registry = if registry_path.exists() {
+                    SymbolRegistry::load(&registry_path)?
+                } else {
+                    SymbolRegistry::new()
+                };
+                for path in &files {
+                    let securities = securities_path(path, &out_dir);
+                    if securities.exists() {
+                        master.extend(date, load_securities(securities)?);
+                    }
+                    let symbols = symbols_path(path, &out_dir);
+                    if symbols.exists() {
+                        for (venue, ticker) in load_symbols(symbols)? {
+                            registry.map(date, venue, &ticker);
+                        }
+                    }
+                }
+                master.save(&master_path)?;
+                registry.save(&registry_path)?;
+            }
         }
         Opt::Recon {
             files,
@@ -61,7 +116,7 @@ fn main() -> anyhow::Result<()> {
             files
                 .into_par_iter()
                 .map(|file| reconstruct(file, !without_validation))
-                .collect::<Vec<_>>();
+                .collect::<anyhow::Result<Vec<_>>>()?;
         }
     }
     Ok(())
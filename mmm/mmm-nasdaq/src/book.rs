@@ -4,12 +4,12 @@ use anyhow::bail;
 use itchy::CrossType;
 
 // use crate::data::{Body, Message};
-use mmm_us::{OrderBook, PriceTimePriority, Side};
+use mmm_us::{util::Time, BookChanges, OrderBook, PriceTimePriority, Side};
 pub type IdTimeQuantity = (u64, u64, u64);
 
 #[derive(Clone, Debug)]
 pub struct Message {
-    pub time: u64,
+    pub time: Time,
     pub body: Body,
 }
 
@@ -46,15 +46,20 @@ pub enum Body {
     CrossTrade {
         cross_type: CrossType,
     },
-    NonCrossTrade {},
+    NonCrossTrade {
+        shares: u64,
+        price: u64,
+    },
 }
 
 
 #[derive(Debug)]
 pub struct NasdaqOrderBook {
     max_ref: u64,
-    book: OrderBook<u64, u64, u64, u64>,
+    book: OrderBook<u64, u64, u64, u64, BookChanges<u64>>,
     with_validation: bool,
+    // midnight in New York of the first message, the time of an order counts from it
+    midnight: Option<Time>,
 }
 
 impl NasdaqOrderBook {
@@ -63,6 +68,7 @@ impl NasdaqOrderBook {
             max_ref: 0,
             book: OrderBook::new(),
             with_validation,
+            midnight: None,
         }
     }
     pub fn handle(&mut self, msg: &Message) -> anyhow::Result<()> {
@@ -81,6 +87,9 @@ impl NasdaqOrderBook {
                 bt.price
             );
         }
+        let midnight = *(self.midnight)
+            .get_or_insert_with(|| Time::from_local_nanos(msg.time.local_date(), 0));
+        let time = msg.time.epoch_nanos() - midnight.epoch_nanos();
         match msg.body {
             Body::AddOrder {
                 reference,
@@ -94,12 +103,12 @@ impl NasdaqOrderBook {
                     match side {
                         Side::Ask => {
                             self.book
-                                .insert(reference, side, price, shares, msg.time)
+                                .insert(reference, side, price, shares, time)
                                 .unwrap();
                         }
                         Side::Bid => {
                             self.book
-                                .insert(reference, side, price, shares, msg.time)
+                                .insert(reference, side, price, shares, time)
                                 .unwrap();
                         }
                     }
@@ -107,12 +116,12 @@ impl NasdaqOrderBook {
                     match side {
                         Side::Ask => {
                             self.book
-                                .sorted_insert_ask_by_key(reference, price, shares, msg.time)
+                                .sorted_insert_ask_by_key(reference, price, shares, time)
                                 .unwrap();
                         }
                         Side::Bid => {
                             self.book
-                                .sorted_insert_bid_by_key(reference, price, shares, msg.time)
+                                .sorted_insert_bid_by_key(reference, price, shares, time)
                                 .unwrap();
                         }
                     }
@@ -137,11 +146,11 @@ impl NasdaqOrderBook {
                 match &s {
                     Side::Ask => self
                         .book
-                        .insert(new_reference, s ,price, shares, msg.time)
+                        .insert(new_reference, s ,price, shares, time)
                         .unwrap(),
                     Side::Bid => self
                         .book
-                        .insert(new_reference, s ,price, shares, msg.time)
+                        .insert(new_reference, s ,price, shares, time)
                         .unwrap(),
                 };
             }
@@ -201,7 +210,8 @@ impl NasdaqOrderBook {
                 cross_type: CrossType::Closing,
                 ..
             }
-            | Body::NonCrossTrade {}
+            // non-displayed executions leave no order in the book, MarketStat counts their volume
+            | Body::NonCrossTrade { .. }
             // | Body::TradingAction {
             //     trading_state: TradingState::Trading,
             //     ..
@@ -210,6 +220,11 @@ impl NasdaqOrderBook {
         };
         Ok(())
     }
+    /// Levels changed since the last call.
+    pub fn take_changes(&mut self) -> BookChanges<u64> {
+        self.book.observer_mut().take()
+    }
+
     pub fn level_summary(&mut self, level: usize) -> HashMap<String, HashMap<u64, u64>> {
         let mut summary = HashMap::new();
 
@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use itchy::{
     AddOrder, CrossTrade, CrossType, MarketParticipantPosition, NonCrossTrade, ReplaceOrder,
     StockDirectory, TradingState, ImbalanceIndicator, ImbalanceDirection
@@ -5,18 +6,31 @@ use itchy::{
 use itertools::Itertools;
 // use mmm_core::collections::Side;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use crate::book::{Body,Message,NasdaqOrderBook};
+use crate::constants::SESSION_SCHEDULE;
 use crate::create_folder;
+use crate::security::security_meta;
 use crate::stat::StatBuilder;
 
 pub const LEVEL: usize = 5;
-use mmm_us::{decode_side, encode_side, price::PriceBasis, Side};
+use chrono::NaiveDate;
+use mmm_us::auction::AUCTION_FILE_SUFFIX;
+use mmm_us::security::{dump_securities, SECURITIES_FILE};
+use mmm_us::symbol::{dump_symbols, VenueId, SYMBOLS_FILE};
+use mmm_us::{calendar::SessionSchedule, decode_side, encode_side, price::PriceBasis, util::Time};
 pub const NUM_FIELDS: usize = 9;
+/// Columns of `{symbol}_noii.bin.zst`, a NOII message as ITCH has it: paired shares,
+/// imbalance shares, imbalance direction, far, near and current reference price,
+/// cross type and price variation indicator.
+pub const NOII_FIELDS: usize = 8;
+/// Columns of `{symbol}_noii_time.bin.zst`: time, stock locate and the `NOII_FIELDS`.
+/// The stock locate is the venue id of the symbol in `symbols.json.zst`.
+pub const TIMED_NOII_FIELDS: usize = NOII_FIELDS + 2;
 
 fn encode_printable(printable: bool) -> u64 {
     match printable {
@@ -83,8 +97,15 @@ fn encode_price_variation_indicator(ind: char) -> u64 {
 }
 
 
-impl From<&[u64]> for Message {
-    fn from(values: &[u64]) -> Self {
+impl Message {
+    /// Decodes a row of the preprocessed file of `date`, whose time is nanoseconds since midnight in New York.
+    pub fn decode(values: &[u64], date: NaiveDate) -> Self {
+        Self::decode_since(values, Time::from_local_nanos(date, 0))
+    }
+
+    /// Decodes a row whose time is nanoseconds since `midnight`,
+    /// e.g. the UTC midnight of rows converted from Coinbase.
+    pub fn decode_since(values: &[u64], midnight: Time) -> Self {
         let body = match values[0] {
             0 => Body::AddOrder {
                 reference: values[2],
@@ -117,11 +138,14 @@ impl From<&[u64]> for Message {
             6 => Body::CrossTrade {
                 cross_type: decode_cross_type(values[7]),
             },
-            7 => Body::NonCrossTrade {},
+            7 => Body::NonCrossTrade {
+                shares: values[3],
+                price: values[4],
+            },
             _ => panic!("unknown value found for 'MessageType'"),
         };
         Self {
-            time: values[1],
+            time: Time::from_epoch_nanos(midnight.epoch_nanos() + values[1]),
             body,
         }
     }
@@ -131,7 +155,8 @@ impl From<&[u64]> for Message {
 pub(crate) struct StockContainer {
     pub(crate) name: Option<String>,
     pub(crate) messages: Vec<[u64; NUM_FIELDS]>,
-    pub(crate) noii_messages: Vec<[u64; 8]>,
+    /// NOII messages in the `TIMED_NOII_FIELDS` layout
+    pub(crate) noii_messages: Vec<[u64; TIMED_NOII_FIELDS]>,
     //pub(crate) bbos: Vec::<[i64; 2]>, 
     pub(crate) book: NasdaqOrderBook,
 }
@@ -175,7 +200,20 @@ impl OrderStatus {
     }
 }
 
-pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
+/// Preprocesses the ITCH file at `path`, whose name must carry its trading day, see `file_date`.
+pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) -> anyhow::Result<()> {
+    process_file_with_schedule(path, out_dir, meta_only, SESSION_SCHEDULE)
+}
+
+/// Same as `process_file`, but the sessions of the day are given, e.g. from a TradingCalendar.
+pub fn process_file_with_schedule(
+    path: PathBuf,
+    out_dir: PathBuf,
+    meta_only: bool,
+    schedule: SessionSchedule,
+) -> anyhow::Result<()> {
+    let date = file_date(&path).ok_or_else(|| anyhow!("no trading day in {:?}", path))?;
+    let midnight = Time::from_local_nanos(date, 0);
     let out_dir = create_folder(&path, &out_dir);
     let done_file = out_dir.join(".done");
     if !meta_only && done_file.exists() {
@@ -183,12 +221,13 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
             "skip `process_file` for {:?}. done file already exists.",
             path
         );
-        return;
+        return Ok(());
     }
 
     let mut containers: HashMap<usize, (StatBuilder, StockContainer)> = HashMap::new();
     let mut status_map = HashMap::new();
     let mut global_mpid_map: HashMap<String, u64> = HashMap::new();
+    let mut symbols = BTreeMap::new();
     let mut count = 0;
     for message in itchy::MessageStream::from_gzip(path).unwrap() {
         let message = message.unwrap();
@@ -205,12 +244,12 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
             }
             (None, &mut containers
                 .entry(stock_locate)
-                .or_insert_with(|| (StatBuilder::new(), StockContainer::new()))
+                .or_insert_with(|| (StatBuilder::new(schedule), StockContainer::new()))
                 .1)
         } else {
             let (stat_builder, stock_container) = containers
                 .entry(stock_locate)
-                .or_insert_with(|| (StatBuilder::new(), StockContainer::new()));
+                .or_insert_with(|| (StatBuilder::new(schedule), StockContainer::new()));
             stat_builder.update(&message, stock_container, &status_map);
             (Some(stat_builder), stock_container)
         };
@@ -415,6 +454,7 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
                 0,
             ]),
             itchy::Body::StockDirectory(StockDirectory { stock, .. }) => {
+                symbols.insert(VenueId::Nasdaq(message.stock_locate), stock.trim_end().to_string());
                 *name = Some(stock.to_string());
                 None
             }
@@ -438,6 +478,8 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
             }) => {
 
                 noii_messages.push([
+                    message.timestamp,
+                    message.stock_locate as u64,
                     paired_shares,
                     imbalance_shares,
                     encode_imbalance_direction(imbalance_direction),
@@ -463,7 +505,7 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
         };
 
         if let Some(encoded) = encoded {
-            book.handle(&Message::from(encoded.as_slice())).unwrap();
+            book.handle(&Message::decode_since(encoded.as_slice(), midnight)).unwrap();
             //let (bo, bb) = book.bbo();
             //match (bo, bb) {
             //    (None, None) => bbos.push([-1, 0]),
@@ -472,7 +514,9 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
             //    (Some(a), Some(b)) => bbos.push([a as i64, b as i64])
             //}
             stock_messages.push(encoded);
-            stat_builder.unwrap().update_lob((stock_container.name.as_ref().unwrap().to_string()), book, message.timestamp, LEVEL);
+            let stat_builder = stat_builder.unwrap();
+            stat_builder.update_mpid(&encoded, book);
+            stat_builder.update_lob(stock_container.name.as_ref().unwrap().to_string(), book, message.timestamp, LEVEL);
         }
 
     }
@@ -492,6 +536,14 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
                 .map(|name| name.stock.clone().trim_end().to_string());
             let out_path = out_dir.join(format!("{}.json.zst", name.as_ref().unwrap().trim_end()));
             dump(out_path, &serde_json::to_vec(&market_stat).unwrap());
+            if !market_stat.mpid_stats.is_empty() {
+                let out_path = out_dir.join(format!("{}_mpid.json.zst", name.as_ref().unwrap()));
+                dump(out_path, &serde_json::to_vec(&market_stat.mpid_stats).unwrap());
+            }
+            if !market_stat.auctions.is_empty() {
+                let out_path = out_dir.join(format!("{}{}", name.as_ref().unwrap(), AUCTION_FILE_SUFFIX));
+                dump(out_path, &serde_json::to_vec(&market_stat.auctions).unwrap());
+            }
             name.map(|name| (name, market_stat))
         })
         .collect::<HashMap<_, _>>();
@@ -502,6 +554,14 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
     //    &serde_json::to_vec(&market_stats).unwrap(),
     //);
 
+    let securities = market_stats
+        .values()
+        .filter_map(|stat| stat.stock_directory.as_ref().map(security_meta))
+        .sorted_by(|a, b| a.ticker.cmp(&b.ticker))
+        .collect::<Vec<_>>();
+    dump_securities(out_dir.join(SECURITIES_FILE), &securities).unwrap();
+    dump_symbols(out_dir.join(SYMBOLS_FILE), &symbols).unwrap();
+
     dump(
         out_dir.join("mpid_map.json.zst"),
         &serde_json::to_vec(
@@ -514,7 +574,7 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
     );
 
     if meta_only {
-        return;
+        return Ok(());
     }
 
     stock_containers
@@ -534,6 +594,10 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
                 "{}_noii.bin.zst",
                 container.name.as_ref().unwrap().trim_end()
             ));
+            let timed_noii_out_path = out_dir.join(format!(
+                "{}_noii_time.bin.zst",
+                container.name.as_ref().unwrap().trim_end()
+            ));
             //dump(
             //    bbo_out_path,
             //    &container
@@ -548,6 +612,15 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
                 &container
                 .noii_messages
                 .iter()
+                .flat_map(|x| &x[TIMED_NOII_FIELDS - NOII_FIELDS..])
+                .flat_map(|x| x.to_ne_bytes())
+                .collect::<Vec<u8>>(),
+            );
+            dump(
+                timed_noii_out_path,
+                &container
+                .noii_messages
+                .iter()
                 .flatten()
                 .flat_map(|x| x.to_ne_bytes())
                 .collect::<Vec<u8>>(),
@@ -564,6 +637,30 @@ pub fn process_file(path: PathBuf, out_dir: PathBuf, meta_only: bool) {
         })
         .collect::<Vec<_>>();
     File::create(done_file).unwrap();
+    Ok(())
+}
+
+/// Security master records written by `process_file` for `path`.
+pub fn securities_path(path: &Path, out_dir: &Path) -> PathBuf {
+    create_folder(path, out_dir).join(SECURITIES_FILE)
+}
+
+/// Trading day of an ITCH file, e.g. "S100421-v50.txt.gz" or "10042021.NASDAQ_ITCH50.gz",
+/// or of a file preprocessed from it, e.g. "S100421-v50/AAPL.bin.zst".
+pub fn file_date(path: &Path) -> Option<NaiveDate> {
+    path.iter().rev().find_map(|name| {
+        let name = name.to_str()?.split(['-', '.']).next()?;
+        match name.strip_prefix('S') {
+            Some(date) if date.len() == 6 => NaiveDate::parse_from_str(date, "%m%d%y").ok(),
+            None if name.len() == 8 => NaiveDate::parse_from_str(name, "%m%d%Y").ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Venue ids written by `process_file` for `path`.
+pub fn symbols_path(path: &Path, out_dir: &Path) -> PathBuf {
+    create_folder(path, out_dir).join(SYMBOLS_FILE)
 }
 
 pub(crate) fn dump(out_path: PathBuf, serialized: &[u8]) {
@@ -595,6 +692,15 @@ pub fn load<P: AsRef<Path>>(path: P, num_fields: usize) -> Vec<Vec<u64>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn trading_day_of_file_names() {
+        let date = NaiveDate::from_ymd_opt(2021, 10, 4);
+        assert_eq!(file_date(Path::new("./test/S100421-v50.txt.gz")), date);
+        assert_eq!(file_date(Path::new("10042021.NASDAQ_ITCH50.gz")), date);
+        assert_eq!(file_date(Path::new("./test/S100421-v50/AAPL.bin.zst")), date);
+        assert_eq!(file_date(Path::new("./test/Test_A_B_C_10")), None);
+    }
+
     #[test]
     fn run_preprocess() {
         std::env::set_var("RUST_BACKTRACE", "full");
@@ -603,7 +709,7 @@ mod tests {
         let now = Instant::now();
         let path = PathBuf::from("./test/S100421-v50.txt.gz");
         // process_file(nypath, PathBuf::from("../sample/done"), false);
-        process_file(path, PathBuf::from("./test/"), false);
+        process_file(path, PathBuf::from("./test/"), false).unwrap();
         let elapsed = now.elapsed();
         println!("Elapsed: {:.2?}", elapsed);
 
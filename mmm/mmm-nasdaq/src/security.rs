@@ -0,0 +1,59 @@
+use itchy::{IssueClassification, LuldRefPriceTier, MarketCategory, StockDirectory};
+use mmm_us::security::SecurityMeta;
+use mmm_us::util::{MarketType, SecurityType};
+
+/// Security master record of a StockDirectory message.
+/// ITCH does not publish the previous close, nor a price resolution other than Rule 612.
+pub fn security_meta(directory: &StockDirectory) -> SecurityMeta {
+    SecurityMeta {
+        ticker: directory.stock.trim_end().to_string(),
+        round_lot_size: directory.round_lot_size as u64,
+        classification: security_type(directory.issue_classification),
+        market_category: market_type(directory.market_category),
+        prev_closing_price: None,
+        issue_subtype: Some(format!("{:?}", directory.issue_subtype)),
+        etp: directory.etp_flag.unwrap_or(false),
+        etp_leverage_factor: directory.etp_leverage_factor,
+        inverse: directory.inverse_indicator,
+        luld_tier: match directory.luld_ref_price_tier {
+            LuldRefPriceTier::Tier1 => Some(1),
+            LuldRefPriceTier::Tier2 => Some(2),
+            LuldRefPriceTier::Na => None,
+        },
+        price_resolution: None,
+    }
+}
+
+fn market_type(category: MarketCategory) -> MarketType {
+    match category {
+        MarketCategory::NasdaqGlobalSelect
+        | MarketCategory::NasdaqGlobalMarket
+        | MarketCategory::NasdaqCapitalMarket => MarketType::Nasdaq,
+        MarketCategory::Nyse => MarketType::Nyse,
+        MarketCategory::NyseMkt => MarketType::NyseAmerican,
+        MarketCategory::NyseArca => MarketType::Arca,
+        MarketCategory::BatsZExchange => MarketType::Cboe,
+        MarketCategory::InvestorsExchange => MarketType::Iex,
+        MarketCategory::Unavailable => MarketType::Other,
+    }
+}
+
+fn security_type(classification: IssueClassification) -> SecurityType {
+    match classification {
+        IssueClassification::AmericanDepositaryShare
+        | IssueClassification::DepositoryReceipt => SecurityType::ADR,
+        IssueClassification::Bond => SecurityType::Bond,
+        IssueClassification::CommonStock => SecurityType::CommonStock,
+        IssueClassification::LimitedPartnership => SecurityType::LimitedPartnership,
+        IssueClassification::OrdinaryShare => SecurityType::OrdinaryShares,
+        IssueClassification::PreferredStock => SecurityType::PreferedStock,
+        IssueClassification::Right => SecurityType::Rights,
+        IssueClassification::SharesOfBeneficialInterest => SecurityType::BeneficialInterest,
+        IssueClassification::ConvertibleDebenture => SecurityType::Debentures,
+        IssueClassification::Unit | IssueClassification::UnitsPerBenifInt => SecurityType::Units,
+        IssueClassification::Warrant => SecurityType::Warrant,
+        IssueClassification::A144
+        | IssueClassification::Notes
+        | IssueClassification::OtherSecurities => SecurityType::Other,
+    }
+}
@@ -1,16 +1,23 @@
+use crate::auction::{auction_type, imbalance_snapshot};
 use crate::book::NasdaqOrderBook;
-use crate::constants::{P_N, REG_START_TIME_NS, R_N, T_N};
-use crate::data::{OrderStatus, LEVEL};
+use crate::data::{OrderStatus, LEVEL, NUM_FIELDS};
 use crate::data::StockContainer;
-use crate::interval_loc;
+use mmm_us::mpid::{MpidStat, MpidStatBuilder};
 use itchy::{CrossTrade, NonCrossTrade, StockDirectory};
 use itertools::Itertools;
+use mmm_us::auction::{Auction, AuctionBuilder, AuctionType};
+use mmm_us::calendar::SessionSchedule;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug)]
 pub(crate) struct StatBuilder {
+    schedule: SessionSchedule,
     partial_stat: MarketStat,
+    mpid: MpidStatBuilder,
+    auction: AuctionBuilder,
+    /// (upper, lower) price of the last LULD Auction Collar message, until the reopening cross
+    collar: Option<(u64, u64)>,
     last_execution_ns: u64,
     last_lob_interval_ind: Option<usize>,
     last_high_interval_ind: Option<usize>,
@@ -19,9 +26,13 @@ pub(crate) struct StatBuilder {
 }
 
 impl StatBuilder {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(schedule: SessionSchedule) -> Self {
         Self {
-            partial_stat: MarketStat::new(),
+            schedule,
+            partial_stat: MarketStat::new(schedule),
+            mpid: MpidStatBuilder::new(schedule),
+            auction: AuctionBuilder::new(),
+            collar: None,
             last_execution_ns: 0,
             last_lob_interval_ind: None,
             last_high_interval_ind: None,
@@ -39,12 +50,13 @@ impl StatBuilder {
         timestamp: u64,
         printable: bool,
     ) {
+        let (open_ind, close_ind) = (self.schedule.open_interval(), self.schedule.close_interval());
         let partial_stat = &mut self.partial_stat;
 
         partial_stat.total_volume += executed;
         partial_stat.post_market_volume += executed;
 
-        let bin_ind = interval_loc(timestamp);
+        let bin_ind = self.schedule.interval_loc(timestamp);
 
         // Sometimes we get multiple execute messages at the same
         // timestamp. If that's the case, we treat it as a single
@@ -60,36 +72,31 @@ impl StatBuilder {
         // we assume that cross trade message Q (with field O) always occurs after 9:30 and
         // and cross trade message Q (with field C) always occurs after 16:00
         let status = status_map.get(reference).unwrap();
-        if (partial_stat.regular_market_start.is_none()) & (bin_ind >= R_N) {
+        if (partial_stat.regular_market_start.is_none()) & (bin_ind >= open_ind) {
             // these are messages between 9:30~Cross trade message Q (with field O)
             // For these messages put it in the last premarket bin
             if printable{            
-                partial_stat.interval_volume[R_N - 1] += executed;
-                partial_stat.interval_price_volume[R_N - 1] += executed * price;
-                self.update_ohlc(R_N-1, price);            
+                partial_stat.interval_volume[open_ind - 1] += executed;
+                partial_stat.interval_price_volume[open_ind - 1] += executed * price;
+                self.update_ohlc(open_ind - 1, price);            
             }
 
-            //if status.mpid_val > 0 {
-            //    if printable {
-            //        partial_stat.interval_lp_volume[R_N - 1] += executed;
-            //        partial_stat.interval_lp_price_volume[R_N - 1] += executed * price;
-            //    }
-            //}
-        } else if (partial_stat.post_market_start.is_none()) & (bin_ind >= P_N) {
+            if status.mpid_val > 0 && printable {
+                self.partial_stat.interval_lp_volume[open_ind - 1] += executed;
+                self.partial_stat.interval_lp_price_volume[open_ind - 1] += executed * price;
+            }
+        } else if (partial_stat.post_market_start.is_none()) & (bin_ind >= close_ind) {
             // these are messages between 16:00~Cross trade message Q (with field C)
             // For these messages put it in the last regular market bin
             if printable{
-                partial_stat.interval_volume[P_N - 1] += executed;
-                partial_stat.interval_price_volume[P_N - 1] += executed * price;
-                self.update_ohlc(P_N-1, price);            
+                partial_stat.interval_volume[close_ind - 1] += executed;
+                partial_stat.interval_price_volume[close_ind - 1] += executed * price;
+                self.update_ohlc(close_ind - 1, price);            
             }   
-            //if status.mpid_val > 0 {
-            //    if printable {
-            //        partial_stat.interval_lp_volume[P_N - 1] += executed;
-            //        partial_stat.interval_lp_price_volume[P_N - 1] += executed * price;
-
-            //    }
-            //}
+            if status.mpid_val > 0 && printable {
+                self.partial_stat.interval_lp_volume[close_ind - 1] += executed;
+                self.partial_stat.interval_lp_price_volume[close_ind - 1] += executed * price;
+            }
         } else {
             // for all other messages, add it to the corresponding bin
             if printable {
@@ -99,12 +106,10 @@ impl StatBuilder {
            }
             
             // add order without mpid has mpid_val of 0
-            //if status.mpid_val > 0 {
-            //    if printable{
-            //        partial_stat.interval_lp_volume[bin_ind] += executed;
-            //        partial_stat.interval_lp_price_volume[bin_ind] += executed * price;
-            //    }
-            //}
+            if status.mpid_val > 0 && printable {
+                self.partial_stat.interval_lp_volume[bin_ind] += executed;
+                self.partial_stat.interval_lp_price_volume[bin_ind] += executed * price;
+            }
         }
         self.last_execution_ns = timestamp;
     }
@@ -158,7 +163,13 @@ impl StatBuilder {
         timestamp: u64,
         level: usize,
     ) {
-        let bin_ind = interval_loc(timestamp);
+        let bin_ind = self.schedule.interval_loc(timestamp);
+
+        // only the time of the snapshot moves while the book stays in the same interval
+        if book.take_changes().is_empty() && self.last_lob_interval_ind == Some(bin_ind) {
+            self.partial_stat.lob_level[bin_ind][0] = timestamp as i64;
+            return;
+        }
         
         let partial_stat = &mut self.partial_stat;
         
@@ -234,7 +245,7 @@ impl StatBuilder {
                         }
                     }
 
-                    if (bin_ind >= R_N) && (bin_ind < P_N){
+                    if (bin_ind >= self.schedule.open_interval()) && (bin_ind < self.schedule.close_interval()){
                        if partial_stat.lob_regmkt_max_spread.is_none(){
                            partial_stat.lob_regmkt_max_spread = Some(a-b);
                        }
@@ -303,9 +314,14 @@ impl StatBuilder {
                 ..
             }) => {
                 partial_stat.total_volume += shares;
+                let auction_type = auction_type(*cross_type);
+                self.auction.cross(auction_type, message.timestamp, cross_price.inner(), *shares);
+                if auction_type == AuctionType::Reopening {
+                    self.collar = None;
+                }
                 match cross_type {
                     itchy::CrossType::Opening => {
-                        assert!(message.timestamp >= REG_START_TIME_NS);
+                        assert!(message.timestamp >= self.schedule.open_ns);
                         partial_stat.opening_cross_price = cross_price.inner();
                         partial_stat.opening_cross_volume = *shares;
 
@@ -336,7 +352,6 @@ impl StatBuilder {
                 // - messages between 16:00-Cross trade message Q (with field C)
                 // we assume that cross trade message Q (with field O) always occurs after 9:30 and
                 // and cross trade message Q (with field C) always occurs after 16:00
-                let bin_ind = interval_loc(message.timestamp);
                 //if (partial_stat.regular_market_start.is_none()) & (bin_ind >= R_N) {
                 //    // these are messages between 9:30~Cross trade message Q (with field O)
                 //    // For these messages put it in the last premarket bin
@@ -380,6 +395,20 @@ impl StatBuilder {
             //    }
             //    _ => {}
             //},
+            itchy::Body::Imbalance(imbalance) => {
+                let auction_type = auction_type(imbalance.cross_type);
+                // LULD collars are of the reopening after a pause only
+                let collar = self.collar.filter(|_| auction_type == AuctionType::Reopening);
+                let snapshot = imbalance_snapshot(message.timestamp, imbalance, collar);
+                self.auction.imbalance(auction_type, snapshot);
+            }
+            itchy::Body::LULDAuctionCollar {
+                upper_price,
+                lower_price,
+                ..
+            } => {
+                self.collar = Some((upper_price.inner(), lower_price.inner()));
+            }
             itchy::Body::StockDirectory(sd) => {
                 partial_stat.stock_directory.replace(sd.clone());
             }
@@ -395,13 +424,22 @@ impl StatBuilder {
     }
 
  
+    /// Updates the MPID statistics with an encoded message, after `book` has handled it.
+    pub(crate) fn update_mpid(&mut self, encoded: &[u64; NUM_FIELDS], book: &mut NasdaqOrderBook) {
+        self.mpid.update(encoded, book);
+    }
+
     pub(crate) fn build(self) -> MarketStat {
-        self.partial_stat
+        let mut market_stat = self.partial_stat;
+        market_stat.mpid_stats = self.mpid.build();
+        market_stat.auctions = self.auction.build();
+        market_stat
     }
 }
 
 #[derive(Serialize, Debug)]
 pub(crate) struct MarketStat {
+    session: SessionSchedule,
     total_volume: u64,
     pre_market_volume: u64,
     regular_market_volume: u64,
@@ -420,8 +458,9 @@ pub(crate) struct MarketStat {
     interval_high: Vec<u64>,
     interval_low: Vec<u64>,
     interval_price: Vec<u64>,
-    //pub interval_lp_volume: Vec<u64>,
-    //pub interval_lp_price_volume: Vec<u64>,
+    /// executed volume of orders attributed to an MPID
+    pub interval_lp_volume: Vec<u64>,
+    pub interval_lp_price_volume: Vec<u64>,
     //interval_nondisp_volume: Vec<u64>,
     //interval_nondisp_price_volume: Vec<u64>,
     interval_execute_msg_count: Vec<u64>,
@@ -430,11 +469,20 @@ pub(crate) struct MarketStat {
     lob_max_shares: u64,
     lob_max_spread: Option<u64>,
     lob_regmkt_max_spread: Option<u64>,
+
+    /// statistics by mpid_val, exported to `{symbol}_mpid.json.zst`
+    #[serde(skip)]
+    pub mpid_stats: BTreeMap<u64, MpidStat>,
+    /// auctions with their imbalances, exported to `{symbol}_auction.json.zst`
+    #[serde(skip)]
+    pub auctions: Vec<Auction>,
 }
 
 impl MarketStat {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(session: SessionSchedule) -> Self {
+        let n = session.interval_count();
         Self {
+            session,
             total_volume: Default::default(),
             pre_market_volume: Default::default(),
             regular_market_volume: Default::default(),
@@ -448,21 +496,23 @@ impl MarketStat {
             closing_cross_volume: Default::default(),
             //events: Default::default(),
              
-            interval_volume: vec![0; T_N],
-            interval_price_volume: vec![0; T_N],
-            interval_high: vec![0; T_N],
-            interval_low: vec![u64::MAX; T_N],
-            interval_price: vec![0; T_N],
-            //interval_lp_volume: vec![0; T_N],
-            //interval_lp_price_volume: vec![0; T_N],
-            //interval_nondisp_volume: vec![0; T_N],
-            //interval_nondisp_price_volume: vec![0; T_N],
-            interval_execute_msg_count: vec![0; T_N],
+            interval_volume: vec![0; n],
+            interval_price_volume: vec![0; n],
+            interval_high: vec![0; n],
+            interval_low: vec![u64::MAX; n],
+            interval_price: vec![0; n],
+            interval_lp_volume: vec![0; n],
+            interval_lp_price_volume: vec![0; n],
+            //interval_nondisp_volume: vec![0; n],
+            //interval_nondisp_price_volume: vec![0; n],
+            interval_execute_msg_count: vec![0; n],
             
-            lob_level: vec![[0; LEVEL * 2 * 2 + 1]; T_N],
+            lob_level: vec![[0; LEVEL * 2 * 2 + 1]; n],
             lob_max_shares: Default::default(),
             lob_max_spread: None,
             lob_regmkt_max_spread: None,
+            mpid_stats: Default::default(),
+            auctions: Default::default(),
         }
     }
 }
@@ -0,0 +1,49 @@
+use crate::data::to_bp;
+use mmm_us::auction::{AuctionType, ImbalanceSnapshot};
+use mmm_us::price::PriceBasis;
+use taq::enums::{self, CrossType, Imbalance};
+
+/// Auction of an Imbalance message of `auction_type`.
+/// Regulatory and extreme closing imbalances are published for the closing auction.
+pub fn imbalance_auction_type(auction_type: &enums::AuctionType) -> AuctionType {
+    match auction_type {
+        enums::AuctionType::O => AuctionType::EarlyOpening,
+        enums::AuctionType::M => AuctionType::Opening,
+        enums::AuctionType::H => AuctionType::Reopening,
+        enums::AuctionType::R | enums::AuctionType::C | enums::AuctionType::P => {
+            AuctionType::Closing
+        }
+    }
+}
+
+/// Auction of a CrossTrade message of `cross_type`.
+pub fn cross_auction_type(cross_type: &CrossType) -> AuctionType {
+    match cross_type {
+        CrossType::E => AuctionType::EarlyOpening,
+        CrossType::O => AuctionType::Opening,
+        CrossType::R => AuctionType::Reopening,
+        CrossType::C => AuctionType::Closing,
+    }
+}
+
+/// Snapshot of an Imbalance message. The near price is the continuous book clearing price,
+/// and the far price the auction interest clearing price.
+pub fn imbalance_snapshot(time_ns: u64, imbalance: &Imbalance) -> ImbalanceSnapshot {
+    ImbalanceSnapshot {
+        time_ns,
+        paired_shares: imbalance.paired_qty as u64,
+        imbalance_shares: imbalance.total_imbalance_qty as u64,
+        imbalance_side: imbalance.imbalance_side,
+        reference_price: price(&imbalance.reference_price),
+        near_price: price(&imbalance.continous_book_clearing_price),
+        far_price: price(&imbalance.auction_interest_clearing_price),
+        indicative_price: price(&imbalance.indicative_match_price),
+        upper_collar: price(&imbalance.upper_collar),
+        lower_collar: price(&imbalance.lower_collar),
+    }
+}
+
+// prices of the Imbalance message are 0 when not applicable
+fn price(price: &PriceBasis) -> Option<u64> {
+    Some(to_bp(price)).filter(|price| *price > 0)
+}
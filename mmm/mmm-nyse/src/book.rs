@@ -6,12 +6,12 @@ use anyhow::bail;
 //     Side,
 // };
 use taq::enums::CrossType;
-use mmm_us::{OrderBook, PriceTimePriority, Side};
+use mmm_us::{util::Time, BookChanges, OrderBook, PriceTimePriority, Side};
 pub type IdTimeQuantity = (u64, u64, u64);
 
 #[derive(Clone, Debug)]
 pub struct Message {
-    pub time: u64,
+    pub time: Time,
     pub body: Body,
 }
 
@@ -48,14 +48,26 @@ pub enum Body {
     CrossTrade {
         cross_type: CrossType,
     },
-    NonCrossTrade {},
+    NonCrossTrade {
+        shares: u64,
+        price: u64,
+    },
+    /// Not in preprocessed files, which write a modify as a cancel or a delete and an add.
+    ModifyOrder {
+        reference: u64,
+        shares: u64,
+        price: u64,
+        keep_priority: bool,
+    },
 }
 
 #[derive(Debug)]
 pub struct NyseOrderBook {
     max_ref: u64,
-    book: OrderBook<u64, u64, u64, u64>,
+    book: OrderBook<u64, u64, u64, u64, BookChanges<u64>>,
     with_validation: bool,
+    // midnight in New York of the first message, the time of an order counts from it
+    midnight: Option<Time>,
 }
 
 impl NyseOrderBook {
@@ -64,6 +76,7 @@ impl NyseOrderBook {
             max_ref: 0,
             book: OrderBook::new(),
             with_validation,
+            midnight: None,
         }
     }
     pub fn handle(&mut self, msg: &Message) -> anyhow::Result<()> {
@@ -82,6 +95,9 @@ impl NyseOrderBook {
                 bt.price
             );
         }
+        let midnight = *(self.midnight)
+            .get_or_insert_with(|| Time::from_local_nanos(msg.time.local_date(), 0));
+        let time = msg.time.epoch_nanos() - midnight.epoch_nanos();
         match msg.body {
             Body::AddOrder {
                 reference,
@@ -95,12 +111,12 @@ impl NyseOrderBook {
                     match side {
                         Side::Ask => {
                             self.book
-                                .insert(reference, side, price, shares, msg.time)
+                                .insert(reference, side, price, shares, time)
                                 .unwrap();
                         }
                         Side::Bid => {
                             self.book
-                                .insert(reference, side, price, shares, msg.time)
+                                .insert(reference, side, price, shares, time)
                                 .unwrap();
                         }
                     }
@@ -108,12 +124,12 @@ impl NyseOrderBook {
                     match side {
                         Side::Ask => {
                             self.book
-                                .sorted_insert_ask_by_key(reference, price, shares, msg.time)
+                                .sorted_insert_ask_by_key(reference, price, shares, time)
                                 .unwrap();
                         }
                         Side::Bid => {
                             self.book
-                                .sorted_insert_bid_by_key(reference, price, shares, msg.time)
+                                .sorted_insert_bid_by_key(reference, price, shares, time)
                                 .unwrap();
                         }
                     }
@@ -138,11 +154,11 @@ impl NyseOrderBook {
                 match &s {
                     Side::Ask => self
                         .book
-                        .insert(new_reference, s, price, shares, msg.time)
+                        .insert(new_reference, s, price, shares, time)
                         .unwrap(),
                     Side::Bid => self
                         .book
-                        .insert(new_reference, s, price, shares, msg.time)
+                        .insert(new_reference, s, price, shares, time)
                         .unwrap(),
                 };
             }
@@ -174,6 +190,22 @@ impl NyseOrderBook {
             } => {
                 self.book.reduce(&reference, executed).unwrap();
             }
+            Body::ModifyOrder {
+                reference,
+                shares,
+                price,
+                keep_priority,
+            } => {
+                let keep_priority = keep_priority
+                    && (self.book.keeps_priority(&reference, &price, &shares)).unwrap_or(false);
+                self.book.modify(&reference, price, shares, keep_priority)?;
+                // time of an order is when it joined the back of its level.
+                if !keep_priority {
+                    if let Some(order_time) = self.book.get_info_mut(&reference) {
+                        *order_time = time;
+                    }
+                }
+            }
             // Body::CrossTrade {
             //     ..
             // } => {
@@ -199,7 +231,8 @@ impl NyseOrderBook {
             // | Body::StockDirectory(_)
             // | Body::SystemEvent { .. }
             | Body::CrossTrade{..}
-            | Body::NonCrossTrade {}
+            // non-displayed executions leave no order in the book, MarketStat counts their volume
+            | Body::NonCrossTrade { .. }
             // | Body::TradingAction {
             //     trading_state: TradingState::Trading,
             //     ..
@@ -208,6 +241,11 @@ impl NyseOrderBook {
         };
         Ok(())
     }
+    /// Levels changed since the last call.
+    pub fn take_changes(&mut self) -> BookChanges<u64> {
+        self.book.observer_mut().take()
+    }
+
     pub fn level_summary(&mut self, level: usize) -> HashMap<String, HashMap<u64, u64>> {
         let mut summary = HashMap::new();
 
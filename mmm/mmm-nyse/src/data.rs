@@ -1,13 +1,16 @@
 use crate::book::{Body, Message, NyseOrderBook};
+use crate::constants::SESSION_SCHEDULE;
+use crate::security::security_meta;
 use crate::stat::{StatBuilder};
 use crate::{create_folder, delete_channel_id};
 pub use decimal::d128;
+use anyhow::anyhow;
 use flate2::read::GzDecoder;
 use itertools::Itertools;
 // use mmm_core::collections::Side;
 // use mmm_us::Side;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::File;
 use std::io::{Read, Write};
@@ -15,11 +18,19 @@ use std::path::{Path, PathBuf};
 use taq;
 use taq::enums::{
     AddOrder, CrossTrade, CrossType, DeleteOrder, ModifyOrder, NonDisplayedTrade, OrderExecution,
-    ReplaceOrder,
+    PositionChange, ReplaceOrder,
 };
 
 pub const LEVEL: usize = 5;
-use mmm_us::{decode_side, encode_side, price::PriceBasis, Side};
+use chrono::NaiveDate;
+use mmm_us::auction::AUCTION_FILE_SUFFIX;
+use mmm_us::security::{dump_securities, SECURITIES_FILE};
+use mmm_us::symbol::{dump_symbols, SymbolId, SymbolRegistry, VenueId, SYMBOLS_FILE};
+use mmm_us::{calendar::SessionSchedule, decode_side, encode_side, price::PriceBasis, util::Time, Side};
+/// A preprocessed message is a row of `NUM_FIELDS`: type, time, reference, shares, price, side,
+/// original shares, extra and the index of the next message of the same order.
+/// Types 0 to 7 are those of NASDAQ. A modify that keeps its place in the queue is a cancel
+/// of the removed shares, otherwise it is a delete and an add of the new order.
 pub const NUM_FIELDS: usize = 9;
 
 // fn encode_side(side: Side) -> u64 {
@@ -67,8 +78,14 @@ fn decode_cross_type(cross_type: u64) -> CrossType {
     }
 }
 
-impl From<&[u64]> for Message {
-    fn from(values: &[u64]) -> Self {
+impl Message {
+    /// Decodes a row of the preprocessed file of `date`, whose time is nanoseconds since midnight in New York.
+    pub fn decode(values: &[u64], date: NaiveDate) -> Self {
+        Self::decode_since(values, Time::from_local_nanos(date, 0))
+    }
+
+    /// Decodes a row whose time is nanoseconds since `midnight`, computed once for the rows of a file.
+    pub fn decode_since(values: &[u64], midnight: Time) -> Self {
         let body = match values[0] {
             0 => Body::AddOrder {
                 reference: values[2],
@@ -101,11 +118,14 @@ impl From<&[u64]> for Message {
             6 => Body::CrossTrade {
                 cross_type: decode_cross_type(values[7]),
             },
-            7 => Body::NonCrossTrade {},
+            7 => Body::NonCrossTrade {
+                shares: values[3],
+                price: values[4],
+            },
             _ => panic!("unknown value found for 'MessageType'"),
         };
         Self {
-            time: values[1],
+            time: Time::from_epoch_nanos(midnight.epoch_nanos() + values[1]),
             body,
         }
     }
@@ -150,8 +170,7 @@ pub struct OrderStatus {
 impl OrderStatus {
     fn new(price: PriceBasis, side: Side, quantity: u32, last_index: usize, mpid_val: u64) -> Self {
         Self {
-            //bug
-            price: price.inner(),
+            price: to_bp(&price),
             side: encode_side(side),
             shares: quantity as u64,
             index: last_index,
@@ -160,10 +179,29 @@ impl OrderStatus {
     }
 }
 
-pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool) {
+/// Price in bp as in the encoded messages, whatever basis it was parsed in.
+/// Rule 612 allows no price finer than a bp.
+pub(crate) fn to_bp(price: &PriceBasis) -> u64 {
+    price.to_basis(10000).unwrap()
+}
+
+/// Preprocesses the TAQ files of one day, whose names must carry the trading day, see `file_date`.
+pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool) -> anyhow::Result<()> {
+    process_file_with_schedule(path_list, out_dir, meta_only, SESSION_SCHEDULE)
+}
+
+/// Same as `process_file`, but the sessions of the day are given, e.g. from a TradingCalendar.
+pub fn process_file_with_schedule(
+    path_list: Vec<PathBuf>,
+    out_dir: PathBuf,
+    meta_only: bool,
+    schedule: SessionSchedule,
+) -> anyhow::Result<()> {
     println!("Processing files...");
     let path = &path_list[0];
     let path = delete_channel_id(path);
+    let date = file_date(&path).ok_or_else(|| anyhow!("no trading day in {:?}", path))?;
+    let midnight = Time::from_local_nanos(date, 0);
     let out_dir = create_folder(&path, &out_dir);
     let done_file = out_dir.join(".done");
     if !meta_only && done_file.exists() {
@@ -171,10 +209,12 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
             "skip `process_file` for {:?}. done file already exists.",
             path
         );
-        return;
+        return Ok(());
     }
 
-    let mut containers: HashMap<String, (StatBuilder, StockContainer)> = HashMap::new();
+    let mut registry = SymbolRegistry::new();
+    let mut symbols = BTreeMap::new();
+    let mut containers: HashMap<SymbolId, (StatBuilder, StockContainer)> = HashMap::new();
     let mut status_map = HashMap::new();
     // there's no participant position msg in nyse taq so add firm id whenever there is some firm id
     let mut global_mpid_map: HashMap<String, u64> = HashMap::new();
@@ -196,12 +236,19 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
             let message = msg.unwrap();
             let stock_locate = message.symbol.as_str();
             let timestamp = message.source_time;
+            if let taq::parser::Body::SymbolIndexMapping(mapping) = &message.body {
+                let venue = VenueId::Nyse {
+                    system_id: mapping.system_id,
+                    symbol_index: message.sequence_number.sequence_number(),
+                };
+                symbols.insert(venue, stock_locate.to_string());
+            }
             let (stat_builder, stock_container) = {
                 let (stat_builder, stock_container) = containers
-                    .entry(stock_locate.to_string())
+                    .entry(registry.intern(stock_locate))
                     .or_insert_with(|| {
                         (
-                            StatBuilder::new(),
+                            StatBuilder::new(schedule),
                             StockContainer::new_with_name(stock_locate.to_string()),
                         )
                     });
@@ -247,13 +294,13 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                     ]])
                 }
                 //modify is only for cases other than cancel or replace
-                //but we just treat everything as modify
+                //the order keeps its place in the queue unless position_change says it is lost
                 taq::parser::Body::ModifyOrder(ModifyOrder {
                     // symbol_seq_number,
                     order_id,
                     price,
                     volume,
-                    ..
+                    position_change,
                 }) => {
                     //status map at this point should have some kind of add order
                     //we want to change this order
@@ -263,41 +310,25 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                     //give that add order index: current index and update the order
                     status.index = current_index;
 
-                    if (price.inner()) != status.price {
-                        //first add a delete order for the existing order
-                        let mut encoded = vec![[
-                            1,
+                    let keeps_priority = position_change == PositionChange::Kept
+                        && to_bp(&price) == status.price
+                        && volume as u64 <= status.shares;
+                    if keeps_priority {
+                        let cancelled = status.shares - volume as u64;
+                        status.shares = volume as u64;
+                        Some(vec![[
+                            2,
                             timestamp.unwrap(),
                             order_id,
-                            status.shares,
+                            cancelled,
                             status.price,
                             status.side,
                             status.shares,
                             0,
                             0,
-                        ]];
-                        let original_status = status_map.remove(&order_id).unwrap();
-                        let status = OrderStatus::new(
-                            price,
-                            decode_side(original_status.side),
-                            volume,
-                            stock_messages.len() + 1,
-                            original_status.mpid_val,
-                        );
-                        status_map.insert(order_id, status.clone());
-                        encoded.push([
-                            0,
-                            timestamp.unwrap(),
-                            order_id,
-                            status.shares,
-                            status.price,
-                            status.side,
-                            0,
-                            status.mpid_val,
-                            0,
-                        ]);
-                        Some(encoded)
-                    } else if volume as u64 > status.shares {
+                        ]])
+                    } else {
+                        //first add a delete order for the existing order
                         let mut encoded = vec![[
                             1,
                             timestamp.unwrap(),
@@ -309,18 +340,15 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                             0,
                             0,
                         ]];
-                        //second add a new order while removing the previous add order
+                        //second add a new order at the back of the queue
                         let original_status = status_map.remove(&order_id).unwrap();
-                        let mpid_val = original_status.mpid_val;
-                        let side = decode_side(original_status.side);
                         let status = OrderStatus::new(
                             price,
-                            side,
+                            decode_side(original_status.side),
                             volume,
                             stock_messages.len() + 1,
-                            mpid_val,
+                            original_status.mpid_val,
                         );
-                        status_map.insert(order_id, status.clone());
                         encoded.push([
                             0,
                             timestamp.unwrap(),
@@ -329,24 +357,11 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                             status.price,
                             status.side,
                             0,
-                            mpid_val,
+                            status.mpid_val,
                             0,
                         ]);
+                        status_map.insert(order_id, status);
                         Some(encoded)
-                    } else {
-                        let cancelled = status.shares - volume as u64;
-                        status.shares = volume as u64;
-                        Some(vec![[
-                            2,
-                            timestamp.unwrap(),
-                            order_id,
-                            cancelled,
-                            status.price,
-                            status.side,
-                            status.shares,
-                            0,
-                            0,
-                        ]])
                     }
                 }
                 taq::parser::Body::DeleteOrder(DeleteOrder { order_id, .. }) => {
@@ -378,7 +393,7 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
 
                     let orig_shares = status.shares;
                     let status = OrderStatus {
-                        price: price.inner(),
+                        price: to_bp(&price),
                         side: status.side,
                         shares: volume as u64,
                         index: stock_messages.len(),
@@ -407,7 +422,7 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                 }) => {
                     let status = status_map.get_mut(&order_id).unwrap();
                     let original_price = status.price;
-                    let current_price = price.inner();
+                    let current_price = to_bp(&price);
                     let current_index = stock_messages.len();
                     stock_messages[status.index][NUM_FIELDS - 1] = current_index as u64;
                     status.index = current_index;
@@ -455,7 +470,7 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                     timestamp.unwrap(),
                     0,
                     volume as u64,
-                    price.inner(),
+                    to_bp(&price),
                     0,
                     0,
                     encode_cross_type(cross_type),
@@ -471,7 +486,7 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                     timestamp.unwrap(),
                     0,
                     volume as u64,
-                    price.inner(),
+                    to_bp(&price),
                     0,
                     0,
                     0,
@@ -493,7 +508,8 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
 
             if let Some(encoded) = encoded {
                 for encoded_array in encoded{
-                    book.handle(&Message::from(encoded_array.as_slice())).unwrap();
+                    book.handle(&Message::decode_since(encoded_array.as_slice(), midnight)).unwrap();
+                    stat_builder.update_mpid(&encoded_array, book);
                     let (bo, bb) = book.bbo();
                     match (bo, bb) {
                         (None, None) => bbos.push([-1, 0]),
@@ -522,6 +538,14 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
                 .map(|name| name.symbol.clone().trim_end().to_string());
             let out_path = out_dir.join(format!("{}.json.zst", name.as_ref().unwrap().trim_end()));
             dump(out_path, &serde_json::to_vec(&market_stat).unwrap());
+            if !market_stat.mpid_stats.is_empty() {
+                let out_path = out_dir.join(format!("{}_mpid.json.zst", name.as_ref().unwrap()));
+                dump(out_path, &serde_json::to_vec(&market_stat.mpid_stats).unwrap());
+            }
+            if !market_stat.auctions.is_empty() {
+                let out_path = out_dir.join(format!("{}{}", name.as_ref().unwrap(), AUCTION_FILE_SUFFIX));
+                dump(out_path, &serde_json::to_vec(&market_stat.auctions).unwrap());
+            }
             name.map(|name| (name, market_stat))
         })
         .collect::<HashMap<_, _>>();
@@ -531,6 +555,22 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
     //    &serde_json::to_vec(&market_stats).unwrap(),
     //);
     
+    let securities = market_stats
+        .values()
+        .filter_map(|stat| {
+            let message = stat.stock_directory.as_ref()?;
+            match &message.body {
+                taq::parser::Body::SymbolIndexMapping(mapping) => {
+                    Some(security_meta(&message.symbol, mapping))
+                }
+                _ => None,
+            }
+        })
+        .sorted_by(|a, b| a.ticker.cmp(&b.ticker))
+        .collect::<Vec<_>>();
+    dump_securities(out_dir.join(SECURITIES_FILE), &securities).unwrap();
+    dump_symbols(out_dir.join(SYMBOLS_FILE), &symbols).unwrap();
+
     dump(
         out_dir.join("mpid_map.json.zst"),
         &serde_json::to_vec(
@@ -543,7 +583,7 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
     );
 
     if meta_only {
-        return;
+        return Ok(());
     }
 
     stock_containers
@@ -579,6 +619,28 @@ pub fn process_file(path_list: Vec<PathBuf>, out_dir: PathBuf, meta_only: bool)
         })
         .collect::<Vec<_>>();
     File::create(done_file).unwrap();
+    Ok(())
+}
+
+/// Security master records written by `process_file` for `path`.
+pub fn securities_path(path: &Path, out_dir: &Path) -> PathBuf {
+    create_folder(&delete_channel_id(path), out_dir).join(SECURITIES_FILE)
+}
+
+/// Trading day of a TAQ file, e.g. "EQY_US_ARCA_IBF_10_20211004.gz",
+/// or of a file preprocessed from it, e.g. "EQY_US_ARCA_IBF_20211004/AAPL.bin.zst".
+pub fn file_date(path: &Path) -> Option<NaiveDate> {
+    path.iter().rev().find_map(|name| {
+        let date = name.to_str()?.split('.').next()?.rsplit('_').next()?;
+        (date.len() == 8)
+            .then(|| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+            .flatten()
+    })
+}
+
+/// Venue ids written by `process_file` for `path`.
+pub fn symbols_path(path: &Path, out_dir: &Path) -> PathBuf {
+    create_folder(&delete_channel_id(path), out_dir).join(SYMBOLS_FILE)
 }
 
 pub(crate) fn dump(out_path: PathBuf, serialized: &[u8]) {
@@ -609,6 +671,34 @@ pub fn load<P: AsRef<Path>>(path: P, num_fields: usize) -> Vec<Vec<u64>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn trading_day_of_file_names() {
+        let date = NaiveDate::from_ymd_opt(2021, 10, 4);
+        assert_eq!(file_date(Path::new("./taq_data/EQY_US_ARCA_IBF_10_20211004.gz")), date);
+        assert_eq!(file_date(Path::new("./test_data/EQY_US_ARCA_IBF_20211004/AAPL.bin.zst")), date);
+        assert_eq!(file_date(Path::new("./taq_data/AAPL.gz")), None);
+    }
+
+    #[test]
+    fn order_times_on_the_spring_forward_day() {
+        let date = NaiveDate::from_ymd_opt(2022, 3, 13).unwrap();
+        let hour = 3_600_000_000_000;
+        let rows = [
+            [0, hour, 1, 100, 100, encode_side(Side::Bid), 0, 0, 0],
+            [0, 10 * hour, 2, 100, 100, encode_side(Side::Bid), 0, 0, 0],
+        ];
+        let midnight = Time::from_local_nanos(date, 0);
+        let mut book = NyseOrderBook::new(false);
+        for row in &rows {
+            let message = Message::decode_since(row, midnight);
+            assert_eq!(message.time, Message::decode(row, date).time);
+            book.handle(&message).unwrap();
+        }
+        // the times of the orders are those of the rows, either side of 2:00
+        let bids = &book.level_snapshot(1)["Bid"][&100];
+        assert_eq!(bids, &vec![(1, hour, 100), (2, 10 * hour, 100)]);
+    }
+
     #[test]
     fn run_preprocess() {
         env::set_var("RUST_BACKTRACE", "1");
@@ -629,7 +719,7 @@ mod tests {
             PathBuf::from("./taq_data/EQY_US_ARCA_IBF_11_20211004.gz"),
         ];
         // process_file(nypath, PathBuf::from("../sample/done"), false);
-        process_file(nypath, PathBuf::from("./test_data/"), false);
+        process_file(nypath, PathBuf::from("./test_data/"), false).unwrap();
         let elapsed = now.elapsed();
         println!("NYSE preprocess elapsed: {:.2?}", elapsed);
        
@@ -0,0 +1,62 @@
+use crate::book::NyseOrderBook;
+use mmm_us::mpid::InsideBook;
+use std::collections::HashMap;
+
+impl InsideBook for NyseOrderBook {
+    fn bbo(&self) -> (Option<u64>, Option<u64>) {
+        NyseOrderBook::bbo(self)
+    }
+
+    fn level_summary(&mut self, level: usize) -> HashMap<String, HashMap<u64, u64>> {
+        NyseOrderBook::level_summary(self, level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmm_us::mpid::MpidStatBuilder;
+    use crate::book::Message;
+    use chrono::NaiveDate;
+    use crate::constants::SESSION_SCHEDULE;
+
+    // side as encoded by `encode_side`
+    const BID: u64 = 2;
+
+    #[test]
+    fn firm_volume_and_inside_time() {
+        let mut book = NyseOrderBook::new(false);
+        let mut builder = MpidStatBuilder::new(SESSION_SCHEDULE);
+        let (open_ns, second) = (SESSION_SCHEDULE.open_ns, 1_000_000_000);
+        let rows = [
+            // firm 7 alone at the best bid, one tick above the next level
+            [0, open_ns, 1, 100, 100, BID, 0, 7, 0],
+            [0, open_ns, 2, 50, 99, BID, 0, 0, 0],
+            [2, open_ns + second, 1, 40, 100, BID, 60, 0, 0],
+            [4, open_ns + 2 * second, 1, 60, 100, BID, 60, 0, 0],
+        ];
+        let date = NaiveDate::from_ymd_opt(2021, 10, 4).unwrap();
+        for row in rows {
+            book.handle(&Message::decode(&row, date)).unwrap();
+            builder.update(&row, &mut book);
+        }
+
+        let stats = builder.build();
+        let stat = &stats[&7];
+        assert_eq!(stats.len(), 1);
+        assert_eq!(
+            (stat.total.added, stat.total.executed, stat.total.cancelled),
+            (100, 60, 40)
+        );
+        assert_eq!(stat.total.inside_ns, 2 * second);
+        assert_eq!(stat.total.spread_contribution, 1.0);
+        assert_eq!(stat.total.orders, 1);
+        assert_eq!((stat.cancel_ratio, stat.fill_rate), (0.4, 0.6));
+        let open = SESSION_SCHEDULE.open_interval();
+        assert_eq!(
+            stat.intervals.keys().copied().collect::<Vec<_>>(),
+            vec![open, open + 1, open + 2]
+        );
+        assert_eq!(stat.intervals[&(open + 1)].inside_ns, second);
+    }
+}
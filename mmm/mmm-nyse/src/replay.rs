@@ -1,3 +1,5 @@
+use chrono::NaiveDate;
+use mmm_us::util::Time;
 use std::collections::{HashMap, VecDeque};
 
 use crate::book::{Message, NyseOrderBook};
@@ -5,6 +7,8 @@ use crate::book::{Message, NyseOrderBook};
 struct TimeBasedReplay {
     book: NyseOrderBook,
     messages: VecDeque<Vec<u64>>,
+    // midnight in New York of the trading day, the time of the messages counts from it
+    midnight: Time,
 }
 
 pub type VolumeResult = (u64, HashMap<String, HashMap<u64, u64>>, Vec<Vec<u64>>, bool);
@@ -16,9 +20,13 @@ pub type QueueResult = (
 );
 
 impl TimeBasedReplay {
-    fn new(messages: VecDeque<Vec<u64>>) -> Self {
+    fn new(messages: VecDeque<Vec<u64>>, date: NaiveDate) -> Self {
         let book = NyseOrderBook::new(false);
-        Self { book, messages }
+        Self {
+            book,
+            messages,
+            midnight: Time::from_local_nanos(date, 0),
+        }
     }
 
     fn step(&mut self) -> Option<(u64, Vec<Vec<u64>>, bool)> {
@@ -34,7 +42,7 @@ impl TimeBasedReplay {
         }
 
         for msg in &messages {
-            self.book.handle(&Message::from(&**msg)).unwrap();
+            self.book.handle(&Message::decode_since(msg, self.midnight)).unwrap();
         }
 
         // println!("AB {} {}", sum["Ask"].keys().len(), sum["Bid"].keys().len());
@@ -53,8 +61,13 @@ pub struct TimeBasedQueueReplay {
 }
 
 impl TimeBasedQueueReplay {
-    pub fn new(messages: VecDeque<Vec<u64>>, orderbook_depth: OrderbookDepth) -> Self {
-        let inner = TimeBasedReplay::new(messages);
+    /// Replays the preprocessed messages of `date`.
+    pub fn new(
+        messages: VecDeque<Vec<u64>>,
+        date: NaiveDate,
+        orderbook_depth: OrderbookDepth,
+    ) -> Self {
+        let inner = TimeBasedReplay::new(messages, date);
         Self {
             inner,
             orderbook_depth,
@@ -75,8 +88,13 @@ pub struct TimeBasedVolumeReplay {
 }
 
 impl TimeBasedVolumeReplay {
-    pub fn new(messages: VecDeque<Vec<u64>>, orderbook_depth: OrderbookDepth) -> Self {
-        let inner = TimeBasedReplay::new(messages);
+    /// Replays the preprocessed messages of `date`.
+    pub fn new(
+        messages: VecDeque<Vec<u64>>,
+        date: NaiveDate,
+        orderbook_depth: OrderbookDepth,
+    ) -> Self {
+        let inner = TimeBasedReplay::new(messages, date);
         Self {
             inner,
             orderbook_depth,
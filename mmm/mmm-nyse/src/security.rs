@@ -0,0 +1,57 @@
+use mmm_us::security::SecurityMeta;
+use mmm_us::util::{MarketType, SecurityType};
+use taq::enums::{IssueClassification, MarketCategory, SymbolIndexMapping};
+
+/// Security master record of the SymbolIndexMapping message of `symbol`.
+/// TAQ does not publish the ETP details, issue subtype nor the LULD tier.
+pub fn security_meta(symbol: &str, mapping: &SymbolIndexMapping) -> SecurityMeta {
+    let classification = security_type(mapping.issue_classification);
+    SecurityMeta {
+        ticker: symbol.trim_end().to_string(),
+        round_lot_size: mapping.round_lot_size as u64,
+        classification,
+        market_category: market_type(mapping.market_category),
+        prev_closing_price: Some(mapping.prev_close_price.clone()),
+        issue_subtype: None,
+        etp: classification == SecurityType::Etf,
+        etp_leverage_factor: 0,
+        inverse: false,
+        luld_tier: None,
+        price_resolution: Some(mapping.mpv as u64),
+    }
+}
+
+fn market_type(category: MarketCategory) -> MarketType {
+    match category {
+        MarketCategory::Nyse => MarketType::Nyse,
+        MarketCategory::NyseArcaEquities => MarketType::Arca,
+        MarketCategory::NyseAmericanEquities => MarketType::NyseAmerican,
+        MarketCategory::NyseNationalEquities => MarketType::NyseNational,
+        MarketCategory::NyseChicagoEquities => MarketType::NyseChicago,
+        MarketCategory::NyseArcaOptions
+        | MarketCategory::NyseBonds
+        | MarketCategory::GlobalOTC
+        | MarketCategory::NyseAmexOptions => MarketType::Other,
+    }
+}
+
+fn security_type(classification: IssueClassification) -> SecurityType {
+    match classification {
+        IssueClassification::ADR => SecurityType::ADR,
+        IssueClassification::CommonStock => SecurityType::CommonStock,
+        IssueClassification::Debuntures => SecurityType::Debentures,
+        IssueClassification::ETF => SecurityType::Etf,
+        IssueClassification::Foreign => SecurityType::Foreign,
+        IssueClassification::USDepositaryShares => SecurityType::UsDepositoryShares,
+        IssueClassification::Units => SecurityType::Units,
+        IssueClassification::IndexLinkedNotes => SecurityType::IndexLinkedNotes,
+        IssueClassification::Trust => SecurityType::Trust,
+        IssueClassification::OrdinaryShares => SecurityType::OrdinaryShares,
+        IssueClassification::PreferredStock => SecurityType::PreferedStock,
+        IssueClassification::Rights => SecurityType::Rights,
+        IssueClassification::BeneficiaryInterest => SecurityType::BeneficialInterest,
+        IssueClassification::ClosedEndFund => SecurityType::ClosedEndFund,
+        IssueClassification::Warrant => SecurityType::Warrant,
+        IssueClassification::Test => SecurityType::Other,
+    }
+}
@@ -1,10 +1,12 @@
+use crate::auction::{cross_auction_type, imbalance_auction_type, imbalance_snapshot};
 use crate::book::NyseOrderBook;
-use crate::constants::{P_N, REG_END_TIME_NS, REG_START_TIME_NS, R_N, T_N};
-use crate::data::OrderStatus;
+use crate::data::{to_bp, OrderStatus, NUM_FIELDS};
 use crate::data::StockContainer;
-use crate::interval_loc;
+use mmm_us::mpid::{MpidStat, MpidStatBuilder};
+use mmm_us::auction::{Auction, AuctionBuilder};
+use mmm_us::calendar::SessionSchedule;
 use serde::{Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use taq::enums::CrossTrade;
 use taq::enums::NonDisplayedTrade;
 use taq::enums::OrderExecution;
@@ -12,15 +14,21 @@ use taq::parser::Message;
 
 #[derive(Debug)]
 pub(crate) struct StatBuilder {
+    schedule: SessionSchedule,
     pub partial_stat: MarketStat,
+    mpid: MpidStatBuilder,
+    auction: AuctionBuilder,
     last_execution_ns: u64,
     last_lob_interval_ind: usize,
 }
 
 impl StatBuilder {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(schedule: SessionSchedule) -> Self {
         Self {
-            partial_stat: MarketStat::new(),
+            schedule,
+            partial_stat: MarketStat::new(schedule),
+            mpid: MpidStatBuilder::new(schedule),
+            auction: AuctionBuilder::new(),
             last_execution_ns: 0,
             last_lob_interval_ind: 0,
         }
@@ -34,12 +42,12 @@ impl StatBuilder {
         reference: &u64,
         timestamp: u64,
     ) {
+        let bin_ind = self.schedule.interval_loc(timestamp);
         let partial_stat = &mut self.partial_stat;
 
         partial_stat.total_volume += executed;
         partial_stat.post_market_volume += executed;
 
-        let bin_ind = interval_loc(timestamp);
 
         // Sometimes we get multiple execute messages at the same
         // timestamp. If that's the case, we treat it as a single
@@ -68,8 +76,16 @@ impl StatBuilder {
         timestamp: u64,
         level: usize,
     ) {
-        let bin_ind = interval_loc(timestamp);
+        let bin_ind = self.schedule.interval_loc(timestamp);
         let partial_stat = &mut self.partial_stat;
+        // only the time of the snapshot moves while the book stays in the same interval
+        if book.take_changes().is_empty()
+            && self.last_lob_interval_ind == bin_ind
+            && partial_stat.lob_level_5[bin_ind][0] != 0
+        {
+            partial_stat.lob_level_5[bin_ind][0] = timestamp;
+            return;
+        }
         let summary = book.level_summary(level);
         assert!(summary["Bid"].len() <= level);
         assert!(summary["Ask"].len() <= level);
@@ -141,7 +157,7 @@ impl StatBuilder {
                     self.update_execute_msg(
                             status_map,
                             *volume as u64,
-                            price.inner(), 
+                            to_bp(price), 
                             order_id,
                             message.source_time.unwrap());
                 }
@@ -154,6 +170,12 @@ impl StatBuilder {
                 ..
             }) => {
                 partial_stat.total_volume += *volume as u64;
+                self.auction.cross(
+                    cross_auction_type(cross_type),
+                    message.source_time.unwrap(),
+                    to_bp(price),
+                    *volume as u64,
+                );
                 match cross_type {
                     taq::enums::CrossType::O => {}
                     taq::enums::CrossType::C => {}
@@ -169,9 +191,9 @@ impl StatBuilder {
                 ..
             }) => {
                 let shares = *volume as u64;
-                let bin_ind = interval_loc(message.source_time.unwrap());
+                let bin_ind = self.schedule.interval_loc(message.source_time.unwrap());
                 partial_stat.interval_nondisp_volume[bin_ind] += shares;
-                partial_stat.interval_nondisp_price_volume[bin_ind] += shares * price.inner();
+                partial_stat.interval_nondisp_price_volume[bin_ind] += shares * to_bp(price);
             }
             // taq::parser::Body::AddOrder(_) => todo!(),
             body @ taq::parser::Body::CrossCorrection(_)
@@ -188,19 +210,33 @@ impl StatBuilder {
             // taq::parser::Body::ReplaceOrder(_) => todo!(),
             //todo for now, nyse does not provide systemevent messsages
             // },
+            taq::parser::Body::Imbalance(imbalance) => {
+                let snapshot = imbalance_snapshot(message.source_time.unwrap(), imbalance);
+                self.auction
+                    .imbalance(imbalance_auction_type(&imbalance.auction_type), snapshot);
+            }
             taq::parser::Body::SymbolIndexMapping(_) => {
                 partial_stat.stock_directory.replace(message.clone());
             }
             _ => {}
         }
     }
+    /// Updates the firm statistics with an encoded message, after `book` has handled it.
+    pub(crate) fn update_mpid(&mut self, encoded: &[u64; NUM_FIELDS], book: &mut NyseOrderBook) {
+        self.mpid.update(encoded, book);
+    }
+
     pub(crate) fn build(self) -> MarketStat {
-        self.partial_stat
+        let mut market_stat = self.partial_stat;
+        market_stat.mpid_stats = self.mpid.build();
+        market_stat.auctions = self.auction.build();
+        market_stat
     }
 }
 
 #[derive(Serialize, Debug)]
 pub(crate) struct MarketStat {
+    session: SessionSchedule,
     total_volume: u64,
     pre_market_volume: u64,
     regular_market_volume: u64,
@@ -223,11 +259,20 @@ pub(crate) struct MarketStat {
     interval_execute_msg_count: Vec<u64>,
 
     lob_level_5: Vec<[u64; 21]>,
+
+    /// statistics by mpid_val of the firm id, exported to `{symbol}_mpid.json.zst`
+    #[serde(skip)]
+    pub mpid_stats: BTreeMap<u64, MpidStat>,
+    /// auctions with their imbalances, exported to `{symbol}_auction.json.zst`
+    #[serde(skip)]
+    pub auctions: Vec<Auction>,
 }
 
 impl MarketStat {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(session: SessionSchedule) -> Self {
+        let n = session.interval_count();
         Self {
+            session,
             total_volume: Default::default(),
             pre_market_volume: Default::default(),
             regular_market_volume: Default::default(),
@@ -241,15 +286,17 @@ impl MarketStat {
             closing_cross_volume: Default::default(),
             events: Default::default(),
             
-            interval_volume: vec![0; T_N],
-            interval_price_volume: vec![0; T_N],
-            interval_lp_volume: vec![0; T_N],
-            interval_lp_price_volume: vec![0; T_N],
-            interval_nondisp_volume: vec![0; T_N],
-            interval_nondisp_price_volume: vec![0; T_N],
-            interval_execute_msg_count: vec![0; T_N],
+            interval_volume: vec![0; n],
+            interval_price_volume: vec![0; n],
+            interval_lp_volume: vec![0; n],
+            interval_lp_price_volume: vec![0; n],
+            interval_nondisp_volume: vec![0; n],
+            interval_nondisp_price_volume: vec![0; n],
+            interval_execute_msg_count: vec![0; n],
             
-            lob_level_5: vec![[0; 21]; T_N],
+            lob_level_5: vec![[0; 21]; n],
+            mpid_stats: Default::default(),
+            auctions: Default::default(),
         }
     }
 }
@@ -1,3 +1,4 @@
+from datetime import date
 from mmm.nasdaq import load_actions, preprocess, compile_trajectory_with_volume_level, preprocess_meta, load_json_zst
 from mmm.util import get_subsequents
 from pprint import pprint
@@ -9,8 +10,8 @@ if __name__ == "__main__":
     #print(load_json_zst("~/data/nasdaq-itch/prep/S101521-v50/market_stats.json.zst")["AAPL"])
 
     actions = load_actions("./data/S010322-v50/AAPL.bin.zst")
-    traj = compile_trajectory_with_volume_level(actions, [0,actions.shape[0]-2, actions.shape[0]-1], [0,0,0], 10, False)
-    traj_full = compile_trajectory_with_volume_level(actions, [0,actions.shape[0]-2, actions.shape[0]-1], [0,0,0], 10, True)
+    traj = compile_trajectory_with_volume_level(actions, [0,actions.shape[0]-2, actions.shape[0]-1], [0,0,0], 10, False, date=date(2022, 1, 3))
+    traj_full = compile_trajectory_with_volume_level(actions, [0,actions.shape[0]-2, actions.shape[0]-1], [0,0,0], 10, True, date=date(2022, 1, 3))
     print(traj)
     print("-------------------------------")
     print(traj_full)
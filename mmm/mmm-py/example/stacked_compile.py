@@ -1,3 +1,4 @@
+from datetime import date
 from mmm.nasdaq import load_actions, preprocess, compile_trajectory_with_volume_level
 
 
@@ -11,7 +12,7 @@ if __name__ == "__main__":
     trajs = []
     for i in range(stack_size-1, -1, -1):
         print(i)
-        trajs.append(compile_trajectory_with_volume_level(actions, [0,10,100,10000,1000000], 10000 + stack_interval * i, 5))
+        trajs.append(compile_trajectory_with_volume_level(actions, [0,10,100,10000,1000000], 10000 + stack_interval * i, 5, False, date=date(2021, 10, 15)))
 
     stacked_trajectory = []
     for i in range(len(trajs[0])):
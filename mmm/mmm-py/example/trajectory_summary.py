@@ -1,9 +1,10 @@
+from datetime import date
 from mmm.nasdaq import load_actions, create_trajectory_summaries
 
 
 if __name__ == "__main__":
     actions = load_actions("~/data/nasdaq-itch/prep/S101521-v50/AAPL.bin.zst")
-    summaries = create_trajectory_summaries(actions)
+    summaries = create_trajectory_summaries(actions, date=date(2021, 10, 15))
     print(summaries[0].reference)
     print(summaries[0].trajectory)
     print(summaries[0].timestamps)
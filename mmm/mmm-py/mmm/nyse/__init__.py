@@ -1,15 +1,17 @@
+from datetime import date as Date
 from typing import Iterable, List, Union
 import zstandard
 from pathlib import Path
 import numpy as np
 import json
-from mmm.nyse_py import TimeBasedQueueReplay, TimeBasedVolumeReplay, create_trajectory_summaries
+from mmm.nyse_py import TimeBasedQueueReplay, TimeBasedVolumeReplay
 
 
 NUM_FEATURES = 9
 
+# `date` is the trading day of `actions`, whose times count from its midnight in New York, and is given by keyword.
 def wrapper(func):
-    def wrapped(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  depth: int,is_inclusive: bool):
+    def wrapped(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  depth: int,is_inclusive: bool, *, date: Date):
         assert actions.shape[1] == NUM_FEATURES
         assert np.all(np.array(latencies_ns) >= 0)
         assert depth > 0
@@ -24,27 +26,31 @@ def wrapper(func):
             latencies_ns = np.array(latencies_ns)
         else:
             latencies_ns = np.repeat(latencies_ns, len(target_indices))
-        res = func(actions, target_indices, latencies_ns, depth, False, is_inclusive)
+        res = func(actions, target_indices, latencies_ns, depth, False, is_inclusive, date.isoformat())
         assert len(res) == len(target_indices)
         return res
     return wrapped
 
 
-def compile_trajectory_with_volume_level(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  level: int, is_inclusive = False):
+def compile_trajectory_with_volume_level(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  level: int, is_inclusive = False, *, date: Date):
     from mmm.nyse_py import compile_trajectory_with_volume_level
-    return wrapper(compile_trajectory_with_volume_level)(actions, target_indices, latencies_ns, level, is_inclusive)
+    return wrapper(compile_trajectory_with_volume_level)(actions, target_indices, latencies_ns, level, is_inclusive, date=date)
 
-def compile_trajectory_with_volume_spread(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  spread: int, is_inclusive = False):
+def compile_trajectory_with_volume_spread(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  spread: int, is_inclusive = False, *, date: Date):
     from mmm.nyse_py import compile_trajectory_with_volume_spread
-    return wrapper(compile_trajectory_with_volume_spread)(actions, target_indices, latencies_ns, spread, is_inclusive)
+    return wrapper(compile_trajectory_with_volume_spread)(actions, target_indices, latencies_ns, spread, is_inclusive, date=date)
 
-def compile_trajectory_with_queue_level(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  level: int, is_inclusive = False):
+def compile_trajectory_with_queue_level(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  level: int, is_inclusive = False, *, date: Date):
     from mmm.nyse_py import compile_trajectory_with_queue_level
-    return wrapper(compile_trajectory_with_queue_level)(actions, target_indices, latencies_ns, level, is_inclusive)
+    return wrapper(compile_trajectory_with_queue_level)(actions, target_indices, latencies_ns, level, is_inclusive, date=date)
 
-def compile_trajectory_with_queue_spread(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  spread: int, is_inclusive = False):
+def compile_trajectory_with_queue_spread(actions: np.ndarray, target_indices: List[int],  latencies_ns: Union[List[int], int],  spread: int, is_inclusive = False, *, date: Date):
     from mmm.nyse_py import compile_trajectory_with_queue_spread
-    return wrapper(compile_trajectory_with_queue_spread)(actions, target_indices, latencies_ns, spread, is_inclusive)
+    return wrapper(compile_trajectory_with_queue_spread)(actions, target_indices, latencies_ns, spread, is_inclusive, date=date)
+
+def create_trajectory_summaries(actions: np.ndarray, *, date: Date):
+    from mmm.nyse_py import create_trajectory_summaries
+    return create_trajectory_summaries(actions, date.isoformat())
 
 # def preprocess(source_file: Path, out_dir: Path):
 #     from mmm.nyse_py import preprocess
@@ -1,5 +1,13 @@
 from typing import Iterable, List, Union
 import numpy as np
+from mmm.util_py import (
+    tick_upper,
+    tick_lower,
+    is_valid_tick,
+    tick_upper_array,
+    tick_lower_array,
+    is_valid_tick_array,
+)
 
 
 def get_subsequents(actions: np.ndarray, seqs: Union[int, List[int]]):
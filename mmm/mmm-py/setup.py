@@ -71,6 +71,7 @@ setup(
     rust_extensions=[
         make_rust_extension("mmm.nasdaq_py", debug=debug),
         make_rust_extension("mmm.nyse_py", debug=debug),
+        make_rust_extension("mmm.util_py", debug=debug),
     ],
     install_requires=install_requires,
     include_package_data=True,
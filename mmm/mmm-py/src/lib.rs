@@ -1,3 +1,13 @@
 mod nasdaq_py;
 mod nyse_py;
 mod summary;
+mod util;
+
+use chrono::NaiveDate;
+use pyo3::{exceptions::PyValueError, PyResult};
+
+/// Trading day of the encoded actions given from Python, e.g. "2021-10-04".
+pub(crate) fn parse_date(date: &str) -> PyResult<NaiveDate> {
+    date.parse()
+        .map_err(|_| PyValueError::new_err(format!("invalid date {:?}", date)))
+}
@@ -6,12 +6,18 @@ use std::{
 
 use mmm_nyse::{
     book::{Message, NyseOrderBook},
-    data::{load, process_file, NUM_FIELDS},
+    data::{file_date, load, process_file, NUM_FIELDS},
     replay::OrderbookDepth,
 };
-use numpy::{PyArray1, PyReadonlyArray2, ToPyArray};
-use pyo3::{prelude::pymodule, pyclass, pymethods, types::PyModule, PyResult, Python};
+use chrono::{Duration, NaiveDate};
+use mmm_us::util::Time;
+use numpy::{PyArray1, PyArray2, PyReadonlyArray2, ToPyArray};
+use pyo3::{
+    exceptions::PyValueError, prelude::pymodule, pyclass, pymethods, types::PyModule, PyResult,
+    Python,
+};
 
+use crate::parse_date;
 use crate::summary::TrajectorySummary;
 
 type Trajectory<'py, T> = Vec<(
@@ -28,26 +34,26 @@ struct TimeBasedQueueReplay(mmm_nyse::replay::TimeBasedQueueReplay);
 #[pymethods]
 impl TimeBasedQueueReplay {
     #[new]
-    fn new(path: &str, level: usize) -> Self {
+    fn new(path: &str, level: usize) -> PyResult<Self> {
         Self::by_level(path, level)
     }
     #[staticmethod]
-    fn by_level(path: &str, level: usize) -> Self {
-        let path = PathBuf::from(path);
-        let msgs = VecDeque::from_iter(load(&path, NUM_FIELDS));
-        Self(mmm_nyse::replay::TimeBasedQueueReplay::new(
+    fn by_level(path: &str, level: usize) -> PyResult<Self> {
+        let (msgs, date) = load_actions(path)?;
+        Ok(Self(mmm_nyse::replay::TimeBasedQueueReplay::new(
             msgs,
+            date,
             OrderbookDepth::Level(level),
-        ))
+        )))
     }
     #[staticmethod]
-    fn by_spread(path: &str, spread: u64) -> Self {
-        let path = PathBuf::from(path);
-        let msgs = VecDeque::from_iter(load(&path, NUM_FIELDS));
-        Self(mmm_nyse::replay::TimeBasedQueueReplay::new(
+    fn by_spread(path: &str, spread: u64) -> PyResult<Self> {
+        let (msgs, date) = load_actions(path)?;
+        Ok(Self(mmm_nyse::replay::TimeBasedQueueReplay::new(
             msgs,
+            date,
             OrderbookDepth::Spread(spread),
-        ))
+        )))
     }
     fn step(&mut self) -> Option<mmm_nyse::replay::QueueResult> {
         self.0.step()
@@ -60,41 +66,49 @@ struct TimeBasedVolumeReplay(mmm_nyse::replay::TimeBasedVolumeReplay);
 #[pymethods]
 impl TimeBasedVolumeReplay {
     #[new]
-    fn new(path: &str, level: usize) -> Self {
+    fn new(path: &str, level: usize) -> PyResult<Self> {
         Self::by_level(path, level)
     }
     #[staticmethod]
-    fn by_level(path: &str, level: usize) -> Self {
-        let path = PathBuf::from(path);
-        let msgs = VecDeque::from_iter(load(&path, NUM_FIELDS));
-        Self(mmm_nyse::replay::TimeBasedVolumeReplay::new(
+    fn by_level(path: &str, level: usize) -> PyResult<Self> {
+        let (msgs, date) = load_actions(path)?;
+        Ok(Self(mmm_nyse::replay::TimeBasedVolumeReplay::new(
             msgs,
+            date,
             OrderbookDepth::Level(level),
-        ))
+        )))
     }
     #[staticmethod]
-    fn by_spread(path: &str, spread: u64) -> Self {
-        let path = PathBuf::from(path);
-        let msgs = VecDeque::from_iter(load(&path, NUM_FIELDS));
-        Self(mmm_nyse::replay::TimeBasedVolumeReplay::new(
+    fn by_spread(path: &str, spread: u64) -> PyResult<Self> {
+        let (msgs, date) = load_actions(path)?;
+        Ok(Self(mmm_nyse::replay::TimeBasedVolumeReplay::new(
             msgs,
+            date,
             OrderbookDepth::Spread(spread),
-        ))
+        )))
     }
     fn step(&mut self) -> Option<mmm_nyse::replay::VolumeResult> {
         self.0.step()
     }
 }
 
-fn compile_trajectory<'py, F, T>(
-    py: Python<'py>,
-    encoded_actions: PyReadonlyArray2<u64>,
+// preprocessed actions of `path` and the trading day of the file
+fn load_actions(path: &str) -> PyResult<(VecDeque<Vec<u64>>, NaiveDate)> {
+    let path = PathBuf::from(path);
+    let date = file_date(&path)
+        .ok_or_else(|| PyValueError::new_err(format!("no trading day in {:?}", path)))?;
+    Ok((VecDeque::from_iter(load(&path, NUM_FIELDS)), date))
+}
+
+fn compile_trajectory<F, T>(
+    encoded_actions: &PyArray2<u64>,
+    date: NaiveDate,
     indicies: Vec<u64>,
     latencies: Vec<u64>,
     func: F,
     with_validation: bool,
     is_inclusive: bool,
-) -> Vec<(usize, &'py PyArray1<u64>, u64, T)>
+) -> Vec<(usize, &PyArray1<u64>, u64, T)>
 where
     F: Fn(&mut NyseOrderBook) -> T,
 {
@@ -102,12 +116,15 @@ where
         return Vec::new();
     }
 
+    let py = encoded_actions.py();
+    let encoded_actions = encoded_actions.readonly();
     let encoded_actions = encoded_actions.as_array();
 
+    let midnight = Time::from_local_nanos(date, 0);
     let actions = encoded_actions
         .rows()
         .into_iter()
-        .map(|row| Message::from(row.as_slice().unwrap()))
+        .map(|row| Message::decode_since(row.as_slice().unwrap(), midnight))
         .collect::<Vec<_>>();
 
     let indicies = indicies.into_iter().map(|v| v as usize).collect::<Vec<_>>();
@@ -115,11 +132,11 @@ where
         .clone()
         .into_iter()
         .zip(latencies.clone())
-        .map(|(index, latency)| actions[index].time - latency)
+        .map(|(index, latency)| actions[index].time - Duration::nanoseconds(latency as i64))
         .collect::<Vec<_>>();
 
     let mut adj_idxs = VecDeque::new();
-    let cmp_fn = if is_inclusive { u64::le } else { u64::lt };
+    let cmp_fn = if is_inclusive { Time::le } else { Time::lt };
     // let inc = if is_inclusive { |x:usize| x } else {|x:usize| x+1};
 
     for (index, time) in indicies.clone().into_iter().zip(times.clone()) {
@@ -180,7 +197,7 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     // }
 
     #[pyfn(m)]
-    fn preprocess_meta(path_list: Vec<&str>, out_dir: &str) {
+    fn preprocess_meta(path_list: Vec<&str>, out_dir: &str) -> PyResult<()> {
         process_file(
             path_list
                 .into_iter()
@@ -188,11 +205,12 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 .collect(),
             PathBuf::from_str(out_dir).unwrap(),
             true,
-        );
+        )
+        .map_err(|e| PyValueError::new_err(e.to_string()))
     }
 
     #[pyfn(m)]
-    fn preprocess(path_list: Vec<&str>, out_dir: &str) {
+    fn preprocess(path_list: Vec<&str>, out_dir: &str) -> PyResult<()> {
         process_file(
             path_list
                 .into_iter()
@@ -200,28 +218,33 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 .collect(),
             PathBuf::from_str(out_dir).unwrap(),
             false,
-        );
+        )
+        .map_err(|e| PyValueError::new_err(e.to_string()))
     }
     #[pyfn(m)]
     fn create_trajectory_summaries(
         encoded_actions: PyReadonlyArray2<u64>,
+        date: &str,
     ) -> PyResult<Vec<TrajectorySummary>> {
-        Ok(crate::summary::create_trajectory_summaries(encoded_actions))
+        Ok(crate::summary::create_trajectory_summaries(
+            encoded_actions,
+            parse_date(date)?,
+        ))
     }
 
     #[pyfn(m)]
     fn compile_trajectory_with_volume_level<'py>(
-        py: Python<'py>,
-        encoded_actions: PyReadonlyArray2<u64>,
+        encoded_actions: &'py PyArray2<u64>,
         indicies: Vec<u64>,
         latencies: Vec<u64>,
         level: usize,
         with_validation: bool,
         is_inclusive: bool,
+        date: &str,
     ) -> PyResult<Trajectory<'py, u64>> {
         Ok(compile_trajectory(
-            py,
             encoded_actions,
+            parse_date(date)?,
             indicies,
             latencies,
             |book: &mut NyseOrderBook| book.level_summary(level),
@@ -232,17 +255,17 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
 
     #[pyfn(m)]
     fn compile_trajectory_with_volume_spread<'py>(
-        py: Python<'py>,
-        encoded_actions: PyReadonlyArray2<u64>,
+        encoded_actions: &'py PyArray2<u64>,
         indicies: Vec<u64>,
         latencies: Vec<u64>,
         spread: u64,
         with_validation: bool,
         is_inclusive: bool,
+        date: &str,
     ) -> PyResult<Trajectory<'py, u64>> {
         Ok(compile_trajectory(
-            py,
             encoded_actions,
+            parse_date(date)?,
             indicies,
             latencies,
             |book: &mut NyseOrderBook| book.spread_summary(spread),
@@ -253,17 +276,17 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
 
     #[pyfn(m)]
     fn compile_trajectory_with_queue_level<'py>(
-        py: Python<'py>,
-        encoded_actions: PyReadonlyArray2<u64>,
+        encoded_actions: &'py PyArray2<u64>,
         indicies: Vec<u64>,
         latencies: Vec<u64>,
         level: usize,
         with_validation: bool,
         is_inclusive: bool,
+        date: &str,
     ) -> PyResult<Trajectory<'py, Vec<(u64, u64, u64)>>> {
         Ok(compile_trajectory(
-            py,
             encoded_actions,
+            parse_date(date)?,
             indicies,
             latencies,
             |book: &mut NyseOrderBook| book.level_snapshot(level),
@@ -274,17 +297,17 @@ fn nyse_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
 
     #[pyfn(m)]
     fn compile_trajectory_with_queue_spread<'py>(
-        py: Python<'py>,
-        encoded_actions: PyReadonlyArray2<u64>,
+        encoded_actions: &'py PyArray2<u64>,
         indicies: Vec<u64>,
         latencies: Vec<u64>,
         spread: u64,
         with_validation: bool,
         is_inclusive: bool,
+        date: &str,
     ) -> PyResult<Trajectory<'py, Vec<(u64, u64, u64)>>> {
         Ok(compile_trajectory(
-            py,
             encoded_actions,
+            parse_date(date)?,
             indicies,
             latencies,
             |book: &mut NyseOrderBook| book.spread_snapshot(spread),
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use chrono::NaiveDate;
 use mmm_nasdaq::data::OrderStatus;
 use mmm_us::price::PriceBasis;
+use mmm_us::util::Time;
 use mmm_core::collections::Side;
 
 use mmm_nasdaq::book::{Body, Message};
@@ -88,21 +90,25 @@ impl TrajectorySummary {
     }
 }
 
+/// Summaries of the orders in the encoded actions of `date`.
 pub fn create_trajectory_summaries(
     encoded_actions: PyReadonlyArray2<u64>,
+    date: NaiveDate,
 ) -> Vec<TrajectorySummary> {
     let encoded_actions = encoded_actions.as_array();
 
+    let midnight = Time::from_local_nanos(date, 0);
     let actions = encoded_actions
         .rows()
         .into_iter()
-        .map(|row| Message::from(row.as_slice().unwrap()))
+        .map(|row| Message::decode_since(row.as_slice().unwrap(), midnight))
         .collect::<Vec<_>>();
 
     let mut status_map = HashMap::new();
     let mut summaries = HashMap::new();
-    let mut message_count = 0;
-    for message in actions {
+    for (message_count, message) in actions.into_iter().enumerate() {
+        // nanoseconds since midnight in New York, as in the rows
+        let time = message.time.epoch_nanos() - midnight.epoch_nanos();
         match message.body {
             Body::AddOrder {
                 reference,
@@ -124,7 +130,7 @@ pub fn create_trajectory_summaries(
                 .insert(
                     reference,
                     TrajectorySummary::new_with_add(
-                        message.time,
+                        time,
                         reference,
                         shares as u32,
                         price as u32,
@@ -136,7 +142,7 @@ pub fn create_trajectory_summaries(
             Body::DeleteOrder { reference } => {
                 let _ = status_map.remove(&reference).unwrap();
                 
-                summaries.get_mut(&reference).unwrap().delete(message.time)
+                summaries.get_mut(&reference).unwrap().delete(time)
             }
             Body::OrderCancelled {
                 reference,
@@ -148,7 +154,7 @@ pub fn create_trajectory_summaries(
                 summaries
                 .get_mut(&reference)
                 .unwrap()
-                .cancel(message.time, cancelled as u32)
+                .cancel(time, cancelled as u32)
             }
             Body::ReplaceOrder {
                 new_reference,
@@ -162,7 +168,7 @@ pub fn create_trajectory_summaries(
                 
                 // insert summaries
                 let new_order = summaries.get_mut(&old_reference).unwrap().replace_with(
-                    message.time,
+                    time,
                     shares as u32,
                     old_shares as u32,
                     price as u32,
@@ -191,7 +197,7 @@ pub fn create_trajectory_summaries(
                 summaries
                 .get_mut(&reference)
                 .unwrap()
-                .execute(message.time, executed as u32)
+                .execute(time, executed as u32)
             }
             Body::OrderExecutedWithPrice {
                 reference,
@@ -203,11 +209,10 @@ pub fn create_trajectory_summaries(
                 summaries
                 .get_mut(&reference)
                 .unwrap()
-                .execute_with_price(message.time, executed as u32)
+                .execute_with_price(time, executed as u32)
             }
              _ => {}
         }
-        message_count += 1;
     }
 
     let mut summaries = summaries.into_iter().map(|(_, s)| s).collect::<Vec<_>>();
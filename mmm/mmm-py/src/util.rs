@@ -1,16 +1,68 @@
+use mmm_core::tick::krx::KrxTable;
+use numpy::{PyArray1, PyReadonlyArray1};
 use pyo3::{
     prelude::{pyfunction, pymodule},
     types::PyModule,
     wrap_pyfunction, PyResult, Python,
 };
 
-#[pyfunction]
-fn tick_upper(price: i32, is_kosdaq: bool) -> i32 {
-    todo!()
+/// The lowest valid price above `price`.
+#[pyfunction(unified = "true")]
+fn tick_upper(price: i64, is_kosdaq: bool, unified: bool) -> i64 {
+    KrxTable::new(is_kosdaq, unified).upper(price)
+}
+
+/// The highest valid price below `price`.
+#[pyfunction(unified = "true")]
+fn tick_lower(price: i64, is_kosdaq: bool, unified: bool) -> i64 {
+    KrxTable::new(is_kosdaq, unified).lower(price)
+}
+
+#[pyfunction(unified = "true")]
+fn is_valid_tick(price: i64, is_kosdaq: bool, unified: bool) -> bool {
+    KrxTable::new(is_kosdaq, unified).is_valid(price)
+}
+
+#[pyfunction(unified = "true")]
+fn tick_upper_array<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<i64>,
+    is_kosdaq: bool,
+    unified: bool,
+) -> &'py PyArray1<i64> {
+    let table = KrxTable::new(is_kosdaq, unified);
+    PyArray1::from_iter(py, prices.as_array().iter().map(|p| table.upper(*p)))
+}
+
+#[pyfunction(unified = "true")]
+fn tick_lower_array<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<i64>,
+    is_kosdaq: bool,
+    unified: bool,
+) -> &'py PyArray1<i64> {
+    let table = KrxTable::new(is_kosdaq, unified);
+    PyArray1::from_iter(py, prices.as_array().iter().map(|p| table.lower(*p)))
+}
+
+#[pyfunction(unified = "true")]
+fn is_valid_tick_array<'py>(
+    py: Python<'py>,
+    prices: PyReadonlyArray1<i64>,
+    is_kosdaq: bool,
+    unified: bool,
+) -> &'py PyArray1<bool> {
+    let table = KrxTable::new(is_kosdaq, unified);
+    PyArray1::from_iter(py, prices.as_array().iter().map(|p| table.is_valid(*p)))
 }
 
 #[pymodule]
-fn util(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+fn util_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_wrapped(wrap_pyfunction!(tick_upper))?;
+    m.add_wrapped(wrap_pyfunction!(tick_lower))?;
+    m.add_wrapped(wrap_pyfunction!(is_valid_tick))?;
+    m.add_wrapped(wrap_pyfunction!(tick_upper_array))?;
+    m.add_wrapped(wrap_pyfunction!(tick_lower_array))?;
+    m.add_wrapped(wrap_pyfunction!(is_valid_tick_array))?;
     Ok(())
 }
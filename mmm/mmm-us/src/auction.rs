@@ -0,0 +1,179 @@
+//! Auction dataset, the imbalance publications of each auction of a symbol and its cross.
+//! Prices are in 1/10000 dollar and times in nanoseconds since midnight in New York,
+//! as in the preprocessed messages. A price is `None` when the venue does not publish it.
+use crate::Side;
+use serde::{Deserialize, Serialize};
+
+/// File suffix of the auctions of a symbol, next to its MarketStat.
+pub const AUCTION_FILE_SUFFIX: &str = "_auction.json.zst";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AuctionType {
+    EarlyOpening,
+    Opening,
+    /// after a halt, or an IPO
+    Reopening,
+    Closing,
+    Other,
+}
+
+/// An imbalance publication, e.g. NOII of ITCH or Imbalance of TAQ.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImbalanceSnapshot {
+    pub time_ns: u64,
+    pub paired_shares: u64,
+    pub imbalance_shares: u64,
+    pub imbalance_side: Option<Side>,
+    pub reference_price: Option<u64>,
+    /// clearing price of the auction and the continuous book
+    pub near_price: Option<u64>,
+    /// clearing price of the auction interest only
+    pub far_price: Option<u64>,
+    pub indicative_price: Option<u64>,
+    pub upper_collar: Option<u64>,
+    pub lower_collar: Option<u64>,
+}
+
+impl ImbalanceSnapshot {
+    /// The indicative match price, or the near price for venues that do not publish it.
+    pub fn indicative(&self) -> Option<u64> {
+        self.indicative_price.or(self.near_price)
+    }
+
+    /// Imbalance over paired and imbalance shares, 0 when both are 0.
+    pub fn imbalance_ratio(&self) -> f64 {
+        match self.paired_shares + self.imbalance_shares {
+            0 => 0.0,
+            total => self.imbalance_shares as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Auction {
+    pub auction_type: AuctionType,
+    pub snapshots: Vec<ImbalanceSnapshot>,
+    pub cross_ns: Option<u64>,
+    pub cross_price: Option<u64>,
+    pub cross_volume: Option<u64>,
+    /// imbalance ratio of each snapshot
+    pub imbalance_ratio: Vec<f64>,
+    /// distance of the indicative price of each snapshot to the cross price, relative to it
+    pub convergence: Vec<Option<f64>>,
+    /// distance of the last indicative price to the cross price, relative to it
+    pub final_error: Option<f64>,
+}
+
+impl Auction {
+    fn new(auction_type: AuctionType) -> Self {
+        Self {
+            auction_type,
+            snapshots: Vec::new(),
+            cross_ns: None,
+            cross_price: None,
+            cross_volume: None,
+            imbalance_ratio: Vec::new(),
+            convergence: Vec::new(),
+            final_error: None,
+        }
+    }
+
+    fn relative_error(&self, price: Option<u64>) -> Option<f64> {
+        let cross_price = self.cross_price.filter(|price| *price > 0)?;
+        Some(price?.abs_diff(cross_price) as f64 / cross_price as f64)
+    }
+}
+
+/// AuctionBuilder collects the auctions of a symbol, in the order they start.
+#[derive(Debug, Default)]
+pub struct AuctionBuilder {
+    auctions: Vec<Auction>,
+}
+
+impl AuctionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the auction of `auction_type` that has not crossed yet
+    fn pending(&mut self, auction_type: AuctionType) -> &mut Auction {
+        let pending = self
+            .auctions
+            .iter()
+            .rposition(|a| a.auction_type == auction_type && a.cross_ns.is_none());
+        match pending {
+            Some(index) => &mut self.auctions[index],
+            None => {
+                self.auctions.push(Auction::new(auction_type));
+                self.auctions.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn imbalance(&mut self, auction_type: AuctionType, snapshot: ImbalanceSnapshot) {
+        self.pending(auction_type).snapshots.push(snapshot);
+    }
+
+    /// Ends the pending auction of `auction_type` with its cross.
+    pub fn cross(&mut self, auction_type: AuctionType, time_ns: u64, price: u64, volume: u64) {
+        let auction = self.pending(auction_type);
+        auction.cross_ns = Some(time_ns);
+        auction.cross_price = Some(price);
+        auction.cross_volume = Some(volume);
+    }
+
+    pub fn build(self) -> Vec<Auction> {
+        let mut auctions = self.auctions;
+        for auction in auctions.iter_mut() {
+            auction.imbalance_ratio = auction
+                .snapshots
+                .iter()
+                .map(ImbalanceSnapshot::imbalance_ratio)
+                .collect();
+            auction.convergence = auction
+                .snapshots
+                .iter()
+                .map(|snapshot| auction.relative_error(snapshot.indicative()))
+                .collect();
+            auction.final_error = auction.convergence.last().copied().flatten();
+        }
+        auctions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imbalances_until_the_cross() {
+        let snapshot = |time_ns, paired_shares, imbalance_shares, near_price| ImbalanceSnapshot {
+            time_ns,
+            paired_shares,
+            imbalance_shares,
+            imbalance_side: Some(Side::Bid),
+            reference_price: Some(1_000_000),
+            near_price: Some(near_price),
+            far_price: None,
+            indicative_price: None,
+            upper_collar: None,
+            lower_collar: None,
+        };
+        let mut builder = AuctionBuilder::new();
+        builder.imbalance(AuctionType::Closing, snapshot(1, 300, 100, 1_010_000));
+        builder.cross(AuctionType::Opening, 2, 990_000, 500);
+        builder.imbalance(AuctionType::Closing, snapshot(3, 400, 0, 1_002_000));
+        builder.cross(AuctionType::Closing, 4, 1_000_000, 400);
+
+        let auctions = builder.build();
+        assert_eq!(auctions.len(), 2);
+        let (closing, opening) = (&auctions[0], &auctions[1]);
+        assert_eq!(opening.auction_type, AuctionType::Opening);
+        assert!(opening.snapshots.is_empty() && opening.final_error.is_none());
+
+        assert_eq!(closing.cross_volume, Some(400));
+        assert_eq!(closing.imbalance_ratio, vec![0.25, 0.0]);
+        assert_eq!(closing.convergence, vec![Some(0.01), Some(0.002)]);
+        assert_eq!(closing.final_error, Some(0.002));
+    }
+}
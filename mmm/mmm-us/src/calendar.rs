@@ -0,0 +1,284 @@
+//! Trading days and session bounds of US equity venues.
+//! Times of a session are nanoseconds since midnight (ET), as timestamps of ITCH and TAQ are.
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const fn min_to_ns(min: u64) -> u64 {
+    min * 60 * 1_000_000_000
+}
+
+/// Bounds of the sessions of a trading day, and the size of the bins statistics are kept in.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct SessionSchedule {
+    /// pre-market starts
+    pub start_ns: u64,
+    /// regular market starts
+    pub open_ns: u64,
+    /// regular market ends
+    pub close_ns: u64,
+    /// post-market ends
+    pub end_ns: u64,
+    pub interval_ns: u64,
+}
+
+impl SessionSchedule {
+    pub const fn new(start_ns: u64, open_ns: u64, close_ns: u64, end_ns: u64) -> Self {
+        Self {
+            start_ns,
+            open_ns,
+            close_ns,
+            end_ns,
+            interval_ns: 1_000_000_000,
+        }
+    }
+
+    /// 4:00 to 20:00 with the regular market from 9:30 to 16:00.
+    /// The day is kept until 20:01, since messages arrive shortly after 20:00.
+    pub const fn nasdaq() -> Self {
+        Self::new(
+            min_to_ns(4 * 60),
+            min_to_ns(9 * 60 + 30),
+            min_to_ns(16 * 60),
+            min_to_ns(20 * 60 + 1),
+        )
+    }
+
+    /// Same hours as NASDAQ.
+    pub const fn nyse() -> Self {
+        Self::nasdaq()
+    }
+
+    #[must_use]
+    pub const fn with_interval(mut self, interval_ns: u64) -> Self {
+        self.interval_ns = interval_ns;
+        self
+    }
+
+    /// The regular market closes at 13:00 and the post-market ends as much earlier.
+    #[must_use]
+    pub const fn early_close(mut self) -> Self {
+        let early = min_to_ns(13 * 60);
+        if self.close_ns > early {
+            self.end_ns -= self.close_ns - early;
+            self.close_ns = early;
+        }
+        self
+    }
+
+    /// Index of the bin of `timestamp`. Timestamps before the start are in the first bin,
+    /// and those from the end, e.g. after 17:01 on an early close, in the last one.
+    pub fn interval_loc(&self, timestamp: u64) -> usize {
+        let timestamp = timestamp.clamp(self.start_ns, self.end_ns - 1);
+        ((timestamp - self.start_ns) / self.interval_ns) as usize
+    }
+
+    // number of bins from the start to `ns`, the last one possibly partial
+    fn intervals_until(&self, ns: u64) -> usize {
+        ((ns - self.start_ns - 1) / self.interval_ns + 1) as usize
+    }
+
+    /// Total number of bins.
+    pub fn interval_count(&self) -> usize {
+        self.intervals_until(self.end_ns)
+    }
+
+    /// Index of the first bin of the regular market.
+    pub fn open_interval(&self) -> usize {
+        self.intervals_until(self.open_ns)
+    }
+
+    /// Index of the first bin of the post-market.
+    pub fn close_interval(&self) -> usize {
+        self.intervals_until(self.close_ns)
+    }
+
+    pub fn is_regular(&self, timestamp: u64) -> bool {
+        (self.open_ns..self.close_ns).contains(&timestamp)
+    }
+}
+
+/// TradingCalendar knows the holidays and early closes of US equity markets.
+/// Regular holidays and half days follow the NYSE rules,
+/// and unscheduled closures can be added.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+pub struct TradingCalendar {
+    closures: BTreeSet<NaiveDate>,
+    early_closes: BTreeSet<NaiveDate>,
+}
+
+impl TradingCalendar {
+    pub fn us_equity() -> Self {
+        Self::default()
+    }
+
+    /// Closes the market on `date`, e.g. a national day of mourning.
+    pub fn add_closure(&mut self, date: NaiveDate) {
+        self.closures.insert(date);
+    }
+
+    pub fn add_early_close(&mut self, date: NaiveDate) {
+        self.early_closes.insert(date);
+    }
+
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.closures.contains(&date) || us_holiday(date)
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.is_holiday(date)
+    }
+
+    pub fn is_early_close(&self, date: NaiveDate) -> bool {
+        self.is_trading_day(date) && (self.early_closes.contains(&date) || us_early_close(date))
+    }
+
+    pub fn next_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date + Duration::days(1);
+        while !self.is_trading_day(date) {
+            date += Duration::days(1);
+        }
+        date
+    }
+
+    /// Sessions of `date` at a venue, `None` when the market is closed.
+    pub fn schedule(&self, date: NaiveDate, venue: SessionSchedule) -> Option<SessionSchedule> {
+        match (self.is_trading_day(date), self.is_early_close(date)) {
+            (false, _) => None,
+            (true, false) => Some(venue),
+            (true, true) => Some(venue.early_close()),
+        }
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+// `n`th `weekday` of a month, counted from the end when `n` is negative
+pub(crate) fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: i64) -> NaiveDate {
+    if n > 0 {
+        let first = ymd(year, month, 1);
+        let offset = (7 + weekday.num_days_from_monday() as i64
+            - first.weekday().num_days_from_monday() as i64)
+            % 7;
+        first + Duration::days(offset + 7 * (n - 1))
+    } else {
+        let next = match month {
+            12 => ymd(year + 1, 1, 1),
+            _ => ymd(year, month + 1, 1),
+        };
+        let last = next - Duration::days(1);
+        let offset = (7 + last.weekday().num_days_from_monday() as i64
+            - weekday.num_days_from_monday() as i64)
+            % 7;
+        last - Duration::days(offset + 7 * (-n - 1))
+    }
+}
+
+// holidays on a Sunday are observed the next Monday, and on a Saturday the Friday before
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+// Gregorian Easter Sunday, by the anonymous algorithm
+fn easter(year: i32) -> NaiveDate {
+    let (a, b, c) = (year % 19, year / 100, year % 100);
+    let (d, e) = (b / 4, b % 4);
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let (i, k) = (c / 4, c % 4);
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    ymd(year, month as u32, day as u32)
+}
+
+fn us_holiday(date: NaiveDate) -> bool {
+    let year = date.year();
+    // New Year's Day on a Saturday is not observed in the year before.
+    let new_year = ymd(year, 1, 1);
+    let mut holidays = vec![
+        match new_year.weekday() {
+            Weekday::Sun => new_year + Duration::days(1),
+            _ => new_year,
+        },
+        nth_weekday(year, 2, Weekday::Mon, 3),
+        easter(year) - Duration::days(2),
+        nth_weekday(year, 5, Weekday::Mon, -1),
+        observed(ymd(year, 7, 4)),
+        nth_weekday(year, 9, Weekday::Mon, 1),
+        nth_weekday(year, 11, Weekday::Thu, 4),
+        observed(ymd(year, 12, 25)),
+    ];
+    if year >= 1998 {
+        holidays.push(nth_weekday(year, 1, Weekday::Mon, 3));
+    }
+    if year >= 2022 {
+        holidays.push(observed(ymd(year, 6, 19)));
+    }
+    holidays.contains(&date)
+}
+
+// the day before Independence Day and Christmas, and the day after Thanksgiving
+fn us_early_close(date: NaiveDate) -> bool {
+    let year = date.year();
+    let weekday = date.weekday();
+    let eve = (date == ymd(year, 7, 3) || date == ymd(year, 12, 24))
+        && !matches!(weekday, Weekday::Fri | Weekday::Sat | Weekday::Sun);
+    eve || date == nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holidays_and_early_closes() {
+        let calendar = TradingCalendar::us_equity();
+        for date in [
+            ymd(2021, 12, 31),
+            ymd(2022, 1, 3),
+            ymd(2022, 4, 15),
+            ymd(2022, 6, 20),
+            ymd(2022, 12, 26),
+            ymd(2023, 1, 16),
+            ymd(2023, 5, 29),
+            ymd(2023, 11, 23),
+        ] {
+            let trading = date == ymd(2021, 12, 31) || date == ymd(2022, 1, 3);
+            assert_eq!(calendar.is_trading_day(date), trading, "{}", date);
+        }
+        assert_eq!(
+            calendar.next_trading_day(ymd(2022, 4, 14)),
+            ymd(2022, 4, 18)
+        );
+
+        let venue = SessionSchedule::nasdaq().with_interval(60_000_000_000);
+        assert_eq!(calendar.schedule(ymd(2023, 7, 4), venue), None);
+        assert_eq!(calendar.schedule(ymd(2023, 7, 5), venue), Some(venue));
+        let early = calendar.schedule(ymd(2023, 11, 24), venue).unwrap();
+        assert!(calendar.is_early_close(ymd(2023, 7, 3)));
+        assert!(!calendar.is_early_close(ymd(2020, 7, 3)));
+        assert_eq!(early.close_ns, min_to_ns(13 * 60));
+        assert_eq!(early.end_ns, min_to_ns(17 * 60 + 1));
+        assert_eq!(
+            early.interval_loc(min_to_ns(20 * 60)),
+            early.interval_count() - 1
+        );
+
+        assert_eq!(venue.interval_count(), 16 * 60 + 1);
+        assert_eq!(venue.open_interval(), 5 * 60 + 30);
+        assert_eq!(venue.close_interval(), 12 * 60);
+        assert_eq!(venue.interval_loc(min_to_ns(9 * 60 + 30)), 5 * 60 + 30);
+        assert_eq!(venue.interval_loc(min_to_ns(3 * 60)), 0);
+        let seconds = SessionSchedule::nasdaq();
+        assert_eq!(seconds.interval_count(), (16 * 60 + 1) * 60);
+    }
+}
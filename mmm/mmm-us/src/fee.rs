@@ -0,0 +1,52 @@
+//! Add and remove liquidity tiers of NASDAQ and NYSE Arca, for `Portfolio::with_fee_model`.
+//! Fees are per share in 1/10000 of a dollar, the basis of the prices, and a rebate is negative.
+//! Tiers are by the shares traded in the month, starting from `volume` traded before the day.
+//! The rates are a simplified copy of the price lists for securities at or above $1,
+//! the venues define their tiers by a share of the consolidated volume and change them often.
+use mmm_core::collections::fee::{PerShare, Tiered};
+
+pub type FeeSchedule = Tiered<i64, PerShare<i64>>;
+
+fn per_share(maker: i64, taker: i64) -> PerShare<i64> {
+    PerShare { maker, taker }
+}
+
+/// NASDAQ, $0.0030 to remove and a rebate of $0.0020 to $0.0029 to add.
+pub fn nasdaq(volume: i64) -> FeeSchedule {
+    let tiers = vec![
+        (0, per_share(-20, 30)),
+        (25_000_000, per_share(-25, 30)),
+        (100_000_000, per_share(-27, 30)),
+        (250_000_000, per_share(-29, 30)),
+    ];
+    Tiered::with_volume(tiers, volume)
+}
+
+/// NYSE Arca, $0.0030 to remove and a rebate of $0.0020 to $0.0028 to add,
+/// with $0.0029 to remove in the top tier.
+pub fn arca(volume: i64) -> FeeSchedule {
+    let tiers = vec![
+        (0, per_share(-20, 30)),
+        (50_000_000, per_share(-25, 30)),
+        (200_000_000, per_share(-28, 29)),
+    ];
+    Tiered::with_volume(tiers, volume)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mmm_core::collections::{fee::Liquidity, pnl::Portfolio, Side};
+
+    #[test]
+    fn rebate_by_tier() {
+        let mut portfolio = Portfolio::with_fee_model(nasdaq(0));
+        portfolio.execute("A", Side::Bid, 1_000_000, 100, Liquidity::Maker);
+        assert_eq!(portfolio.fees(), -2000);
+
+        let mut portfolio = Portfolio::with_fee_model(arca(199_999_950));
+        portfolio.execute("A", Side::Ask, 1_000_000, 100, Liquidity::Maker);
+        portfolio.execute("A", Side::Bid, 1_000_000, 100, Liquidity::Taker);
+        assert_eq!(portfolio.fees(), -2500 + 2900);
+    }
+}
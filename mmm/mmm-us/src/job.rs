@@ -1,5 +1,6 @@
 use crate::price::PriceBasis;
-use crate::util::{AskBidType, MarketType, SecurityType, Time, VolumeBasis};
+use crate::security::SecurityMeta;
+use crate::util::{AskBidType, MarketType, Time, VolumeBasis};
 use mmm_core::collections::account::Account;
 use pyo3::prelude::*;
 
@@ -9,16 +10,6 @@ pub struct MarketMeta {
     market_close: Time,
 }
 
-/// Data specific to such stock class
-#[derive(Debug, Clone)]
-pub struct SecurityMeta {
-    ticker: String,
-    rount_lot_size: u64,
-    classification: SecurityType,
-    market_category: MarketType,
-    prev_closing_price: PriceBasis, //previous market(mon to fri) day closing price, this will affect
-}
-
 /// Job is defined as the entire information about handling one task
 /// This by itself should be enough to find out the current status of the job
 /// generics represent: K, L, Q =
@@ -4,14 +4,23 @@
 //! Only US specific types should be defined in this lib, so refer to mmm-core for more generic types
 mod action;
 mod job;
-mod util;
+pub mod util;
 // mod enums;
+pub mod auction;
+pub mod calendar;
+pub mod fee;
+pub mod mpid;
 pub mod price;
+pub mod security;
+pub mod symbol;
 use mmm_core::collections;
 
 pub type Side = collections::Side;
-pub type OrderBook<K, P, Q, I> = collections::book::OrderBook<K, P, Q, I>;
+pub type OrderBook<K, P, Q, I, O = ()> = collections::book::OrderBook<K, P, Q, I, O>;
+pub type BookChanges<P> = collections::observer::BookChanges<P>;
+pub type PriceLevelBook<P, Q> = collections::level::PriceLevelBook<P, Q>;
 pub type PriceTimePriority = collections::book::PriceTimePriority;
+pub type Visibility<Q> = collections::book::Visibility<Q>;
 
 pub fn encode_side(side: Side) -> u64 {
     match side {
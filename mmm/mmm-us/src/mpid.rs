@@ -0,0 +1,265 @@
+//! Statistics of the market participants of a stock, MPIDs of NASDAQ or firm ids of NYSE,
+//! from the preprocessed messages of either venue.
+//! mpid_val is the value of `mpid_map.json.zst`, and orders without one are not followed.
+use crate::calendar::SessionSchedule;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// sides as encoded by `encode_side`
+const ASK: u64 = 1;
+const BID: u64 = 2;
+
+/// The book of a venue, as much as MpidStatBuilder looks at it.
+pub trait InsideBook {
+    /// Best ask and bid.
+    fn bbo(&self) -> (Option<u64>, Option<u64>);
+    /// Shares by price of the best `level` levels of "Ask" and "Bid".
+    fn level_summary(&mut self, level: usize) -> HashMap<String, HashMap<u64, u64>>;
+}
+
+/// Statistics of an MPID in an interval, or over the day.
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct MpidInterval {
+    /// orders added with the attribution of the MPID
+    pub orders: u64,
+    /// displayed shares added with the attribution of the MPID
+    pub added: u64,
+    pub executed: u64,
+    /// shares cancelled, deleted or replaced
+    pub cancelled: u64,
+    /// nanoseconds with an order at the best bid or ask
+    pub inside_ns: u64,
+    /// how much the best price would worsen without its orders, averaged over `inside_ns`
+    pub spread_contribution: f64,
+}
+
+impl MpidInterval {
+    fn add(&mut self, other: &MpidInterval) {
+        self.orders += other.orders;
+        self.added += other.added;
+        self.executed += other.executed;
+        self.cancelled += other.cancelled;
+        self.inside_ns += other.inside_ns;
+        self.spread_contribution += other.spread_contribution;
+    }
+
+    // spread_contribution is summed weighted by time until the average is taken
+    fn average(&mut self) {
+        if self.inside_ns > 0 {
+            self.spread_contribution /= self.inside_ns as f64;
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct MpidStat {
+    pub total: MpidInterval,
+    /// cancelled over added shares
+    pub cancel_ratio: f64,
+    /// executed over added shares
+    pub fill_rate: f64,
+    /// intervals with activity, by index
+    pub intervals: BTreeMap<usize, MpidInterval>,
+}
+
+#[derive(Debug)]
+struct AttributedOrder {
+    mpid_val: u64,
+    side: u64,
+    price: u64,
+    shares: u64,
+}
+
+/// MpidStatBuilder follows the attributed orders through the encoded messages of a stock.
+#[derive(Debug)]
+pub struct MpidStatBuilder {
+    schedule: SessionSchedule,
+    orders: HashMap<u64, AttributedOrder>,
+    /// resting shares of each MPID by (side, price)
+    levels: HashMap<u64, HashMap<(u64, u64), u64>>,
+    /// MPIDs at the inside since `last_ns`, with their spread contribution
+    inside: Vec<(u64, u64)>,
+    last_ns: Option<u64>,
+    stats: BTreeMap<u64, MpidStat>,
+}
+
+impl MpidStatBuilder {
+    pub fn new(schedule: SessionSchedule) -> Self {
+        Self {
+            schedule,
+            orders: HashMap::new(),
+            levels: HashMap::new(),
+            inside: Vec::new(),
+            last_ns: None,
+            stats: BTreeMap::new(),
+        }
+    }
+
+    /// Updates with an encoded message, after `book` has handled it.
+    pub fn update<B: InsideBook>(&mut self, encoded: &[u64], book: &mut B) {
+        let timestamp = encoded[1];
+        self.accrue(timestamp);
+        let bin_ind = self.schedule.interval_loc(timestamp);
+        match encoded[0] {
+            0 if encoded[7] > 0 => {
+                let order = AttributedOrder {
+                    mpid_val: encoded[7],
+                    side: encoded[5],
+                    price: encoded[4],
+                    shares: encoded[3],
+                };
+                let interval = self.interval(order.mpid_val, bin_ind);
+                interval.orders += 1;
+                interval.added += order.shares;
+                self.insert(encoded[2], order);
+            }
+            1 => {
+                if let Some(order) = self.reduce(encoded[2], u64::MAX) {
+                    self.interval(order.0, bin_ind).cancelled += order.1;
+                }
+            }
+            2 => {
+                if let Some(order) = self.reduce(encoded[2], encoded[3]) {
+                    self.interval(order.0, bin_ind).cancelled += order.1;
+                }
+            }
+            3 => {
+                // the new order keeps the attribution of the old one
+                let old = self.orders.get(&encoded[7]).map(|o| (o.mpid_val, o.side));
+                if let Some((mpid_val, side)) = old {
+                    let (_, cancelled) = self.reduce(encoded[7], u64::MAX).unwrap();
+                    let interval = self.interval(mpid_val, bin_ind);
+                    interval.cancelled += cancelled;
+                    interval.added += encoded[3];
+                    let order = AttributedOrder {
+                        mpid_val,
+                        side,
+                        price: encoded[4],
+                        shares: encoded[3],
+                    };
+                    self.insert(encoded[2], order);
+                }
+            }
+            4 | 5 => {
+                if let Some(order) = self.reduce(encoded[2], encoded[3]) {
+                    self.interval(order.0, bin_ind).executed += order.1;
+                }
+            }
+            _ => {}
+        }
+        self.inside = self.at_inside(book);
+        self.last_ns = Some(timestamp);
+    }
+
+    fn interval(&mut self, mpid_val: u64, bin_ind: usize) -> &mut MpidInterval {
+        self.stats
+            .entry(mpid_val)
+            .or_default()
+            .intervals
+            .entry(bin_ind)
+            .or_default()
+    }
+
+    fn insert(&mut self, reference: u64, order: AttributedOrder) {
+        *self
+            .levels
+            .entry(order.mpid_val)
+            .or_default()
+            .entry((order.side, order.price))
+            .or_default() += order.shares;
+        self.orders.insert(reference, order);
+    }
+
+    // removes up to `shares` of an attributed order, returning its mpid_val and the shares removed
+    fn reduce(&mut self, reference: u64, shares: u64) -> Option<(u64, u64)> {
+        let order = self.orders.get_mut(&reference)?;
+        let shares = shares.min(order.shares);
+        order.shares -= shares;
+        let (mpid_val, key) = (order.mpid_val, (order.side, order.price));
+        if order.shares == 0 {
+            self.orders.remove(&reference);
+        }
+        let levels = self.levels.get_mut(&mpid_val).unwrap();
+        let resting = levels.get_mut(&key).unwrap();
+        *resting -= shares;
+        if *resting == 0 {
+            levels.remove(&key);
+            if levels.is_empty() {
+                self.levels.remove(&mpid_val);
+            }
+        }
+        Some((mpid_val, shares))
+    }
+
+    // MPIDs with an order at the best bid or ask. When its orders are all of the best level,
+    // the best price without them is the next level, and the distance is its contribution.
+    fn at_inside<B: InsideBook>(&self, book: &mut B) -> Vec<(u64, u64)> {
+        let (ask, bid) = book.bbo();
+        let mut summary = None;
+        let mut inside = Vec::new();
+        for (mpid_val, resting) in &self.levels {
+            let mut at_inside = false;
+            let mut contribution = 0;
+            for (side, name, best) in [(ASK, "Ask", ask), (BID, "Bid", bid)] {
+                let best = match best {
+                    Some(best) => best,
+                    None => continue,
+                };
+                let shares = match resting.get(&(side, best)) {
+                    Some(shares) => shares,
+                    None => continue,
+                };
+                at_inside = true;
+                let level = &summary.get_or_insert_with(|| book.level_summary(2))[name];
+                if level.get(&best) == Some(shares) {
+                    contribution += level
+                        .keys()
+                        .find(|price| **price != best)
+                        .map(|next| next.abs_diff(best))
+                        .unwrap_or_default();
+                }
+            }
+            if at_inside {
+                inside.push((*mpid_val, contribution));
+            }
+        }
+        inside
+    }
+
+    // time since the last message goes to the MPIDs at the inside, split at interval bounds,
+    // within the session
+    fn accrue(&mut self, timestamp: u64) {
+        let mut from = match self.last_ns {
+            Some(last_ns) if !self.inside.is_empty() => last_ns.max(self.schedule.start_ns),
+            _ => return,
+        };
+        let to = timestamp.min(self.schedule.end_ns);
+        while from < to {
+            let bin_ind = self.schedule.interval_loc(from);
+            let bin_end = self.schedule.start_ns + (bin_ind as u64 + 1) * self.schedule.interval_ns;
+            let ns = bin_end.min(to) - from;
+            for (mpid_val, contribution) in self.inside.clone() {
+                let interval = self.interval(mpid_val, bin_ind);
+                interval.inside_ns += ns;
+                interval.spread_contribution += (contribution * ns) as f64;
+            }
+            from += ns;
+        }
+    }
+
+    pub fn build(self) -> BTreeMap<u64, MpidStat> {
+        let mut stats = self.stats;
+        for stat in stats.values_mut() {
+            for interval in stat.intervals.values_mut() {
+                stat.total.add(interval);
+                interval.average();
+            }
+            stat.total.average();
+            if stat.total.added > 0 {
+                stat.cancel_ratio = stat.total.cancelled as f64 / stat.total.added as f64;
+                stat.fill_rate = stat.total.executed as f64 / stat.total.added as f64;
+            }
+        }
+        stats
+    }
+}
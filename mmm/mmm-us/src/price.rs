@@ -1,10 +1,29 @@
+use mmm_core::tick::{Direction, PriceTick};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, AddAssign, Rem, Sub, SubAssign};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PriceError {
+    #[error("invalid price {0:?}.")]
+    Invalid(String),
+    #[error("price {0:?} overflows.")]
+    Overflow(String),
+    #[error("price {0} can not be represented in basis {1}.")]
+    Inexact(PriceBasis, u64),
+}
+
 //super class that can represent anything related to price
 //does all the arithmatics in u64 which is faster and less prone to overflow
 //actual price in dollars = inner / basis
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PriceBasis {
     inner: u64, //values are all converted to u64
     basis: u64, //default as 10000
+    #[serde(default)]
+    mpv: u64, //minimum price variation in 100ths of a cent, 0 follows Rule 612 only
 }
 
 impl Default for PriceBasis {
@@ -12,6 +31,7 @@ impl Default for PriceBasis {
         PriceBasis {
             inner: 0,
             basis: 10000,
+            mpv: 0,
         }
     }
 }
@@ -20,16 +40,137 @@ impl From<u32> for PriceBasis {
     fn from(v: u32) -> Self {
         PriceBasis {
             inner: v as u64,
-            basis: 10000,
+            ..Default::default()
         }
     }
 }
 
+// rounded to the nearest basis point, parse the string instead for an exact price
 impl From<f64> for PriceBasis {
     fn from(v: f64) -> Self {
         PriceBasis {
-            inner: (v * 10000.0).floor() as u64,
-            basis: 10000,
+            inner: (v * 10000.0).round() as u64,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses a decimal string exactly, e.g. "1.0003".
+/// The basis is 10000, or finer when the price has more than 4 significant decimals.
+impl FromStr for PriceBasis {
+    type Err = PriceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PriceError::Invalid(s.to_string());
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let frac = frac.trim_end_matches('0');
+        let digits = |v: &str| v.bytes().all(|b| b.is_ascii_digit());
+        if int.is_empty() || !digits(int) || !digits(frac) || s.ends_with('.') {
+            return Err(invalid());
+        }
+        let scale = frac.len().max(4) as u32;
+        let overflow = || PriceError::Overflow(s.to_string());
+        let basis = 10u64.checked_pow(scale).ok_or_else(overflow)?;
+        let frac = format!("{:0<width$}", frac, width = scale as usize);
+        let inner = (int.parse::<u64>().map_err(|_| overflow())?)
+            .checked_mul(basis)
+            .and_then(|v| v.checked_add(frac.parse().ok()?))
+            .ok_or_else(overflow)?;
+        Ok(PriceBasis::new(inner, basis))
+    }
+}
+
+/// Prints every decimal of the basis, e.g. "1.0300" in basis 10000.
+impl fmt::Display for PriceBasis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = self.basis.ilog10();
+        if 10u64.pow(scale) != self.basis {
+            return write!(f, "{}", self.actual_price());
+        }
+        let (int, frac) = (self.inner / self.basis, self.inner % self.basis);
+        match scale {
+            0 => write!(f, "{}", int),
+            _ => write!(f, "{}.{:0width$}", int, frac, width = scale as usize),
+        }
+    }
+}
+
+// prices are equal by value, whatever their basis
+impl PartialEq for PriceBasis {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PriceBasis {}
+
+impl Ord for PriceBasis {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let lhs = self.inner as u128 * other.basis as u128;
+        lhs.cmp(&(other.inner as u128 * self.basis as u128))
+    }
+}
+
+impl PartialOrd for PriceBasis {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// arithmetics are exact in the common basis, and panic on overflow as u64 does,
+// except SubAssign, which saturates at zero so that the floor of a zero price is zero
+impl Add for PriceBasis {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(&rhs)
+            .expect("attempt to add prices with overflow")
+    }
+}
+
+impl Sub for PriceBasis {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(&rhs)
+            .expect("attempt to subtract prices with overflow")
+    }
+}
+
+impl AddAssign for PriceBasis {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = self.clone() + rhs;
+    }
+}
+
+impl SubAssign for PriceBasis {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = self.saturating_sub(&rhs);
+    }
+}
+
+impl Rem for PriceBasis {
+    type Output = Self;
+    fn rem(self, rhs: Self) -> Self {
+        let (basis, lhs, rhs) = self.common(&rhs).expect("price overflow");
+        PriceBasis {
+            inner: lhs % rhs,
+            basis,
+            mpv: self.mpv,
+        }
+    }
+}
+
+/// Ticks of Reg NMS Rule 612, $0.01 at or above $1 and $0.0001 below,
+/// or the MPV of the symbol when it is coarser.
+impl PriceTick for PriceBasis {
+    fn tickify(&self, direction: Direction) -> Self {
+        let inner = match direction {
+            Direction::Up | Direction::None => self.inner,
+            // the tick below $1.00 is sub-penny.
+            Direction::Down => self.inner.saturating_sub(1),
+        };
+        PriceBasis {
+            inner: self.tick_at(inner),
+            ..self.clone()
         }
     }
 }
@@ -39,6 +180,101 @@ impl PriceBasis {
         PriceBasis {
             inner: value,
             basis: basis,
+            mpv: 0,
+        }
+    }
+
+    /// Ticks follow `mpv`, e.g. `SymbolIndexMapping.mpv` of NYSE, as well as Rule 612.
+    pub fn with_mpv(mut self, mpv: u64) -> Self {
+        self.mpv = mpv;
+        self
+    }
+
+    pub fn mpv(&self) -> u64 {
+        self.mpv
+    }
+
+    pub fn basis(&self) -> u64 {
+        self.basis
+    }
+
+    // the least common basis of both prices, with their inners in it
+    fn common(&self, other: &Self) -> Option<(u64, u64, u64)> {
+        let gcd = gcd(self.basis, other.basis);
+        let basis = (self.basis / gcd).checked_mul(other.basis)?;
+        let lhs = self.inner.checked_mul(basis / self.basis)?;
+        let rhs = other.inner.checked_mul(basis / other.basis)?;
+        Some((basis, lhs, rhs))
+    }
+
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        let (basis, lhs, rhs) = self.common(rhs)?;
+        Some(PriceBasis {
+            inner: lhs.checked_add(rhs)?,
+            basis,
+            mpv: self.mpv,
+        })
+    }
+
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        let (basis, lhs, rhs) = self.common(rhs)?;
+        Some(PriceBasis {
+            inner: lhs.checked_sub(rhs)?,
+            basis,
+            mpv: self.mpv,
+        })
+    }
+
+    /// Subtracts in the common basis, clamped at zero. Panics when the common basis overflows.
+    pub fn saturating_sub(&self, rhs: &Self) -> Self {
+        let (basis, lhs, rhs) = self.common(rhs).expect("price overflow");
+        PriceBasis {
+            inner: lhs.saturating_sub(rhs),
+            basis,
+            mpv: self.mpv,
+        }
+    }
+
+    /// Inner of the price in `basis`, if it is exact.
+    pub fn to_basis(&self, basis: u64) -> Result<u64, PriceError> {
+        let scaled = self.inner as u128 * basis as u128;
+        match scaled % self.basis as u128 {
+            0 => u64::try_from(scaled / self.basis as u128)
+                .map_err(|_| PriceError::Overflow(self.to_string())),
+            _ => Err(PriceError::Inexact(self.clone(), basis)),
+        }
+    }
+
+    // tick of a price `inner` in the basis of self, at least 1
+    fn tick_at(&self, inner: u64) -> u64 {
+        let rule_612 = if inner >= self.basis {
+            self.basis / 100
+        } else {
+            self.basis / 10000
+        };
+        let mpv = (self.mpv as u128 * self.basis as u128 / 10000) as u64;
+        rule_612.max(mpv).max(1)
+    }
+
+    /// The nearest valid tick in `direction`, the price itself if it is valid.
+    /// `Direction::None` rounds to the nearest tick, half up.
+    pub fn to_valid_price(&self, direction: Direction) -> Self {
+        let tick = self.tick_at(self.inner);
+        let down = self.inner - self.inner % tick;
+        let up = if down == self.inner {
+            down
+        } else {
+            down + tick
+        };
+        let inner = match direction {
+            Direction::Down => down,
+            Direction::Up => up,
+            Direction::None if self.inner - down < up - self.inner => down,
+            Direction::None => up,
+        };
+        PriceBasis {
+            inner,
+            ..self.clone()
         }
     }
 
@@ -50,9 +286,87 @@ impl PriceBasis {
         self.inner as f64 / self.basis as f64
     }
 
-    // Assume basis has the form of 10^n
-    pub fn change_basis(&mut self, new_basis: u64) {
-        self.inner = self.inner * new_basis / self.basis;
+    /// Fails, leaving the price as is, when it can not be represented in `new_basis`.
+    pub fn change_basis(&mut self, new_basis: u64) -> Result<(), PriceError> {
+        self.inner = self.to_basis(new_basis)?;
         self.basis = new_basis;
+        Ok(())
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_612_ticks() {
+        let price = PriceBasis::new(10100, 10000);
+        assert!(price.valid_price());
+        assert_eq!(price.clone().ceiling_price(), PriceBasis::new(10200, 10000));
+        assert_eq!(price.floor_price(), PriceBasis::new(10000, 10000));
+        assert_eq!(
+            PriceBasis::new(0, 10000).floor_price(),
+            PriceBasis::new(0, 10000)
+        );
+
+        let dollar = PriceBasis::new(10000, 10000);
+        assert_eq!(dollar.clone().floor_price(), PriceBasis::new(9999, 10000));
+        assert_eq!(PriceBasis::new(9999, 10000).ceiling_price(), dollar);
+
+        let price = PriceBasis::new(123456, 10000);
+        assert!(!price.valid_price());
+        assert_eq!(
+            price.to_valid_price(Direction::Down),
+            PriceBasis::new(12340, 1000)
+        );
+        assert_eq!(
+            price.to_valid_price(Direction::Up),
+            PriceBasis::new(123500, 10000)
+        );
+        assert_eq!(
+            price.to_valid_price(Direction::None),
+            PriceBasis::new(1235, 100)
+        );
+        assert!(PriceBasis::new(5001, 10000).valid_price());
+
+        // $0.05 MPV.
+        let price = PriceBasis::new(123600, 10000).with_mpv(500);
+        assert!(!price.valid_price());
+        assert_eq!(price.to_valid_price(Direction::Up).inner(), 124000);
+        assert_eq!(
+            price.to_valid_price(Direction::Down).floor_price().inner(),
+            123000
+        );
+    }
+
+    #[test]
+    fn exact_parsing_and_arithmetic() {
+        let p = |s: &str| s.parse::<PriceBasis>().unwrap();
+        assert_eq!(p("1.0003").inner(), 10003);
+        assert_eq!(p("12.3").to_string(), "12.3000");
+        assert_eq!(p("0.000125").to_string(), "0.000125");
+        assert_eq!(p("0.00012500").basis(), 1000000);
+        for s in ["", ".5", "5.", "-1", "1.2.3", "1e3", "18446744073709551616"] {
+            assert!(s.parse::<PriceBasis>().is_err(), "{:?}", s);
+        }
+
+        assert_eq!(p("1.0003") + p("0.000125"), p("1.000425"));
+        assert_eq!(p("1.0003") - p("0.0003"), p("1"));
+        assert!(p("0.1").checked_sub(&p("0.2")).is_none());
+        assert!(p("1.00005") > p("1.0000") && p("0.5") < PriceBasis::new(3, 5));
+
+        let mut price = p("1.000125");
+        assert!(price.change_basis(10000).is_err());
+        assert_eq!(price.basis(), 1000000);
+        price.change_basis(100000000).unwrap();
+        assert_eq!(price.inner(), 100012500);
+        assert_eq!(PriceBasis::from(1.0003).inner(), 10003);
     }
 }
@@ -0,0 +1,177 @@
+//! Security master, the reference data of each listed security by day.
+//! Records are built by each venue from its directory messages, e.g. the ITCH StockDirectory.
+use crate::price::PriceBasis;
+use crate::util::{MarketType, SecurityType};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use thiserror::Error;
+
+/// File of the records of a day, in the output folder of a preprocessed file.
+pub const SECURITIES_FILE: &str = "securities.json.zst";
+/// File of the SecurityMaster of the days preprocessed into an output directory.
+pub const SECURITY_MASTER_FILE: &str = "security_master.json.zst";
+
+#[derive(Error, Debug)]
+pub enum SecurityError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Data specific to such stock class
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecurityMeta {
+    pub ticker: String,
+    pub round_lot_size: u64,
+    pub classification: SecurityType,
+    pub market_category: MarketType,
+    /// previous market(mon to fri) day closing price, when the venue publishes it
+    pub prev_closing_price: Option<PriceBasis>,
+    /// finer classification of the venue, e.g. `ETFIndexFundShares` of ITCH
+    pub issue_subtype: Option<String>,
+    /// exchange traded product
+    pub etp: bool,
+    pub etp_leverage_factor: u32,
+    pub inverse: bool,
+    /// LULD reference price tier, 1 or 2
+    pub luld_tier: Option<u8>,
+    /// minimum price variation in 1/10000 dollar, `None` when it follows Rule 612
+    pub price_resolution: Option<u64>,
+}
+
+/// SecurityMaster keeps the SecurityMeta of each symbol for each day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SecurityMaster {
+    days: BTreeMap<NaiveDate, BTreeMap<String, SecurityMeta>>,
+}
+
+impl SecurityMaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, date: NaiveDate, meta: SecurityMeta) -> Option<SecurityMeta> {
+        self.days
+            .entry(date)
+            .or_default()
+            .insert(meta.ticker.clone(), meta)
+    }
+
+    /// Adds the records of a day, replacing those of the same symbols.
+    pub fn extend(&mut self, date: NaiveDate, metas: impl IntoIterator<Item = SecurityMeta>) {
+        for meta in metas {
+            self.insert(date, meta);
+        }
+    }
+
+    pub fn get(&self, symbol: &str, date: NaiveDate) -> Option<&SecurityMeta> {
+        self.days.get(&date)?.get(symbol)
+    }
+
+    /// The latest record of `symbol` on or before `date`, with its date.
+    pub fn as_of(&self, symbol: &str, date: NaiveDate) -> Option<(NaiveDate, &SecurityMeta)> {
+        self.days
+            .range(..=date)
+            .rev()
+            .find_map(|(date, day)| day.get(symbol).map(|meta| (*date, meta)))
+    }
+
+    /// Records of `date`, ordered by symbol.
+    pub fn day(&self, date: NaiveDate) -> impl Iterator<Item = &SecurityMeta> {
+        self.days
+            .get(&date)
+            .into_iter()
+            .flat_map(|day| day.values())
+    }
+
+    /// Records of `symbol`, oldest first.
+    pub fn history<'a>(
+        &'a self,
+        symbol: &'a str,
+    ) -> impl Iterator<Item = (NaiveDate, &'a SecurityMeta)> {
+        self.days
+            .iter()
+            .filter_map(move |(date, day)| day.get(symbol).map(|meta| (*date, meta)))
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.days.keys().copied()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SecurityError> {
+        Ok(serde_json::from_slice(&read_zstd(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SecurityError> {
+        write_zstd(path, &serde_json::to_vec(self)?)
+    }
+}
+
+/// Records of a day as written next to the preprocessed output.
+pub fn load_securities<P: AsRef<Path>>(path: P) -> Result<Vec<SecurityMeta>, SecurityError> {
+    Ok(serde_json::from_slice(&read_zstd(path)?)?)
+}
+
+pub fn dump_securities<P: AsRef<Path>>(
+    path: P,
+    metas: &[SecurityMeta],
+) -> Result<(), SecurityError> {
+    write_zstd(path, &serde_json::to_vec(metas)?)
+}
+
+pub(crate) fn read_zstd<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, SecurityError> {
+    let mut buf = vec![];
+    File::open(path)?.read_to_end(&mut buf)?;
+    Ok(zstd::decode_all(&*buf)?)
+}
+
+pub(crate) fn write_zstd<P: AsRef<Path>>(path: P, serialized: &[u8]) -> Result<(), SecurityError> {
+    File::create(path)?.write_all(&zstd::encode_all(serialized, 0)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_by_symbol_and_date() {
+        let date = |d| NaiveDate::from_ymd_opt(2022, 3, d).unwrap();
+        let meta = |ticker: &str, round_lot_size| SecurityMeta {
+            ticker: ticker.to_string(),
+            round_lot_size,
+            classification: SecurityType::CommonStock,
+            market_category: MarketType::Nasdaq,
+            prev_closing_price: None,
+            issue_subtype: None,
+            etp: false,
+            etp_leverage_factor: 0,
+            inverse: false,
+            luld_tier: Some(1),
+            price_resolution: None,
+        };
+        let mut master = SecurityMaster::new();
+        master.extend(date(14), [meta("AAPL", 100), meta("MSFT", 100)]);
+        master.insert(date(16), meta("AAPL", 10));
+
+        assert_eq!(master.get("AAPL", date(15)), None);
+        assert_eq!(
+            master.as_of("AAPL", date(15)),
+            Some((date(14), &meta("AAPL", 100)))
+        );
+        assert_eq!(master.as_of("AAPL", date(16)).unwrap().1.round_lot_size, 10);
+        assert_eq!(master.as_of("AAPL", date(13)), None);
+        assert_eq!(master.day(date(14)).count(), 2);
+        assert_eq!(master.history("AAPL").count(), 2);
+
+        let path = std::env::temp_dir().join("mmm_security_master.json.zst");
+        master.save(&path).unwrap();
+        assert_eq!(SecurityMaster::load(&path).unwrap(), master);
+        let _ = std::fs::remove_file(path);
+    }
+}
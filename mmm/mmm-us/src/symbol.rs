@@ -0,0 +1,246 @@
+//! Symbol registry, one id for a ticker across venues and days.
+//! Tickers are encoded with AlphaNumeric, so the id of e.g. `AAPL` is the same in every registry.
+use crate::security::{read_zstd, write_zstd, SecurityError};
+use chrono::NaiveDate;
+use mmm_core::util::AlphaNumeric;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// File of the venue ids of a day, in the output folder of a preprocessed file.
+pub const SYMBOLS_FILE: &str = "symbols.json.zst";
+/// File of the SymbolRegistry of the days preprocessed into an output directory.
+pub const SYMBOL_REGISTRY_FILE: &str = "symbol_registry.json.zst";
+
+// ids of tickers AlphaNumeric can not encode, e.g. `BRK.B`, have this bit set.
+const INTERNED: u64 = 1 << 63;
+
+/// SymbolId is the compact id of a ticker.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SymbolId(u64);
+
+impl SymbolId {
+    /// Id of `ticker` by its AlphaNumeric code, `None` when it has other characters,
+    /// lowercase letters, a leading zero, or does not fit.
+    pub fn new(ticker: &str) -> Option<Self> {
+        if ticker.starts_with('0') || ticker.chars().any(|c| c.is_ascii_lowercase()) {
+            return None;
+        }
+        AlphaNumeric::new(ticker)
+            .map(|code| code.value())
+            .filter(|value| value & INTERNED == 0)
+            .map(Self)
+    }
+
+    pub const fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// The AlphaNumeric code, `None` for an interned ticker.
+    pub fn code(&self) -> Option<AlphaNumeric> {
+        (self.0 & INTERNED == 0).then(|| AlphaNumeric::from_value(self.0))
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid venue id {0:?}.")]
+pub struct VenueIdError(String);
+
+/// VenueId is how a venue refers to a security, valid for a day.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(into = "String", try_from = "String")]
+pub enum VenueId {
+    /// stock_locate of ITCH
+    Nasdaq(u16),
+    /// symbol index of the matching engine `system_id`, of TAQ
+    Nyse { system_id: u64, symbol_index: u64 },
+    /// product id of Coinbase, e.g. `BTC-USD`
+    Coinbase(String),
+}
+
+impl Display for VenueId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VenueId::Nasdaq(locate) => write!(f, "nasdaq:{}", locate),
+            VenueId::Nyse {
+                system_id,
+                symbol_index,
+            } => write!(f, "nyse:{}:{}", system_id, symbol_index),
+            VenueId::Coinbase(product) => write!(f, "coinbase:{}", product),
+        }
+    }
+}
+
+impl FromStr for VenueId {
+    type Err = VenueIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VenueIdError(s.to_string());
+        let (venue, id) = s.split_once(':').ok_or_else(invalid)?;
+        match venue {
+            "nasdaq" => Ok(VenueId::Nasdaq(id.parse().map_err(|_| invalid())?)),
+            "nyse" => {
+                let (system_id, symbol_index) = id.split_once(':').ok_or_else(invalid)?;
+                Ok(VenueId::Nyse {
+                    system_id: system_id.parse().map_err(|_| invalid())?,
+                    symbol_index: symbol_index.parse().map_err(|_| invalid())?,
+                })
+            }
+            "coinbase" => Ok(VenueId::Coinbase(id.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl From<VenueId> for String {
+    fn from(id: VenueId) -> Self {
+        id.to_string()
+    }
+}
+
+impl TryFrom<String> for VenueId {
+    type Error = VenueIdError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// SymbolRegistry interns tickers to SymbolIds and maps the venue ids of each day to them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(from = "StoredRegistry")]
+pub struct SymbolRegistry {
+    /// tickers AlphaNumeric can not encode, by their index
+    interned: Vec<String>,
+    #[serde(skip)]
+    interned_ids: HashMap<String, SymbolId>,
+    days: BTreeMap<NaiveDate, BTreeMap<VenueId, SymbolId>>,
+}
+
+#[derive(Deserialize)]
+struct StoredRegistry {
+    interned: Vec<String>,
+    days: BTreeMap<NaiveDate, BTreeMap<VenueId, SymbolId>>,
+}
+
+impl From<StoredRegistry> for SymbolRegistry {
+    fn from(stored: StoredRegistry) -> Self {
+        let interned_ids = stored
+            .interned
+            .iter()
+            .enumerate()
+            .map(|(index, ticker)| (ticker.clone(), SymbolId(INTERNED | index as u64)))
+            .collect();
+        Self {
+            interned: stored.interned,
+            interned_ids,
+            days: stored.days,
+        }
+    }
+}
+
+impl SymbolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, ticker: &str) -> SymbolId {
+        if let Some(id) = self.id(ticker) {
+            return id;
+        }
+        let id = SymbolId(INTERNED | self.interned.len() as u64);
+        self.interned.push(ticker.to_string());
+        self.interned_ids.insert(ticker.to_string(), id);
+        id
+    }
+
+    /// Id of `ticker` if it is encoded or already interned.
+    pub fn id(&self, ticker: &str) -> Option<SymbolId> {
+        SymbolId::new(ticker).or_else(|| self.interned_ids.get(ticker).copied())
+    }
+
+    pub fn ticker(&self, id: SymbolId) -> Option<String> {
+        match id.code() {
+            Some(code) => Some(code.to_string()),
+            None => self.interned.get((id.0 & !INTERNED) as usize).cloned(),
+        }
+    }
+
+    /// Maps the venue id of `ticker` on `date`.
+    pub fn map(&mut self, date: NaiveDate, venue: VenueId, ticker: &str) -> SymbolId {
+        let id = self.intern(ticker);
+        self.days.entry(date).or_default().insert(venue, id);
+        id
+    }
+
+    pub fn resolve(&self, date: NaiveDate, venue: &VenueId) -> Option<SymbolId> {
+        self.days.get(&date)?.get(venue).copied()
+    }
+
+    /// Venue ids of `date` and their SymbolIds.
+    pub fn venue_ids(&self, date: NaiveDate) -> impl Iterator<Item = (&VenueId, SymbolId)> {
+        self.days
+            .get(&date)
+            .into_iter()
+            .flat_map(|day| day.iter().map(|(venue, id)| (venue, *id)))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SecurityError> {
+        Ok(serde_json::from_slice(&read_zstd(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SecurityError> {
+        write_zstd(path, &serde_json::to_vec(self)?)
+    }
+}
+
+/// Venue ids of a day and their tickers, as written next to the preprocessed output.
+pub fn load_symbols<P: AsRef<Path>>(path: P) -> Result<BTreeMap<VenueId, String>, SecurityError> {
+    Ok(serde_json::from_slice(&read_zstd(path)?)?)
+}
+
+pub fn dump_symbols<P: AsRef<Path>>(
+    path: P,
+    symbols: &BTreeMap<VenueId, String>,
+) -> Result<(), SecurityError> {
+    write_zstd(path, &serde_json::to_vec(symbols)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tickers_and_venue_ids() {
+        let date = NaiveDate::from_ymd_opt(2022, 3, 14).unwrap();
+        let mut registry = SymbolRegistry::new();
+        let aapl = registry.map(date, VenueId::Nasdaq(13), "AAPL");
+        let wso = registry.map(
+            date,
+            VenueId::Nyse {
+                system_id: 58,
+                symbol_index: 5,
+            },
+            "WSO B",
+        );
+        registry.map(date, VenueId::Coinbase("BTC-USD".to_string()), "BTC-USD");
+
+        // encoded tickers need no registry, interned ones keep their id
+        assert_eq!(SymbolId::new("AAPL"), Some(aapl));
+        assert_eq!(SymbolId::new("WSO B"), None);
+        assert_eq!(registry.intern("WSO B"), wso);
+        assert_eq!(registry.ticker(wso).unwrap(), "WSO B");
+        assert_eq!(registry.ticker(aapl).unwrap(), "AAPL");
+        assert_eq!(registry.resolve(date, &VenueId::Nasdaq(13)), Some(aapl));
+        assert_eq!(registry.resolve(date, &VenueId::Nasdaq(14)), None);
+
+        let json = serde_json::to_string(&registry).unwrap();
+        let loaded: SymbolRegistry = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, registry);
+        assert_eq!(loaded.id("BTC-USD"), registry.id("BTC-USD"));
+        assert_eq!(loaded.venue_ids(date).count(), 3);
+    }
+}
@@ -1,8 +1,12 @@
+use crate::calendar::nth_weekday;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Utc, Weekday};
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Sub};
 pub type Side = mmm_core::collections::Side;
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SecurityType {
     ADR,
     CommonStock, //we only treat this for now
@@ -16,13 +20,26 @@ pub enum SecurityType {
     OrdinaryShares,
     PreferedStock,
     Rights,
+    Warrant,
+    ClosedEndFund,
+    BeneficialInterest,
+    Bond,
+    LimitedPartnership,
+    Other,
 }
 
-///only supports nasdaq for now
-#[derive(Debug, Clone)]
+/// Listing market of a security.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MarketType {
     Nasdaq,
     Arca,
+    Nyse,
+    NyseAmerican,
+    NyseNational,
+    NyseChicago,
+    Cboe,
+    Iex,
+    Other,
 }
 #[derive(Debug, Clone)]
 pub enum AskBidType {
@@ -30,15 +47,132 @@ pub enum AskBidType {
     Bid,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, Debug)]
+/// Time is an instant on one clock for every venue, nanoseconds since the Unix epoch (UTC).
+/// ITCH and TAQ timestamps are nanoseconds since midnight in New York, and are made a Time with
+/// the date of the file, applying the daylight saving time of that date.
+#[derive(
+    Copy, Clone, Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Default,
+)]
 pub struct Time {
     inner: u64, //time in nanoseconds
 }
 
+const NS_PER_SEC: i64 = 1_000_000_000;
+const NS_PER_HOUR: i64 = 3600 * NS_PER_SEC;
+
 impl Time {
-    pub(crate) fn get_nanoseconds(&self) -> u64 {
+    pub const fn from_epoch_nanos(nanos: u64) -> Self {
+        Self { inner: nanos }
+    }
+
+    pub const fn epoch_nanos(&self) -> u64 {
         self.inner
     }
+
+    /// `nanos` since midnight of `date` in New York.
+    /// It is the time elapsed since midnight, so on the days daylight saving time starts or ends
+    /// it differs from the wall clock after 2:00 by an hour.
+    pub fn from_local_nanos(date: NaiveDate, nanos: u64) -> Self {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let offset = if is_dst_date(date) { -4 } else { -5 };
+        let midnight = midnight.timestamp_nanos_opt().unwrap() - offset * NS_PER_HOUR;
+        Self::from_epoch_nanos(midnight as u64 + nanos)
+    }
+
+    /// Date in New York.
+    pub fn local_date(&self) -> NaiveDate {
+        self.to_local().date_naive()
+    }
+
+    /// Nanoseconds since midnight in New York, the inverse of `from_local_nanos`.
+    pub fn local_nanos(&self) -> u64 {
+        self.inner - Self::from_local_nanos(self.local_date(), 0).inner
+    }
+
+    /// Offset of New York from UTC at this instant, -4 hours under daylight saving time and -5 otherwise.
+    pub fn utc_offset(&self) -> FixedOffset {
+        let year = self.to_utc().year();
+        let (start, end) = dst_dates(year);
+        // daylight saving time starts at 2:00 EST and ends at 2:00 EDT
+        let start = Self::from_local_nanos(start, 2 * NS_PER_HOUR as u64);
+        let end = Self::from_local_nanos(end, 2 * NS_PER_HOUR as u64);
+        let hours = if (start..end).contains(self) { -4 } else { -5 };
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    pub fn to_utc(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_nanos(self.inner as i64)
+    }
+
+    /// The time in New York.
+    pub fn to_local(&self) -> DateTime<FixedOffset> {
+        self.to_utc().with_timezone(&self.utc_offset())
+    }
+}
+
+// start and end dates of daylight saving time in the US, by the rules since 1987
+fn dst_dates(year: i32) -> (NaiveDate, NaiveDate) {
+    if year >= 2007 {
+        (
+            nth_weekday(year, 3, Weekday::Sun, 2),
+            nth_weekday(year, 11, Weekday::Sun, 1),
+        )
+    } else {
+        (
+            nth_weekday(year, 4, Weekday::Sun, 1),
+            nth_weekday(year, 10, Weekday::Sun, -1),
+        )
+    }
+}
+
+// whether midnight of `date` is under daylight saving time, which changes at 2:00
+fn is_dst_date(date: NaiveDate) -> bool {
+    let (start, end) = dst_dates(date.year());
+    start < date && date <= end
+}
+
+impl From<DateTime<Utc>> for Time {
+    fn from(time: DateTime<Utc>) -> Self {
+        Self::from_epoch_nanos(time.timestamp_nanos_opt().unwrap() as u64)
+    }
+}
+
+impl From<Time> for DateTime<Utc> {
+    fn from(time: Time) -> Self {
+        time.to_utc()
+    }
+}
+
+/// RFC 3339 in New York with nanoseconds, e.g. `2022-03-14T09:30:00.000000000-04:00`.
+impl Display for Time {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_local().format("%Y-%m-%dT%H:%M:%S%.9f%:z"))
+    }
+}
+
+impl Add<Duration> for Time {
+    type Output = Time;
+
+    fn add(self, rhs: Duration) -> Self::Output {
+        let nanos = self.inner as i64 + rhs.num_nanoseconds().unwrap();
+        Self::from_epoch_nanos(u64::try_from(nanos).expect("time before the epoch"))
+    }
+}
+
+impl Sub<Duration> for Time {
+    type Output = Time;
+
+    fn sub(self, rhs: Duration) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Sub for Time {
+    type Output = Duration;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Duration::nanoseconds(self.inner as i64 - rhs.inner as i64)
+    }
 }
 
 //separate type for volume in case "lot" is used in the future
@@ -54,3 +188,119 @@ impl VolumeBasis {
         self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_york_time_across_dst() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).unwrap();
+        let open = 9 * NS_PER_HOUR as u64 + 30 * 60 * NS_PER_SEC as u64;
+        // EST in winter, EDT in summer
+        let winter = Time::from_local_nanos(date(3, 11), open);
+        let summer = Time::from_local_nanos(date(3, 14), open);
+        assert_eq!(winter.to_utc().to_rfc3339(), "2022-03-11T14:30:00+00:00");
+        assert_eq!(summer.to_utc().to_rfc3339(), "2022-03-14T13:30:00+00:00");
+        assert_eq!(summer.to_string(), "2022-03-14T09:30:00.000000000-04:00");
+        assert_eq!(summer - winter, Duration::days(3) - Duration::hours(1));
+
+        // 1:59:59 EST is followed by 3:00 EDT on 2022-03-13
+        let spring = Time::from_local_nanos(date(3, 13), 2 * NS_PER_HOUR as u64);
+        assert_eq!(spring.to_local().format("%H:%M").to_string(), "03:00");
+        assert_eq!(
+            (spring - Duration::nanoseconds(1))
+                .utc_offset()
+                .local_minus_utc(),
+            -5 * 3600
+        );
+        // 1:00 EDT happens again as 1:00 EST on 2022-11-06
+        let fall = Time::from_local_nanos(date(11, 6), 2 * NS_PER_HOUR as u64);
+        assert_eq!(
+            fall.to_local().format("%H:%M %z").to_string(),
+            "01:00 -0500"
+        );
+
+        for time in [winter, summer, spring, fall] {
+            let (date, nanos) = (time.local_date(), time.local_nanos());
+            assert_eq!(Time::from_local_nanos(date, nanos), time);
+            assert_eq!(Time::from(time.to_utc()), time);
+        }
+        assert_eq!(
+            Time::from_epoch_nanos(summer.epoch_nanos()) + Duration::hours(1),
+            summer + Duration::hours(1)
+        );
+    }
+
+    #[test]
+    fn local_nanos_on_dst_days() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2022, m, d).unwrap();
+        let hours = |h: u64| h * NS_PER_HOUR as u64;
+        let wall_clock = |time: Time| time.to_local().format("%m-%d %H:%M %z").to_string();
+
+        // the spring-forward day has 23 hours, 9:30 elapsed is 10:30 on the wall clock
+        let spring = date(3, 13);
+        let midnight = Time::from_local_nanos(spring, 0);
+        assert_eq!(
+            Time::from_local_nanos(date(3, 14), 0) - midnight,
+            Duration::hours(23)
+        );
+        let open = Time::from_local_nanos(spring, hours(9) + hours(1) / 2);
+        assert_eq!(wall_clock(open), "03-13 10:30 -0400");
+        assert_eq!(
+            (open.local_date(), open.local_nanos()),
+            (spring, hours(19) / 2)
+        );
+        assert_eq!(
+            wall_clock(Time::from_local_nanos(spring, hours(23))),
+            "03-14 00:00 -0400"
+        );
+
+        // the fall-back day has 25 hours, 9:30 elapsed is 8:30 on the wall clock
+        let fall = date(11, 6);
+        let midnight = Time::from_local_nanos(fall, 0);
+        assert_eq!(
+            Time::from_local_nanos(date(11, 7), 0) - midnight,
+            Duration::hours(25)
+        );
+        let open = Time::from_local_nanos(fall, hours(9) + hours(1) / 2);
+        assert_eq!(wall_clock(open), "11-06 08:30 -0500");
+        assert_eq!(
+            (open.local_date(), open.local_nanos()),
+            (fall, hours(19) / 2)
+        );
+        // the 25th hour is still the same day
+        let last = Time::from_local_nanos(fall, hours(24) + 1);
+        assert_eq!(wall_clock(last), "11-06 23:00 -0500");
+        assert_eq!(
+            (last.local_date(), last.local_nanos()),
+            (fall, hours(24) + 1)
+        );
+    }
+
+    #[test]
+    fn add_and_sub_durations() {
+        let date = NaiveDate::from_ymd_opt(2022, 3, 13).unwrap();
+        let before = Time::from_local_nanos(date, 2 * NS_PER_HOUR as u64 - 1);
+        // adding crosses the switch to daylight saving time, the instants are a nanosecond apart
+        let after = before + Duration::nanoseconds(1);
+        assert_eq!(
+            after.to_local().format("%H:%M %z").to_string(),
+            "03:00 -0400"
+        );
+        assert_eq!(after - before, Duration::nanoseconds(1));
+        assert_eq!(before - after, Duration::nanoseconds(-1));
+        assert_eq!(after - Duration::nanoseconds(1), before);
+        assert_eq!(after + Duration::nanoseconds(-1), before);
+        assert_eq!(
+            Time::from_epoch_nanos(0) + Duration::days(1),
+            Time::from_epoch_nanos(24 * NS_PER_HOUR as u64)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "time before the epoch")]
+    fn sub_before_the_epoch() {
+        let _ = Time::from_epoch_nanos(1) - Duration::nanoseconds(2);
+    }
+}
@@ -299,19 +299,19 @@ pub struct CrossCorrection {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Imbalance {
-    pub reference_price: f64,
+    pub reference_price: PriceBasis,
     pub paired_qty: u32,
     pub total_imbalance_qty: u32,
     pub market_imbalance_qty: u32,
     pub auction_time: u64, //In nanosec
     pub auction_type: AuctionType,
     pub imbalance_side: Option<Side>,
-    pub continous_book_clearing_price: f64,
-    pub auction_interest_clearing_price: f64,
-    pub ssr_filling_price: f64,
-    pub indicative_match_price: f64,
-    pub upper_collar: f64,
-    pub lower_collar: f64,
+    pub continous_book_clearing_price: PriceBasis,
+    pub auction_interest_clearing_price: PriceBasis,
+    pub ssr_filling_price: PriceBasis,
+    pub indicative_match_price: PriceBasis,
+    pub upper_collar: PriceBasis,
+    pub lower_collar: PriceBasis,
     pub auction_status: AuctionStatus,
     pub freeze_status: FreezeStatus,
     pub unpaired_qty: u32,
@@ -594,6 +594,13 @@ pub struct SymbolIndexMapping {
     pub unit_of_trade: u64,
 }
 
+impl SymbolIndexMapping {
+    /// `price` on the ticks of this symbol, see `PriceTick`.
+    pub fn ticked(&self, price: PriceBasis) -> PriceBasis {
+        price.with_mpv(self.mpv as u64)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SecurityStatus {
     pub security_status: SecurityStatusType,
@@ -611,9 +618,9 @@ pub struct SecurityStatus {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StockSummary {
     // symbol_seq_number: u16,
-    pub high_price: f64,
-    pub low_price: f64,
-    pub opening_price: f64,
-    pub closing_price: f64,
+    pub high_price: PriceBasis,
+    pub low_price: PriceBasis,
+    pub opening_price: PriceBasis,
+    pub closing_price: PriceBasis,
     pub total_volume: u64,
 }
@@ -28,6 +28,15 @@ impl SequenceNumber {
         }
     }
 
+    pub fn channel_id(&self) -> u8 {
+        self.channel_id
+    }
+
+    /// Sequence number of the message, or the symbol index of a SymbolIndexMapping.
+    pub fn sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
     pub fn calculate_unique_reference_number(&self) -> u64 {
         // Basic Cantor Pairing function
         let k1 = self.channel_id as u64;
@@ -204,13 +213,13 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
                     exchange_code: parse_exchange_code(record.get(5).unwrap()),
                     issue_classification: parse_issue_classifcation(record.get(6).unwrap()),
                     round_lot_size: record.get(7).unwrap().parse::<u32>().unwrap(),
-                    prev_close_price: PriceBasis::from(
-                        record.get(8).unwrap().parse::<f64>().unwrap(),
-                    ),
+                    prev_close_price: record.get(8).unwrap().parse::<PriceBasis>().unwrap(),
                     prev_close_volume: record.get(9).unwrap().parse::<u64>().unwrap(),
                     price_resolution: record.get(10).unwrap().parse::<u64>().unwrap(),
                     round_lots_accepted,
-                    mpv: (record.get(12).unwrap().parse::<f64>().unwrap() * 10000.0) as u32,
+                    mpv: (record.get(12).unwrap().parse::<PriceBasis>())
+                        .and_then(|mpv| mpv.to_basis(10000))
+                        .unwrap() as u32,
                     unit_of_trade: record.get(13).unwrap().parse::<u64>().unwrap(),
                 }),
             }
@@ -224,12 +233,8 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             body: Body::SecurityStatus(SecurityStatus {
                 security_status: encode_security_status_type(record.get(5).unwrap()),
                 halt_condition: encode_halt_condition(record.get(6).unwrap()),
-                price_1: PriceBasis::from(
-                    record.get(7).unwrap().parse::<f64>().unwrap_or_default(),
-                ),
-                price_2: PriceBasis::from(
-                    record.get(8).unwrap().parse::<f64>().unwrap_or_default(),
-                ),
+                price_1: record.get(7).unwrap().parse::<PriceBasis>().unwrap_or_default(),
+                price_2: record.get(8).unwrap().parse::<PriceBasis>().unwrap_or_default(),
                 ssr_triggering_exchange_id: parse_ssr_triggering_exchange_id(
                     record.get(9).unwrap(),
                 ),
@@ -247,7 +252,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::AddOrder(AddOrder {
                 order_id: record.get(5).unwrap().parse::<u64>().unwrap(),
-                price: PriceBasis::from(record.get(6).unwrap().parse::<f64>().unwrap()),
+                price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(7).unwrap().parse::<u32>().unwrap(),
                 side: parse_side(record.get(8).unwrap()).unwrap(),
                 firm_id: ArrayString5::from(record.get(9).unwrap().trim_end()),
@@ -261,7 +266,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::ModifyOrder(ModifyOrder {
                 order_id: record.get(5).unwrap().parse::<u64>().unwrap(),
-                price: PriceBasis::from(record.get(6).unwrap().parse::<f64>().unwrap()),
+                price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(7).unwrap().parse::<u32>().unwrap(),
                 position_change: parse_position_change(record.get(8).unwrap()),
             }),
@@ -275,7 +280,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             body: Body::ReplaceOrder(ReplaceOrder {
                 order_id: record.get(5).unwrap().parse::<u64>().unwrap(),
                 new_order_id: record.get(6).unwrap().parse::<u64>().unwrap(),
-                price: PriceBasis::from(record.get(7).unwrap().parse::<f64>().unwrap()),
+                price: record.get(7).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(8).unwrap().parse::<u32>().unwrap(),
             }),
         },
@@ -299,7 +304,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
                 body: Body::OrderExecution(OrderExecution {
                     order_id: record.get(5).unwrap().parse::<u64>().unwrap(),
                     trade_id: record.get(6).unwrap().parse::<u32>().unwrap(),
-                    price: PriceBasis::from(record.get(7).unwrap().parse::<f64>().unwrap()),
+                    price: record.get(7).unwrap().parse::<PriceBasis>().unwrap(),
                     volume: record.get(8).unwrap().parse::<u32>().unwrap(),
                     printable_flag: record.get(9).unwrap().parse::<u8>().unwrap(),
                     //trade_condition_1: encode_trade_condition_1(record.get(11).unwrap()),
@@ -317,7 +322,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::NonDisplayedTrade(NonDisplayedTrade {
                 trade_id: record.get(5).unwrap().parse::<u32>().unwrap(),
-                price: PriceBasis::from(record.get(6).unwrap().parse::<f64>().unwrap()),
+                price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(7).unwrap().parse::<u32>().unwrap(),
             }),
         },
@@ -349,7 +354,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::CrossTrade(CrossTrade {
                 cross_id: record.get(5).unwrap().parse::<u32>().unwrap(),
-                price: PriceBasis::from(record.get(6).unwrap().parse::<f64>().unwrap()),
+                price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(7).unwrap().parse::<u32>().unwrap(),
                 cross_type: parse_cross_type(record.get(8).unwrap()),
             }),
@@ -376,23 +381,27 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
                 sequence_number: SequenceNumber::new(channel_id, sequence_number),
                 source_time: Some(parse_source_time(record.get(2).unwrap())),
                 body: Body::Imbalance(Imbalance {
-                    reference_price: record.get(5).unwrap().parse::<f64>().unwrap(),
+                    reference_price: record.get(5).unwrap().parse::<PriceBasis>().unwrap(),
                     paired_qty: record.get(6).unwrap().parse::<u32>().unwrap(),
                     total_imbalance_qty: record.get(7).unwrap().parse::<u32>().unwrap(),
                     market_imbalance_qty: record.get(8).unwrap().parse::<u32>().unwrap(),
                     auction_time,
                     auction_type: parse_auction_type(record.get(10).unwrap()),
                     imbalance_side: parse_side(record.get(11).unwrap()),
-                    continous_book_clearing_price: record.get(12).unwrap().parse::<f64>().unwrap(),
+                    continous_book_clearing_price: record
+                        .get(12)
+                        .unwrap()
+                        .parse::<PriceBasis>()
+                        .unwrap(),
                     auction_interest_clearing_price: record
                         .get(13)
                         .unwrap()
-                        .parse::<f64>()
+                        .parse::<PriceBasis>()
                         .unwrap(),
-                    ssr_filling_price: record.get(14).unwrap().parse::<f64>().unwrap(),
-                    indicative_match_price: record.get(15).unwrap().parse::<f64>().unwrap(),
-                    upper_collar: record.get(16).unwrap().parse::<f64>().unwrap(),
-                    lower_collar: record.get(17).unwrap().parse::<f64>().unwrap(),
+                    ssr_filling_price: record.get(14).unwrap().parse::<PriceBasis>().unwrap(),
+                    indicative_match_price: record.get(15).unwrap().parse::<PriceBasis>().unwrap(),
+                    upper_collar: record.get(16).unwrap().parse::<PriceBasis>().unwrap(),
+                    lower_collar: record.get(17).unwrap().parse::<PriceBasis>().unwrap(),
                     auction_status: num::FromPrimitive::from_u8(
                         record.get(18).unwrap().parse::<u8>().unwrap(),
                     )
@@ -414,7 +423,7 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::AddOrderRefresh(AddOrderRefresh {
                 order_id: record.get(5).unwrap().parse::<u16>().unwrap(),
-                price: PriceBasis::from(record.get(6).unwrap().parse::<f64>().unwrap()),
+                price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
                 volume: record.get(7).unwrap().parse::<u32>().unwrap(),
                 side: parse_side(record.get(8).unwrap()).unwrap(),
                 firm_id: ArrayString5::from_str_truncate(record.get(9).unwrap()),
@@ -427,10 +436,10 @@ pub fn parse_message(record: &mut StringRecord, channel_id: u8) -> csv::Result<M
             sequence_number: SequenceNumber::new(channel_id, sequence_number),
             source_time: Some(parse_source_time(record.get(2).unwrap())),
             body: Body::StockSummary(StockSummary {
-                high_price: record.get(4).unwrap().parse::<f64>().unwrap(),
-                low_price: record.get(5).unwrap().parse::<f64>().unwrap(),
-                opening_price: record.get(6).unwrap().parse::<f64>().unwrap(),
-                closing_price: record.get(7).unwrap().parse::<f64>().unwrap(),
+                high_price: record.get(4).unwrap().parse::<PriceBasis>().unwrap(),
+                low_price: record.get(5).unwrap().parse::<PriceBasis>().unwrap(),
+                opening_price: record.get(6).unwrap().parse::<PriceBasis>().unwrap(),
+                closing_price: record.get(7).unwrap().parse::<PriceBasis>().unwrap(),
                 total_volume: record.get(8).unwrap().parse::<u64>().unwrap(),
             }),
         },